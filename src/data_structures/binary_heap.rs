@@ -0,0 +1,252 @@
+/// A position independent reference to an element of a `DaryHeap`,
+/// returned by `push` and valid until the element is popped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle(usize);
+
+/// A min-heap where every node has D children, supporting:
+/// 1. push an element, returning a handle to it
+/// 2. peek at or pop the smallest element
+/// 3. given a handle, decrease or change the element it refers to
+///
+/// all in O(log_D n), except peek which is O(1). Changing elements in
+/// place is what `std::collections::BinaryHeap` can't do, and what
+/// Dijkstra's algorithm needs to lower the distance of a queued vertex
+/// instead of queueing it again. A wider heap is shallower, making
+/// pushes and decrease_key cheaper while pops compare more children,
+/// which pays off when decrease_key is the most frequent operation.
+pub struct DaryHeap<T: Ord, const D: usize = 2> {
+    // the heap itself, items[0] is the smallest and the children
+    // of items[k] are items[k * D + 1] ..= items[k * D + D]
+    items: Vec<(Handle, T)>,
+    // position[h] is the index in items of the element with handle h,
+    // or None once it has been popped
+    position: Vec<Option<usize>>,
+}
+
+impl<T: Ord, const D: usize> Default for DaryHeap<T, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord, const D: usize> DaryHeap<T, D> {
+    /// create an empty heap
+    pub fn new() -> Self {
+        assert!(D >= 2, "branching factor must be at least 2");
+        Self {
+            items: Vec::new(),
+            position: Vec::new(),
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// return true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// add value to the heap, returning a handle to it
+    pub fn push(&mut self, value: T) -> Handle {
+        let handle = Handle(self.position.len());
+        self.position.push(Some(self.items.len()));
+        self.items.push((handle, value));
+        self.sift_up(self.items.len() - 1);
+        handle
+    }
+
+    /// return the smallest element and its handle
+    pub fn peek(&self) -> Option<(Handle, &T)> {
+        self.items.first().map(|(handle, value)| (*handle, value))
+    }
+
+    /// remove and return the smallest element and its handle
+    pub fn pop(&mut self) -> Option<(Handle, T)> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let last = self.items.len() - 1;
+        self.swap(0, last);
+        let (handle, value) = self.items.pop().unwrap();
+        self.position[handle.0] = None;
+        if !self.items.is_empty() {
+            self.sift_down(0);
+        }
+        Some((handle, value))
+    }
+
+    /// return true if the element of handle is still in the heap
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.position[handle.0].is_some()
+    }
+
+    /// return the element of handle, if it is still in the heap
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.position[handle.0].map(|k| &self.items[k].1)
+    }
+
+    /// lower the element of handle to value, which must not be
+    /// greater than the element
+    pub fn decrease_key(&mut self, handle: Handle, value: T) {
+        let k = self.position[handle.0].expect("element is no longer in the heap");
+        assert!(
+            value <= self.items[k].1,
+            "new key is greater than current key"
+        );
+        self.items[k].1 = value;
+        self.sift_up(k);
+    }
+
+    /// replace the element of handle with value, which may be
+    /// greater or smaller than the element
+    pub fn update(&mut self, handle: Handle, value: T) {
+        let k = self.position[handle.0].expect("element is no longer in the heap");
+        self.items[k].1 = value;
+        let k = self.sift_up(k);
+        self.sift_down(k);
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.items.swap(a, b);
+        self.position[self.items[a].0 .0] = Some(a);
+        self.position[self.items[b].0 .0] = Some(b);
+    }
+
+    // move items[k] up until its parent is not greater,
+    // returning its final index
+    fn sift_up(&mut self, mut k: usize) -> usize {
+        while k > 0 {
+            let parent = (k - 1) / D;
+            if self.items[parent].1 <= self.items[k].1 {
+                break;
+            }
+            self.swap(k, parent);
+            k = parent;
+        }
+        k
+    }
+
+    // move items[k] down until none of its children is smaller
+    fn sift_down(&mut self, mut k: usize) {
+        loop {
+            let first = k * D + 1;
+            let last = (first + D).min(self.items.len());
+            if first >= last {
+                return;
+            }
+            let smallest = (first + 1..last).fold(first, |best, c| {
+                if self.items[c].1 < self.items[best].1 {
+                    c
+                } else {
+                    best
+                }
+            });
+            if self.items[k].1 <= self.items[smallest].1 {
+                return;
+            }
+            self.swap(k, smallest);
+            k = smallest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    fn check_heap_order<const D: usize>(seed: u64) {
+        let mut rng = PCG32::new_default(seed);
+        let mut heap = DaryHeap::<u32, D>::new();
+        let mut reference: Vec<u32> = Vec::new();
+        for _ in 0..2000 {
+            if rng.get_u32() % 3 == 0 {
+                reference.sort_unstable_by(|a, b| b.cmp(a));
+                assert_eq!(reference.pop(), heap.pop().map(|(_, value)| value));
+            } else {
+                let value = rng.get_u32() % 1000;
+                heap.push(value);
+                reference.push(value);
+            }
+            assert_eq!(reference.len(), heap.len());
+            assert_eq!(reference.iter().min(), heap.peek().map(|(_, value)| value));
+        }
+    }
+
+    #[test]
+    fn test_heap_order() {
+        check_heap_order::<2>(2);
+        check_heap_order::<3>(3);
+        check_heap_order::<8>(8);
+    }
+
+    #[test]
+    fn test_decrease_key() {
+        let mut heap: DaryHeap<u32> = DaryHeap::new();
+        let handles: Vec<Handle> = [50, 20, 40, 10, 30].iter().map(|&v| heap.push(v)).collect();
+        assert_eq!(Some((handles[3], &10)), heap.peek());
+
+        // 40 moves ahead of everything
+        heap.decrease_key(handles[2], 5);
+        assert_eq!(Some(&5), heap.get(handles[2]));
+        assert_eq!(Some((handles[2], 5)), heap.pop());
+        assert!(!heap.contains(handles[2]));
+        assert_eq!(Some((handles[3], 10)), heap.pop());
+
+        // 50 moves ahead of 30 but not of 20
+        heap.decrease_key(handles[0], 25);
+        assert_eq!(Some((handles[1], 20)), heap.pop());
+        assert_eq!(Some((handles[0], 25)), heap.pop());
+        assert_eq!(Some((handles[4], 30)), heap.pop());
+        assert_eq!(None, heap.pop());
+    }
+
+    #[test]
+    fn test_update() {
+        let mut heap = DaryHeap::<i32, 4>::new();
+        let handles: Vec<Handle> = (1..=10).map(|v| heap.push(v)).collect();
+        // the smallest element becomes the largest
+        heap.update(handles[0], 100);
+        heap.update(handles[9], -1);
+        let order: Vec<i32> = std::iter::from_fn(|| heap.pop().map(|(_, v)| v)).collect();
+        assert_eq!(vec![-1, 2, 3, 4, 5, 6, 7, 8, 9, 100], order);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // edges of a small directed graph as (from, to, weight)
+        let edges = [
+            (0, 1, 4),
+            (0, 2, 1),
+            (2, 1, 2),
+            (1, 3, 1),
+            (2, 3, 5),
+            (3, 4, 3),
+        ];
+        let n = 5;
+        let mut heap = DaryHeap::<(u32, usize)>::new();
+        let mut dist = vec![u32::MAX; n];
+        let mut handles = vec![None; n];
+        dist[0] = 0;
+        handles[0] = Some(heap.push((0, 0)));
+        while let Some((_, (d, u))) = heap.pop() {
+            for &(_, to, w) in edges.iter().filter(|e| e.0 == u) {
+                let candidate = d + w;
+                if candidate < dist[to] {
+                    dist[to] = candidate;
+                    match handles[to] {
+                        Some(handle) if heap.contains(handle) => {
+                            heap.decrease_key(handle, (candidate, to))
+                        }
+                        _ => handles[to] = Some(heap.push((candidate, to))),
+                    }
+                }
+            }
+        }
+        assert_eq!(vec![0, 3, 1, 4, 7], dist);
+    }
+}
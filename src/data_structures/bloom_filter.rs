@@ -0,0 +1,132 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A Bloom filter answers whether an item may have been inserted, with:
+/// 1. no false negatives, every inserted item is reported as present
+/// 2. false positives, an item that was never inserted is reported
+///    as present, at about the rate the filter was sized for
+///
+/// in O(k) for k hash functions, using m bits for any number of items.
+/// For n expected items and a false positive rate p the best sizes are
+/// m = -n ln(p) / ln(2)^2 bits and k = (m / n) ln(2) hash functions.
+/// The k bit positions of an item are h1 + i * h2 mod m for i < k, where
+/// h1 and h2 are two independent hashes, which is as good as k separate
+/// hash functions for this purpose.
+pub struct BloomFilter {
+    // bits[i / 64] >> (i % 64) & 1 is bit i
+    bits: Vec<u64>,
+    // number of bits m
+    len: usize,
+    hashes: usize,
+}
+
+impl BloomFilter {
+    /// create a filter sized for expected_items items with a false
+    /// positive rate of false_positive_rate once they are inserted
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            0.0 < false_positive_rate && false_positive_rate < 1.0,
+            "false positive rate must be in (0, 1)"
+        );
+        let n = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let len = (-n * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(1.0) as usize;
+        let hashes = (len as f64 / n * ln2).round().max(1.0) as usize;
+        Self {
+            bits: vec![0; len.div_ceil(64)],
+            len,
+            hashes,
+        }
+    }
+
+    /// return the number of bits
+    pub fn bit_len(&self) -> usize {
+        self.len
+    }
+
+    /// return the number of hash functions
+    pub fn hash_count(&self) -> usize {
+        self.hashes
+    }
+
+    // the k bit positions of item
+    fn positions<T: Hash + ?Sized>(&self, item: &T) -> impl Iterator<Item = usize> {
+        let hash = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            item.hash(&mut hasher);
+            hasher.finish()
+        };
+        // an odd step never shares a factor with a power of two,
+        // which keeps the positions apart for most m
+        let (h1, h2) = (hash(0), hash(1) | 1);
+        let len = self.len as u64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    /// add item to the filter
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        for i in self.positions(item) {
+            self.bits[i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    /// return false if item was certainly never inserted, and true
+    /// if it was inserted or is a false positive
+    pub fn maybe_contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.positions(item)
+            .all(|i| self.bits[i / 64] >> (i % 64) & 1 == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sizes() {
+        // 1% needs about 9.6 bits per item and 7 hash functions
+        let filter = BloomFilter::new(1000, 0.01);
+        assert_eq!(9586, filter.bit_len());
+        assert_eq!(7, filter.hash_count());
+        let filter = BloomFilter::new(0, 0.5);
+        assert!(filter.bit_len() >= 1);
+        assert_eq!(1, filter.hash_count());
+    }
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = BloomFilter::new(100, 0.01);
+        assert!(!filter.maybe_contains("apple"));
+        for word in ["apple", "banana", "cherry"].iter() {
+            filter.insert(*word);
+        }
+        assert!(filter.maybe_contains("apple"));
+        assert!(filter.maybe_contains("banana"));
+        assert!(filter.maybe_contains("cherry"));
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let n = 5000;
+        let target = 0.02;
+        let mut filter = BloomFilter::new(n, target);
+        for i in 0..n as u64 {
+            filter.insert(&i);
+        }
+        for i in 0..n as u64 {
+            assert!(filter.maybe_contains(&i));
+        }
+        // none of these were inserted
+        let trials = 100_000;
+        let false_positives = (n as u64..n as u64 + trials)
+            .filter(|i| filter.maybe_contains(i))
+            .count();
+        let rate = false_positives as f64 / trials as f64;
+        assert!(rate < target * 2.0, "false positive rate {}", rate);
+        assert!(rate > target / 2.0, "false positive rate {}", rate);
+    }
+}
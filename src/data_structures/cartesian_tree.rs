@@ -0,0 +1,174 @@
+/// The Cartesian tree of an array is the binary tree where
+/// 1. the root is the position of the minimum of the array
+/// 2. the left and right subtrees are the Cartesian trees of the
+///    parts of the array to the left and to the right of it
+///
+/// so it is a min-heap on the values, and an in-order traversal visits
+/// the positions 0, 1, .., n - 1 in order. The minimum of arr[l . . . r]
+/// sits at the lowest common ancestor of l and r, which turns range
+/// minimum queries into LCA queries. Of equal values the leftmost is
+/// the ancestor.
+///
+/// It is built in O(n) by adding the positions from left to right and
+/// keeping the rightmost path of the tree on a stack: a new position
+/// pops every greater value, adopts the last popped one as its left
+/// child and becomes the right child of what is left on top.
+pub struct CartesianTree {
+    root: Option<usize>,
+    parent: Vec<Option<usize>>,
+    left: Vec<Option<usize>>,
+    right: Vec<Option<usize>>,
+}
+
+impl CartesianTree {
+    /// build the tree of an array of values
+    pub fn from_vec<T: Ord>(values: &[T]) -> Self {
+        let n = values.len();
+        let mut parent = vec![None; n];
+        let mut left = vec![None; n];
+        let mut right = vec![None; n];
+        // the rightmost path, from the root down
+        let mut stack: Vec<usize> = Vec::with_capacity(n);
+        for i in 0..n {
+            let mut last = None;
+            while let Some(&top) = stack.last() {
+                if values[top] <= values[i] {
+                    break;
+                }
+                last = stack.pop();
+            }
+            if let Some(child) = last {
+                left[i] = Some(child);
+                parent[child] = Some(i);
+            }
+            if let Some(&top) = stack.last() {
+                right[top] = Some(i);
+                parent[i] = Some(top);
+            }
+            stack.push(i);
+        }
+        CartesianTree {
+            root: stack.first().copied(),
+            parent,
+            left,
+            right,
+        }
+    }
+
+    /// return the number of nodes
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// return true if the tree has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// return the position of the minimum, None for an empty array
+    pub fn root(&self) -> Option<usize> {
+        self.root
+    }
+
+    /// return the parent of every position, None for the root
+    pub fn parents(&self) -> &[Option<usize>] {
+        &self.parent
+    }
+
+    /// return the left child of every position
+    pub fn left_children(&self) -> &[Option<usize>] {
+        &self.left
+    }
+
+    /// return the right child of every position
+    pub fn right_children(&self) -> &[Option<usize>] {
+        &self.right
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    fn in_order(tree: &CartesianTree, node: Option<usize>, out: &mut Vec<usize>) {
+        if let Some(i) = node {
+            in_order(tree, tree.left_children()[i], out);
+            out.push(i);
+            in_order(tree, tree.right_children()[i], out);
+        }
+    }
+
+    fn check_tree(values: &[i32]) {
+        let tree = CartesianTree::from_vec(values);
+        assert_eq!(values.len(), tree.len());
+
+        // the in-order traversal is the array order
+        let mut order = Vec::new();
+        in_order(&tree, tree.root(), &mut order);
+        assert_eq!((0..values.len()).collect::<Vec<_>>(), order);
+
+        // every node is at most its children, and linked both ways
+        for i in 0..values.len() {
+            for child in [tree.left_children()[i], tree.right_children()[i]]
+                .iter()
+                .flatten()
+            {
+                assert!(values[i] <= values[*child]);
+                assert_eq!(Some(i), tree.parents()[*child]);
+            }
+        }
+        let roots: Vec<usize> = (0..values.len())
+            .filter(|&i| tree.parents()[i].is_none())
+            .collect();
+        assert_eq!(tree.root().into_iter().collect::<Vec<_>>(), roots);
+    }
+
+    #[test]
+    fn test_shape() {
+        let values = [9, 3, 7, 1, 8, 12, 10, 20, 15, 18, 5];
+        let tree = CartesianTree::from_vec(&values);
+        assert_eq!(Some(3), tree.root());
+        // 1 splits the array into [9, 3, 7] and [8, .., 5]
+        assert_eq!(Some(1), tree.left_children()[3]);
+        assert_eq!(Some(10), tree.right_children()[3]);
+        assert_eq!(Some(0), tree.left_children()[1]);
+        assert_eq!(Some(2), tree.right_children()[1]);
+        assert_eq!(Some(4), tree.left_children()[10]);
+        assert_eq!(None, tree.right_children()[10]);
+        assert_eq!(Some(6), tree.right_children()[4]);
+        assert_eq!(None, tree.left_children()[0]);
+        check_tree(&values);
+    }
+
+    #[test]
+    fn test_hand_picked_arrays() {
+        check_tree(&[]);
+        check_tree(&[4]);
+        // a sorted array is a path of right children
+        check_tree(&[1, 2, 3, 4, 5]);
+        let tree = CartesianTree::from_vec(&[1, 2, 3, 4, 5]);
+        assert_eq!(Some(0), tree.root());
+        assert_eq!(Some(4), tree.right_children()[3]);
+        // and a reversed one a path of left children
+        check_tree(&[5, 4, 3, 2, 1]);
+        let tree = CartesianTree::from_vec(&[5, 4, 3, 2, 1]);
+        assert_eq!(Some(4), tree.root());
+        assert_eq!(Some(0), tree.left_children()[1]);
+        // the leftmost of equal values is the ancestor
+        check_tree(&[2, 2, 2]);
+        let tree = CartesianTree::from_vec(&[2, 2, 2]);
+        assert_eq!(Some(0), tree.root());
+        assert_eq!(Some(1), tree.parents()[2]);
+    }
+
+    #[test]
+    fn test_random_arrays() {
+        let mut rng = PCG32::new_default(1123);
+        for &n in &[2usize, 10, 100, 1000] {
+            let values: Vec<i32> = (0..n).map(|_| (rng.get_u32() % 50) as i32).collect();
+            check_tree(&values);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use std::ops::{Add, AddAssign};
+use super::segment_tree_sum::Summable;
+use std::ops::{Add, AddAssign, Neg, Sub};
 
 /// Fenwick Tree / Binary Indexed Tree
 /// Consider we have an array arr[0 . . . n-1]. We would like to
@@ -16,9 +17,35 @@ impl<T: Add<Output = T> + AddAssign + Copy + Default> FenwickTree<T> {
         }
     }
 
+    /// construct a FenwickTree holding `values` in O(n)
+    pub fn from_vec(values: &[T]) -> Self {
+        let mut data = vec![T::default(); values.len() + 1];
+        data[1..].copy_from_slice(values);
+        // every node adds its partial sum into its parent,
+        // which covers a range including the node's range
+        for i in 1..data.len() {
+            let parent = i + lowbit(i);
+            if parent < data.len() {
+                let partial = data[i];
+                data[parent] += partial;
+            }
+        }
+        FenwickTree { data }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.data.len() - 1
+    }
+
+    /// return true if the tree has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// add `val` to `idx`
     pub fn add(&mut self, i: usize, val: T) {
-        assert!(i < self.data.len());
+        assert!(i < self.len(), "index out of bounds");
         let mut i = i + 1;
         while i < self.data.len() {
             self.data[i] += val;
@@ -28,8 +55,13 @@ impl<T: Add<Output = T> + AddAssign + Copy + Default> FenwickTree<T> {
 
     /// get the sum of [0, i]
     pub fn prefix_sum(&self, i: usize) -> T {
-        assert!(i < self.data.len());
-        let mut i = i + 1;
+        assert!(i < self.len(), "index out of bounds");
+        self.sum_first(i + 1)
+    }
+
+    // get the sum of the first n elements
+    fn sum_first(&self, n: usize) -> T {
+        let mut i = n;
         let mut res = T::default();
         while i > 0 {
             res += self.data[i];
@@ -39,6 +71,215 @@ impl<T: Add<Output = T> + AddAssign + Copy + Default> FenwickTree<T> {
     }
 }
 
+impl<T: Add<Output = T> + AddAssign + Sub<Output = T> + Copy + Default> FenwickTree<T> {
+    /// get the sum of [i, j]
+    pub fn range_sum(&self, i: usize, j: usize) -> T {
+        assert!(i <= j && j < self.len(), "invalid range");
+        self.sum_first(j + 1) - self.sum_first(i)
+    }
+}
+
+/// Fenwick Tree supporting range updates
+/// Consider we have an array arr[0 . . . n-1]. We would like to
+/// 1. Add x to every element of arr[l . . . r].
+/// 2. Compute the sum of arr[l . . . r].
+///
+/// Both take O(log n), like a lazy segment tree but with half the memory.
+/// Writing the array as prefix sums of a difference array d, the sum of the
+/// first k elements is k * (d[0] + .. + d[k-1]) - (0 * d[0] + .. + (k-1) * d[k-1]),
+/// so it's enough to keep one tree over d[i] and one over i * d[i].
+pub struct RangeFenwickTree<T: Summable + Sub<Output = T> + Neg<Output = T>> {
+    // tree over d[i]
+    diff: FenwickTree<T>,
+    // tree over i * d[i]
+    weighted_diff: FenwickTree<T>,
+}
+
+impl<T: Summable + Sub<Output = T> + Neg<Output = T>> RangeFenwickTree<T> {
+    /// construct a new RangeFenwickTree with given length
+    pub fn with_len(len: usize) -> Self {
+        RangeFenwickTree {
+            diff: FenwickTree::with_len(len),
+            weighted_diff: FenwickTree::with_len(len),
+        }
+    }
+
+    /// construct a RangeFenwickTree holding `values` in O(n)
+    pub fn from_vec(values: &[T]) -> Self {
+        let mut prev = T::default();
+        let mut diff = Vec::with_capacity(values.len());
+        let mut weighted_diff = Vec::with_capacity(values.len());
+        for (i, &value) in values.iter().enumerate() {
+            diff.push(value - prev);
+            weighted_diff.push((value - prev) * T::from_usize(i));
+            prev = value;
+        }
+        RangeFenwickTree {
+            diff: FenwickTree::from_vec(&diff),
+            weighted_diff: FenwickTree::from_vec(&weighted_diff),
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.diff.len()
+    }
+
+    /// return true if the tree has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// add `val` to every element in [l, r]
+    pub fn range_add(&mut self, l: usize, r: usize, val: T) {
+        assert!(l <= r && r < self.len(), "invalid range");
+        self.diff.add(l, val);
+        self.weighted_diff.add(l, val * T::from_usize(l));
+        if r + 1 < self.len() {
+            self.diff.add(r + 1, -val);
+            self.weighted_diff.add(r + 1, -val * T::from_usize(r + 1));
+        }
+    }
+
+    /// get the sum of [l, r]
+    pub fn range_sum(&self, l: usize, r: usize) -> T {
+        assert!(l <= r && r < self.len(), "invalid range");
+        self.sum_first(r + 1) - self.sum_first(l)
+    }
+
+    // get the sum of the first n elements
+    fn sum_first(&self, n: usize) -> T {
+        if n == 0 {
+            return T::default();
+        }
+        self.diff.prefix_sum(n - 1) * T::from_usize(n) - self.weighted_diff.prefix_sum(n - 1)
+    }
+}
+
+/// Two-dimensional Fenwick Tree supporting range updates
+/// Consider we have a grid arr[0 . . . rows-1][0 . . . cols-1]. We would like to
+/// 1. Add x to every cell of the rectangle arr[r1 . . . r2][c1 . . . c2].
+/// 2. Compute the sum of the rectangle arr[r1 . . . r2][c1 . . . c2].
+///
+/// Both take O(log rows * log cols). This is `RangeFenwickTree` in two
+/// dimensions: writing the grid as 2D prefix sums of a difference grid d,
+/// a rectangle add touches d at its four corners only, and with 1-indexed
+/// cells the sum of the first X rows and Y columns is the sum of
+/// d[x][y] * (X - x + 1) * (Y - y + 1) over x <= X, y <= Y. Expanding the
+/// product, it's enough to keep trees over d, x * d, y * d and x * y * d,
+/// which share one array of nodes here.
+pub struct Fenwick2DRange<T: Summable + Sub<Output = T> + Neg<Output = T>> {
+    rows: usize,
+    cols: usize,
+    // the node (i, j) of the four trees over d[x][y], x * d[x][y],
+    // y * d[x][y] and x * y * d[x][y] is data[i * (cols + 1) + j]
+    data: Vec<[T; 4]>,
+}
+
+impl<T: Summable + Sub<Output = T> + Neg<Output = T>> Fenwick2DRange<T> {
+    /// construct a rows by cols grid of zeros
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Fenwick2DRange {
+            rows,
+            cols,
+            data: vec![[T::default(); 4]; (rows + 1) * (cols + 1)],
+        }
+    }
+
+    /// return the number of rows
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// return the number of columns
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// add `delta` to every cell of the rectangle [r1, r2] x [c1, c2]
+    pub fn range_add(&mut self, r1: usize, c1: usize, r2: usize, c2: usize, delta: T) {
+        assert!(
+            r1 <= r2 && r2 < self.rows && c1 <= c2 && c2 < self.cols,
+            "invalid range"
+        );
+        self.add_diff(r1 + 1, c1 + 1, delta);
+        self.add_diff(r1 + 1, c2 + 2, -delta);
+        self.add_diff(r2 + 2, c1 + 1, -delta);
+        self.add_diff(r2 + 2, c2 + 2, delta);
+    }
+
+    // add delta to d[x][y], 1-indexed, where corners past the
+    // last row or column affect no cell and are skipped
+    fn add_diff(&mut self, x: usize, y: usize, delta: T) {
+        if x > self.rows || y > self.cols {
+            return;
+        }
+        let (fx, fy) = (T::from_usize(x), T::from_usize(y));
+        let terms = [delta, delta * fx, delta * fy, delta * fx * fy];
+        let mut i = x;
+        while i <= self.rows {
+            let mut j = y;
+            while j <= self.cols {
+                let node = &mut self.data[i * (self.cols + 1) + j];
+                for (sum, &term) in node.iter_mut().zip(terms.iter()) {
+                    *sum += term;
+                }
+                j += lowbit(j);
+            }
+            i += lowbit(i);
+        }
+    }
+
+    /// get the sum of the rectangle [r1, r2] x [c1, c2]
+    pub fn range_sum(&self, r1: usize, c1: usize, r2: usize, c2: usize) -> T {
+        assert!(
+            r1 <= r2 && r2 < self.rows && c1 <= c2 && c2 < self.cols,
+            "invalid range"
+        );
+        self.sum_first(r2 + 1, c2 + 1) - self.sum_first(r1, c2 + 1) - self.sum_first(r2 + 1, c1)
+            + self.sum_first(r1, c1)
+    }
+
+    // get the sum of the first x rows and y columns
+    fn sum_first(&self, x: usize, y: usize) -> T {
+        let mut sums = [T::default(); 4];
+        let mut i = x;
+        while i > 0 {
+            let mut j = y;
+            while j > 0 {
+                let node = &self.data[i * (self.cols + 1) + j];
+                for (sum, &partial) in sums.iter_mut().zip(node.iter()) {
+                    *sum += partial;
+                }
+                j -= lowbit(j);
+            }
+            i -= lowbit(i);
+        }
+        let (fx, fy) = (T::from_usize(x + 1), T::from_usize(y + 1));
+        sums[0] * fx * fy - sums[1] * fy - sums[2] * fx + sums[3]
+    }
+}
+
+/// count the pairs i < j with values[i] > values[j] in O(n log n)
+///
+/// The values are replaced by their ranks among the distinct values,
+/// and swept from left to right with a FenwickTree counting how many
+/// earlier values have each rank, so the earlier values greater than
+/// values[j] are j minus those of rank at most the rank of values[j].
+pub fn count_inversions(values: &[i64]) -> u64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut seen = FenwickTree::with_len(sorted.len());
+    let mut inversions = 0;
+    for (j, value) in values.iter().enumerate() {
+        let rank = sorted.binary_search(value).unwrap();
+        inversions += j as u64 - seen.prefix_sum(rank);
+        seen.add(rank, 1);
+    }
+    inversions
+}
+
 /// get the lowest bit of `i`
 const fn lowbit(x: usize) -> usize {
     let x = x as isize;
@@ -48,6 +289,9 @@ const fn lowbit(x: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use data_structures::RangeSumSegmentTree;
+    use math::PCG32;
+
     #[test]
     fn it_works() {
         let mut ft = FenwickTree::with_len(10);
@@ -72,4 +316,162 @@ mod tests {
         assert_eq!(ft.prefix_sum(8), 45);
         assert_eq!(ft.prefix_sum(9), 55);
     }
+
+    #[test]
+    fn test_from_vec() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        let ft = FenwickTree::from_vec(&values);
+        let mut expected = FenwickTree::with_len(values.len());
+        for (i, &value) in values.iter().enumerate() {
+            expected.add(i, value);
+        }
+        assert_eq!(expected.data, ft.data);
+        assert_eq!(11, ft.len());
+        assert_eq!(44, ft.prefix_sum(10));
+        assert!(FenwickTree::<i64>::from_vec(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_range_sum() {
+        let mut rng = PCG32::new_default(271828);
+        for &n in &[1usize, 2, 10, 100] {
+            let mut values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100 - 50).collect();
+            let mut ft = FenwickTree::from_vec(&values);
+            for _ in 0..200 {
+                let i = rng.get_u32() as usize % n;
+                let delta = rng.get_u32() as i64 % 100 - 50;
+                ft.add(i, delta);
+                values[i] += delta;
+
+                let a = rng.get_u32() as usize % n;
+                let b = rng.get_u32() as usize % n;
+                let (i, j) = (a.min(b), a.max(b));
+                assert_eq!(values[i..=j].iter().sum::<i64>(), ft.range_sum(i, j));
+                assert_eq!(values[..=j].iter().sum::<i64>(), ft.prefix_sum(j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_add() {
+        let mut ft = RangeFenwickTree::from_vec(&[2, 4, 1, 3, 5, 7]);
+        assert_eq!(22, ft.range_sum(0, 5));
+        ft.range_add(1, 3, 1);
+        // new values should be [2, 5, 2, 4, 5, 7]
+        assert_eq!(11, ft.range_sum(1, 3));
+        assert_eq!(5, ft.range_sum(1, 1));
+        ft.range_add(2, 5, -2);
+        // new values should be [2, 5, 0, 2, 3, 5]
+        assert_eq!(8, ft.range_sum(4, 5));
+        assert_eq!(17, ft.range_sum(0, 5));
+        assert_eq!(0, ft.range_sum(2, 2));
+    }
+
+    #[test]
+    fn test_against_segment_tree() {
+        let mut rng = PCG32::new_default(161803);
+        for &n in &[1usize, 3, 16, 100] {
+            let values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100 - 50).collect();
+            let mut ft = RangeFenwickTree::from_vec(&values);
+            let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+            let mut empty = RangeFenwickTree::with_len(n);
+            for _ in 0..200 {
+                let a = rng.get_u32() as usize % n;
+                let b = rng.get_u32() as usize % n;
+                let (l, r) = (a.min(b), a.max(b));
+                if rng.get_u32().is_multiple_of(2) {
+                    let delta = rng.get_u32() as i64 % 100 - 50;
+                    ft.range_add(l, r, delta);
+                    seg_tree.update(l + 1, r + 1, delta);
+                    empty.range_add(l, r, delta);
+                } else {
+                    let sum = seg_tree.query(l + 1, r + 1);
+                    assert_eq!(sum, ft.range_sum(l, r));
+                    assert_eq!(
+                        sum - values[l..=r].iter().sum::<i64>(),
+                        empty.range_sum(l, r)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_inversions() {
+        assert_eq!(0, count_inversions(&[]));
+        assert_eq!(0, count_inversions(&[7]));
+        assert_eq!(0, count_inversions(&[-3, 1, 1, 2, 5, 8]));
+        let n = 100;
+        let reversed: Vec<i64> = (0..n).rev().collect();
+        assert_eq!((n * (n - 1) / 2) as u64, count_inversions(&reversed));
+        // equal values are no inversion
+        assert_eq!(0, count_inversions(&[4, 4, 4]));
+        assert_eq!(7, count_inversions(&[2, 4, 1, 3, 5, 1]));
+    }
+
+    #[test]
+    fn test_count_inversions_against_brute_force() {
+        let mut rng = PCG32::new_default(314159);
+        for &n in &[2usize, 10, 57, 300] {
+            let values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 50 - 25).collect();
+            let mut expected = 0;
+            for i in 0..n {
+                for j in i + 1..n {
+                    if values[i] > values[j] {
+                        expected += 1;
+                    }
+                }
+            }
+            assert_eq!(expected, count_inversions(&values));
+        }
+    }
+
+    #[test]
+    fn test_2d_range_add() {
+        let mut ft = Fenwick2DRange::new(3, 4);
+        assert_eq!(3, ft.rows());
+        assert_eq!(4, ft.cols());
+        ft.range_add(0, 1, 1, 2, 2);
+        ft.range_add(1, 0, 2, 3, 1);
+        ft.range_add(2, 3, 2, 3, -5);
+        // the grid should be
+        // [0, 2, 2, 0]
+        // [1, 3, 3, 1]
+        // [1, 1, 1, -4]
+        assert_eq!(11, ft.range_sum(0, 0, 2, 3));
+        assert_eq!(10, ft.range_sum(0, 1, 1, 2));
+        assert_eq!(-4, ft.range_sum(2, 3, 2, 3));
+        assert_eq!(0, ft.range_sum(0, 0, 0, 0));
+        assert_eq!(2, ft.range_sum(0, 0, 2, 0));
+        assert_eq!(-3, ft.range_sum(1, 3, 2, 3));
+    }
+
+    #[test]
+    fn test_2d_against_brute_force() {
+        let mut rng = PCG32::new_default(577215);
+        for &(rows, cols) in &[(1usize, 1usize), (1, 7), (5, 1), (8, 8), (13, 10)] {
+            let mut grid = vec![vec![0i64; cols]; rows];
+            let mut ft = Fenwick2DRange::new(rows, cols);
+            for _ in 0..200 {
+                let (a, b) = (rng.get_u32() as usize % rows, rng.get_u32() as usize % rows);
+                let (c, d) = (rng.get_u32() as usize % cols, rng.get_u32() as usize % cols);
+                let (r1, r2, c1, c2) = (a.min(b), a.max(b), c.min(d), c.max(d));
+                if rng.get_u32().is_multiple_of(2) {
+                    let delta = rng.get_u32() as i64 % 100 - 50;
+                    ft.range_add(r1, c1, r2, c2, delta);
+                    for row in grid[r1..=r2].iter_mut() {
+                        for cell in row[c1..=c2].iter_mut() {
+                            *cell += delta;
+                        }
+                    }
+                } else {
+                    let sum: i64 = grid[r1..=r2]
+                        .iter()
+                        .map(|row| row[c1..=c2].iter().sum::<i64>())
+                        .sum();
+                    assert_eq!(sum, ft.range_sum(r1, c1, r2, c2));
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,299 @@
+use super::segment_tree_sum::check_range;
+use math::PCG32;
+
+const DEFAULT_SEED: u64 = 0x7ea9;
+
+/// An implicit treap represents a sequence of i64 that supports:
+/// 1. given a position i and a value, insert value so that it ends up
+///    at position i, shifting later elements right
+/// 2. given a position i, remove the element at position i
+/// 3. given a range [l, r], reverse the elements in range
+/// 4. given a range [l, r], query the sum of the elements in range
+///
+/// all in expected O(log n), with positions starting from 1. Nodes are
+/// ordered by position, which is never stored but implied by subtree
+/// sizes, and heap ordered by random priorities, keeping the tree
+/// balanced in expectation. Every operation splits the treap into the
+/// pieces before, inside and after a range, works on the middle piece
+/// and merges them back. Reversal is a lazy flag that swaps children
+/// when pushed down.
+pub struct ImplicitTreap {
+    nodes: Vec<Node>,
+    // slots of erased nodes, reused by insert
+    free: Vec<usize>,
+    root: Option<usize>,
+    rng: PCG32,
+}
+
+struct Node {
+    value: i64,
+    // sum and number of the elements in this subtree
+    sum: i64,
+    size: usize,
+    priority: u32,
+    // the subtree's children still have to be swapped
+    reversed: bool,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl Default for ImplicitTreap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImplicitTreap {
+    /// create an empty sequence with a fixed default seed
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    /// create an empty sequence, drawing node priorities from seed
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: None,
+            rng: PCG32::new_default(seed),
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.size(self.root)
+    }
+
+    /// return true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    fn size(&self, t: Option<usize>) -> usize {
+        t.map_or(0, |t| self.nodes[t].size)
+    }
+
+    fn sum(&self, t: Option<usize>) -> i64 {
+        t.map_or(0, |t| self.nodes[t].sum)
+    }
+
+    fn pull(&mut self, t: usize) {
+        let (left, right) = (self.nodes[t].left, self.nodes[t].right);
+        self.nodes[t].size = self.size(left) + self.size(right) + 1;
+        self.nodes[t].sum = self.sum(left) + self.sum(right) + self.nodes[t].value;
+    }
+
+    fn push_down(&mut self, t: usize) {
+        if !self.nodes[t].reversed {
+            return;
+        }
+        let node = &mut self.nodes[t];
+        node.reversed = false;
+        std::mem::swap(&mut node.left, &mut node.right);
+        let (left, right) = (node.left, node.right);
+        for child in left.into_iter().chain(right) {
+            self.nodes[child].reversed ^= true;
+        }
+    }
+
+    // split t into its first k elements and the rest
+    fn split(&mut self, t: Option<usize>, k: usize) -> (Option<usize>, Option<usize>) {
+        let t = match t {
+            Some(t) => t,
+            None => return (None, None),
+        };
+        self.push_down(t);
+        let left_size = self.size(self.nodes[t].left);
+        if k <= left_size {
+            let (first, rest) = self.split(self.nodes[t].left, k);
+            self.nodes[t].left = rest;
+            self.pull(t);
+            (first, Some(t))
+        } else {
+            let (first, rest) = self.split(self.nodes[t].right, k - left_size - 1);
+            self.nodes[t].right = first;
+            self.pull(t);
+            (Some(t), rest)
+        }
+    }
+
+    // concatenate the sequences a and b
+    fn merge(&mut self, a: Option<usize>, b: Option<usize>) -> Option<usize> {
+        let (a, b) = match (a, b) {
+            (None, b) => return b,
+            (a, None) => return a,
+            (Some(a), Some(b)) => (a, b),
+        };
+        if self.nodes[a].priority > self.nodes[b].priority {
+            self.push_down(a);
+            let right = self.merge(self.nodes[a].right, Some(b));
+            self.nodes[a].right = right;
+            self.pull(a);
+            Some(a)
+        } else {
+            self.push_down(b);
+            let left = self.merge(Some(a), self.nodes[b].left);
+            self.nodes[b].left = left;
+            self.pull(b);
+            Some(b)
+        }
+    }
+
+    // split the sequence into [1, l - 1], [l, r] and [r + 1, len]
+    fn split_range(&mut self, l: usize, r: usize) -> (Option<usize>, Option<usize>, Option<usize>) {
+        let (rest, after) = self.split(self.root, r);
+        let (before, middle) = self.split(rest, l - 1);
+        (before, middle, after)
+    }
+
+    fn merge_range(&mut self, before: Option<usize>, middle: Option<usize>, after: Option<usize>) {
+        let rest = self.merge(before, middle);
+        self.root = self.merge(rest, after);
+    }
+
+    /// insert value at position i, where 1 <= i <= len + 1
+    pub fn insert(&mut self, i: usize, value: i64) {
+        assert!(1 <= i && i <= self.len() + 1, "index out of bounds");
+        let node = Node {
+            value,
+            sum: value,
+            size: 1,
+            priority: self.rng.get_u32(),
+            reversed: false,
+            left: None,
+            right: None,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        let (before, after) = self.split(self.root, i - 1);
+        self.merge_range(before, Some(index), after);
+    }
+
+    /// remove and return the element at position i
+    pub fn erase(&mut self, i: usize) -> i64 {
+        check_range(i, i, self.len()).expect("index out of bounds");
+        let (before, middle, after) = self.split_range(i, i);
+        let index = middle.unwrap();
+        self.free.push(index);
+        self.root = self.merge(before, after);
+        self.nodes[index].value
+    }
+
+    /// reverse the elements in range [l, r]
+    pub fn range_reverse(&mut self, l: usize, r: usize) {
+        check_range(l, r, self.len()).expect("reverse range out of bounds");
+        let (before, middle, after) = self.split_range(l, r);
+        let middle_root = middle.unwrap();
+        self.nodes[middle_root].reversed ^= true;
+        self.merge_range(before, middle, after);
+    }
+
+    /// return the sum of the elements in range [l, r]
+    pub fn range_sum(&mut self, l: usize, r: usize) -> i64 {
+        check_range(l, r, self.len()).expect("query range out of bounds");
+        let (before, middle, after) = self.split_range(l, r);
+        let sum = self.sum(middle);
+        self.merge_range(before, middle, after);
+        sum
+    }
+
+    /// return the elements in order
+    pub fn to_vec(&mut self) -> Vec<i64> {
+        let mut values = Vec::with_capacity(self.len());
+        self.collect_rec(self.root, &mut values);
+        values
+    }
+
+    fn collect_rec(&mut self, t: Option<usize>, values: &mut Vec<i64>) {
+        if let Some(t) = t {
+            self.push_down(t);
+            self.collect_rec(self.nodes[t].left, values);
+            values.push(self.nodes[t].value);
+            self.collect_rec(self.nodes[t].right, values);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sequence_operations() {
+        let mut treap = ImplicitTreap::new();
+        assert!(treap.is_empty());
+        for (i, v) in (1..=6).enumerate() {
+            treap.insert(i + 1, v);
+        }
+        assert_eq!(vec![1, 2, 3, 4, 5, 6], treap.to_vec());
+        treap.insert(1, 10);
+        treap.insert(4, 20);
+        treap.insert(9, 30);
+        assert_eq!(vec![10, 1, 2, 20, 3, 4, 5, 6, 30], treap.to_vec());
+        assert_eq!(9, treap.len());
+
+        treap.range_reverse(2, 6);
+        assert_eq!(vec![10, 4, 3, 20, 2, 1, 5, 6, 30], treap.to_vec());
+        assert_eq!(29, treap.range_sum(2, 5));
+        assert_eq!(81, treap.range_sum(1, 9));
+
+        assert_eq!(20, treap.erase(4));
+        assert_eq!(10, treap.erase(1));
+        assert_eq!(vec![4, 3, 2, 1, 5, 6, 30], treap.to_vec());
+        assert_eq!(10, treap.range_sum(1, 4));
+
+        // erased slots are reused
+        treap.insert(8, 7);
+        assert_eq!(9, treap.nodes.len());
+        assert_eq!(vec![4, 3, 2, 1, 5, 6, 30, 7], treap.to_vec());
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(662607);
+        let mut treap = ImplicitTreap::with_seed(15);
+        let mut reference: Vec<i64> = Vec::new();
+        for _ in 0..3000 {
+            let len = reference.len();
+            match rng.get_u32() % 4 {
+                0 if len > 0 => {
+                    let i = rng.get_u32() as usize % len + 1;
+                    assert_eq!(reference.remove(i - 1), treap.erase(i));
+                }
+                1 if len > 0 => {
+                    let a = rng.get_u32() as usize % len + 1;
+                    let b = rng.get_u32() as usize % len + 1;
+                    let (l, r) = (a.min(b), a.max(b));
+                    treap.range_reverse(l, r);
+                    reference[l - 1..r].reverse();
+                }
+                2 if len > 0 => {
+                    let a = rng.get_u32() as usize % len + 1;
+                    let b = rng.get_u32() as usize % len + 1;
+                    let (l, r) = (a.min(b), a.max(b));
+                    assert_eq!(
+                        reference[l - 1..r].iter().sum::<i64>(),
+                        treap.range_sum(l, r)
+                    );
+                }
+                _ => {
+                    let i = rng.get_u32() as usize % (len + 1) + 1;
+                    let value = rng.get_u32() as i64 % 100;
+                    treap.insert(i, value);
+                    reference.insert(i - 1, value);
+                }
+            }
+            assert_eq!(reference.len(), treap.len());
+        }
+        assert_eq!(reference, treap.to_vec());
+    }
+}
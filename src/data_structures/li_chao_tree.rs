@@ -0,0 +1,146 @@
+use super::segment_tree_sum::calculate_length;
+
+/// Li Chao tree: a set of lines y = m * x + b, answering the maximum
+/// y over all lines at an integer x in [lo, hi).
+///
+/// Each node keeps the line that is highest at the middle of its
+/// segment. Two lines cross at most once, so the line that loses at the
+/// middle can only win on one half and is pushed down into that child
+/// alone, making both `add_line` and `query` O(log(hi - lo)).
+pub struct LiChaoTree {
+    // x-domain [lo, hi)
+    lo: i64,
+    hi: i64,
+    // lines[p] is the line kept at node p, if any
+    lines: Vec<Option<Line>>,
+}
+
+#[derive(Clone, Copy)]
+struct Line {
+    m: i64,
+    b: i64,
+}
+
+impl Line {
+    fn eval(&self, x: i64) -> i64 {
+        self.m * x + self.b
+    }
+}
+
+impl LiChaoTree {
+    /// create an empty tree over the x-domain [lo, hi)
+    pub fn new(lo: i64, hi: i64) -> Self {
+        assert!(lo < hi, "empty domain");
+        Self {
+            lo,
+            hi,
+            lines: vec![None; calculate_length((hi - lo) as usize)],
+        }
+    }
+
+    /// insert the line y = m * x + b
+    pub fn add_line(&mut self, m: i64, b: i64) {
+        self.add_rec(Line { m, b }, self.lo, self.hi - 1, 1)
+    }
+
+    fn add_rec(&mut self, mut line: Line, cl: i64, cr: i64, p: usize) {
+        let mut kept = match self.lines[p] {
+            Some(kept) => kept,
+            None => {
+                self.lines[p] = Some(line);
+                return;
+            }
+        };
+        let mid = cl + (cr - cl) / 2;
+        // keep whichever line is higher at mid
+        if line.eval(mid) > kept.eval(mid) {
+            std::mem::swap(&mut line, &mut kept);
+        }
+        self.lines[p] = Some(kept);
+        if cl == cr {
+            return;
+        }
+        // the lower line can still win on the side where it's higher
+        if line.eval(cl) > kept.eval(cl) {
+            self.add_rec(line, cl, mid, p * 2);
+        } else if line.eval(cr) > kept.eval(cr) {
+            self.add_rec(line, mid + 1, cr, p * 2 + 1);
+        }
+    }
+
+    /// return the maximum m * x + b over all inserted lines,
+    /// or None if no line has been inserted
+    pub fn query(&self, x: i64) -> Option<i64> {
+        assert!(self.lo <= x && x < self.hi, "x out of bounds");
+        let (mut cl, mut cr, mut p) = (self.lo, self.hi - 1, 1);
+        let mut best = None;
+        // x only lies in the segments along one root-to-leaf path
+        while let Some(line) = self.lines[p] {
+            let y = line.eval(x);
+            best = Some(best.map_or(y, |best: i64| best.max(y)));
+            if cl == cr {
+                break;
+            }
+            let mid = cl + (cr - cl) / 2;
+            if x <= mid {
+                cr = mid;
+                p *= 2;
+            } else {
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_query() {
+        let mut tree = LiChaoTree::new(-10, 11);
+        assert_eq!(None, tree.query(0));
+
+        tree.add_line(1, 0);
+        tree.add_line(-1, 0);
+        tree.add_line(0, 3);
+        // max(x, -x, 3)
+        assert_eq!(Some(10), tree.query(-10));
+        assert_eq!(Some(4), tree.query(-4));
+        assert_eq!(Some(3), tree.query(-3));
+        assert_eq!(Some(3), tree.query(0));
+        assert_eq!(Some(3), tree.query(2));
+        assert_eq!(Some(7), tree.query(7));
+        assert_eq!(Some(10), tree.query(10));
+
+        tree.add_line(2, -10);
+        // 2x - 10 takes over past x = 10, outside the domain
+        assert_eq!(Some(10), tree.query(10));
+        tree.add_line(0, 5);
+        assert_eq!(Some(5), tree.query(-5));
+        assert_eq!(Some(6), tree.query(6));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(161803);
+        let (lo, hi) = (-1000, 1000);
+        let mut tree = LiChaoTree::new(lo, hi);
+        let mut lines = Vec::new();
+        for _ in 0..200 {
+            let m = rng.get_u32() as i64 % 2001 - 1000;
+            let b = rng.get_u32() as i64 % 2_000_001 - 1_000_000;
+            tree.add_line(m, b);
+            lines.push((m, b));
+            for _ in 0..10 {
+                let x = lo + rng.get_u32() as i64 % (hi - lo);
+                let expected = lines.iter().map(|&(m, b)| m * x + b).max();
+                assert_eq!(expected, tree.query(x));
+            }
+        }
+    }
+}
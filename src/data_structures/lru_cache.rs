@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A cache holding at most capacity entries, which supports:
+/// 1. given a key, return its value and mark it as the most
+///    recently used entry
+/// 2. given a key and a value, insert or replace the entry, evicting
+///    the least recently used entry if the cache is over capacity
+///
+/// both in O(1) amortized. A hash map finds the node of a key, and the
+/// nodes form a doubly linked list from the most to the least recently
+/// used, linked by their indices in a Vec. Evicting the tail frees a
+/// slot for the entry that is being inserted.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    map: HashMap<K, usize>,
+    nodes: Vec<Node<K, V>>,
+    // most and least recently used entries
+    head: Option<usize>,
+    tail: Option<usize>,
+}
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    /// create an empty cache holding at most capacity entries
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: HashMap::with_capacity(capacity),
+            nodes: Vec::with_capacity(capacity),
+            head: None,
+            tail: None,
+        }
+    }
+
+    /// return the number of entries
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// return true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// return the value of key, marking it as the most recently used
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let index = *self.map.get(key)?;
+        self.move_to_front(index);
+        Some(&self.nodes[index].value)
+    }
+
+    /// insert key with value, or replace the value of key, marking it
+    /// as the most recently used. Returns the evicted entry, if any.
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if let Some(&index) = self.map.get(&key) {
+            self.nodes[index].value = value;
+            self.move_to_front(index);
+            return None;
+        }
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        let node = Node {
+            key: key.clone(),
+            value,
+            prev: None,
+            next: None,
+        };
+        let (index, evicted) = if self.nodes.len() < self.capacity {
+            self.nodes.push(node);
+            (self.nodes.len() - 1, None)
+        } else {
+            // reuse the slot of the least recently used entry
+            let index = self.tail.unwrap();
+            self.unlink(index);
+            let old = std::mem::replace(&mut self.nodes[index], node);
+            self.map.remove(&old.key);
+            (index, Some((old.key, old.value)))
+        };
+        self.map.insert(key, index);
+        self.push_front(index);
+        evicted
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let (prev, next) = (self.nodes[index].prev, self.nodes[index].next);
+        match prev {
+            Some(prev) => self.nodes[prev].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+    }
+
+    fn push_front(&mut self, index: usize) {
+        self.nodes[index].prev = None;
+        self.nodes[index].next = self.head;
+        match self.head {
+            Some(head) => self.nodes[head].prev = Some(index),
+            None => self.tail = Some(index),
+        }
+        self.head = Some(index);
+    }
+
+    fn move_to_front(&mut self, index: usize) {
+        if self.head != Some(index) {
+            self.unlink(index);
+            self.push_front(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_eviction_order() {
+        let mut cache = LruCache::new(3);
+        assert!(cache.is_empty());
+        assert_eq!(None, cache.put(1, "one"));
+        assert_eq!(None, cache.put(2, "two"));
+        assert_eq!(None, cache.put(3, "three"));
+        // past capacity, 1 is the least recently used
+        assert_eq!(Some((1, "one")), cache.put(4, "four"));
+        assert_eq!(None, cache.get(&1));
+        assert_eq!(3, cache.len());
+
+        // refreshing 2 leaves 3 as the least recently used
+        assert_eq!(Some(&"two"), cache.get(&2));
+        assert_eq!(Some((3, "three")), cache.put(5, "five"));
+        assert_eq!(None, cache.get(&3));
+
+        // replacing a value refreshes it too
+        assert_eq!(None, cache.put(4, "FOUR"));
+        assert_eq!(Some((2, "two")), cache.put(6, "six"));
+        assert_eq!(Some(&"FOUR"), cache.get(&4));
+        assert_eq!(Some(&"five"), cache.get(&5));
+        assert_eq!(Some(&"six"), cache.get(&6));
+    }
+
+    #[test]
+    fn test_capacity_one() {
+        let mut cache = LruCache::new(1);
+        cache.put("a", 1);
+        assert_eq!(Some(("a", 1)), cache.put("b", 2));
+        assert_eq!(Some(&2), cache.get(&"b"));
+        assert_eq!(None, cache.get(&"a"));
+    }
+
+    #[test]
+    fn test_capacity_zero() {
+        let mut cache = LruCache::new(0);
+        assert_eq!(Some(("a", 1)), cache.put("a", 1));
+        assert!(cache.is_empty());
+        assert_eq!(None, cache.get(&"a"));
+    }
+}
@@ -0,0 +1,137 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// A merge sort tree is a segment tree over an immutable array of i32
+/// in which every node stores the elements it covers in sorted order,
+/// the way they appear while merge sorting the array. It supports:
+/// 1. given an index l, r and a value x, count the elements in
+///    range [l, r] that are <= x, in O(log^2 n)
+/// 2. given an index l, r and k, find the k-th smallest element
+///    in range [l, r], in O(log^3 n)
+///
+/// The tree takes O(n log n) memory, since every element appears
+/// once on each level.
+pub struct MergeSortTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of sorted[p]
+    // is child sorted[p * 2] and sorted[p * 2 + 1]
+    sorted: Vec<Vec<i32>>,
+}
+
+impl MergeSortTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i32]) -> Self {
+        let n = values.len();
+        let mut tree = Self {
+            len: n,
+            sorted: vec![Vec::new(); calculate_length(n)],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i32], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.sorted[p] = vec![values[left - 1]];
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.sorted[p] = merge(&self.sorted[p * 2], &self.sorted[p * 2 + 1]);
+    }
+
+    /// return the number of elements of array[l]..array[r] inclusive
+    /// that are less than or equal to x
+    pub fn query_count_le(&self, l: usize, r: usize, x: i32) -> usize {
+        check_range(l, r, self.len).expect("query range out of bounds");
+        self.count_rec(l, r, 1, self.len, 1, x)
+    }
+
+    fn count_rec(&self, l: usize, r: usize, cl: usize, cr: usize, p: usize, x: i32) -> usize {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            // number of elements <= x in the sorted node
+            return self.sorted[p].partition_point(|&v| v <= x);
+        }
+        let mid = cl + (cr - cl) / 2;
+        self.count_rec(l, r, cl, mid, p * 2, x) + self.count_rec(l, r, mid + 1, cr, p * 2 + 1, x)
+    }
+
+    /// return the k-th smallest element of array[l]..array[r] inclusive,
+    /// where k = 1 is the smallest
+    pub fn query_kth_smallest(&self, l: usize, r: usize, k: usize) -> i32 {
+        check_range(l, r, self.len).expect("query range out of bounds");
+        assert!(1 <= k && k <= r - l + 1, "k out of range");
+        // the answer is the smallest element x of the whole array
+        // with at least k elements <= x in the range
+        let candidates = &self.sorted[1];
+        let index = candidates.partition_point(|&x| self.count_rec(l, r, 1, self.len, 1, x) < k);
+        candidates[index]
+    }
+}
+
+fn merge(a: &[i32], b: &[i32]) -> Vec<i32> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] <= b[j] {
+            merged.push(a[i]);
+            i += 1;
+        } else {
+            merged.push(b[j]);
+            j += 1;
+        }
+    }
+    merged.extend_from_slice(&a[i..]);
+    merged.extend_from_slice(&b[j..]);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_query() {
+        let values = [5, 1, 4, 1, 5, 9, 2, 6];
+        let tree = MergeSortTree::from_vec(&values);
+        assert_eq!(2, tree.query_count_le(1, 8, 1));
+        assert_eq!(6, tree.query_count_le(1, 8, 5));
+        assert_eq!(0, tree.query_count_le(6, 6, 8));
+        assert_eq!(1, tree.query_count_le(6, 6, 9));
+        assert_eq!(1, tree.query_kth_smallest(1, 8, 1));
+        assert_eq!(1, tree.query_kth_smallest(1, 8, 2));
+        assert_eq!(2, tree.query_kth_smallest(1, 8, 3));
+        assert_eq!(9, tree.query_kth_smallest(1, 8, 8));
+        assert_eq!(5, tree.query_kth_smallest(3, 6, 3));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(693147);
+        for &n in &[1usize, 2, 9, 50] {
+            let values: Vec<i32> = (0..n).map(|_| (rng.get_u32() % 20) as i32 - 10).collect();
+            let tree = MergeSortTree::from_vec(&values);
+            for _ in 0..200 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                let x = (rng.get_u32() % 24) as i32 - 12;
+                let expected = values[l - 1..r].iter().filter(|&&v| v <= x).count();
+                assert_eq!(expected, tree.query_count_le(l, r, x));
+
+                let mut range = values[l - 1..r].to_vec();
+                range.sort_unstable();
+                let k = rng.get_u32() as usize % range.len() + 1;
+                assert_eq!(range[k - 1], tree.query_kth_smallest(l, r, k));
+            }
+        }
+    }
+}
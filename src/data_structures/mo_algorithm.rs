@@ -0,0 +1,153 @@
+use super::segment_tree_sum::check_range;
+
+/// The statistic maintained by `mo_process` over a window of the array.
+/// Positions start from 1, like the query ranges.
+pub trait MoState {
+    type Answer;
+
+    /// position i enters the window
+    fn add(&mut self, i: usize);
+
+    /// position i leaves the window
+    fn remove(&mut self, i: usize);
+
+    /// the statistic of the current window
+    fn answer(&self) -> Self::Answer;
+}
+
+/// Answer the inclusive range queries [l, r] over an array of n elements
+/// offline with Mo's algorithm, returning the answers in query order.
+///
+/// The window of `state` is moved from one query range to the next by
+/// adding and removing one position at a time. Sorting the queries by
+/// the block of l, with blocks of n / sqrt(q) positions, and then by r
+/// keeps the total movement at O(n sqrt(q)), so any statistic with O(1)
+/// updates is answered in O(n sqrt(q) + q log q).
+pub fn mo_process<S: MoState>(
+    n: usize,
+    queries: &[(usize, usize)],
+    state: &mut S,
+) -> Vec<S::Answer> {
+    for &(l, r) in queries {
+        check_range(l, r, n).expect("query range out of bounds");
+    }
+    let block = (n as f64 / (queries.len() as f64).sqrt()).max(1.0) as usize;
+    let mut order: Vec<usize> = (0..queries.len()).collect();
+    order.sort_by_key(|&q| {
+        let (l, r) = queries[q];
+        let b = l / block;
+        // sweeping r back and forth on alternate blocks
+        // saves the walk back to the start of every block
+        (b, if b.is_multiple_of(2) { r } else { n - r })
+    });
+
+    let mut answers: Vec<Option<S::Answer>> = (0..queries.len()).map(|_| None).collect();
+    // the window is [cl, cr], starting out empty
+    let (mut cl, mut cr) = (1, 0);
+    for q in order {
+        let (l, r) = queries[q];
+        // grow before shrinking so the window never has negative length
+        while cl > l {
+            cl -= 1;
+            state.add(cl);
+        }
+        while cr < r {
+            cr += 1;
+            state.add(cr);
+        }
+        while cl < l {
+            state.remove(cl);
+            cl += 1;
+        }
+        while cr > r {
+            state.remove(cr);
+            cr -= 1;
+        }
+        answers[q] = Some(state.answer());
+    }
+    answers.into_iter().map(Option::unwrap).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    // number of distinct values in the window
+    struct Distinct<'a> {
+        values: &'a [usize],
+        counts: Vec<usize>,
+        distinct: usize,
+    }
+
+    impl<'a> MoState for Distinct<'a> {
+        type Answer = usize;
+
+        fn add(&mut self, i: usize) {
+            let count = &mut self.counts[self.values[i - 1]];
+            if *count == 0 {
+                self.distinct += 1;
+            }
+            *count += 1;
+        }
+
+        fn remove(&mut self, i: usize) {
+            let count = &mut self.counts[self.values[i - 1]];
+            *count -= 1;
+            if *count == 0 {
+                self.distinct -= 1;
+            }
+        }
+
+        fn answer(&self) -> usize {
+            self.distinct
+        }
+    }
+
+    fn distinct_brute_force(values: &[usize], l: usize, r: usize) -> usize {
+        let mut seen = values[l - 1..r].to_vec();
+        seen.sort_unstable();
+        seen.dedup();
+        seen.len()
+    }
+
+    #[test]
+    fn test_distinct() {
+        let values = [1, 2, 1, 3, 2, 2, 4, 1];
+        let mut state = Distinct {
+            values: &values,
+            counts: vec![0; 5],
+            distinct: 0,
+        };
+        let queries = [(1, 8), (2, 3), (5, 6), (4, 7), (1, 1), (3, 5)];
+        assert_eq!(
+            vec![4, 2, 1, 3, 1, 3],
+            mo_process(values.len(), &queries, &mut state)
+        );
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(602214);
+        let n = 300;
+        let values: Vec<usize> = (0..n).map(|_| rng.get_u32() as usize % 30).collect();
+        let queries: Vec<(usize, usize)> = (0..500)
+            .map(|_| {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                (a.min(b), a.max(b))
+            })
+            .collect();
+        let mut state = Distinct {
+            values: &values,
+            counts: vec![0; 30],
+            distinct: 0,
+        };
+        let answers = mo_process(n, &queries, &mut state);
+        for (&(l, r), &answer) in queries.iter().zip(&answers) {
+            assert_eq!(distinct_brute_force(&values, l, r), answer);
+        }
+        assert!(mo_process(n, &[], &mut state).is_empty());
+    }
+}
@@ -1,28 +1,201 @@
+#[cfg(feature = "std")]
 mod avl_tree;
+#[cfg(feature = "std")]
 mod b_tree;
+#[cfg(feature = "std")]
+mod binary_heap;
+#[cfg(feature = "std")]
 mod binary_search_tree;
+#[cfg(feature = "std")]
+mod bloom_filter;
+#[cfg(feature = "std")]
+mod cartesian_tree;
+#[cfg(feature = "std")]
 mod fenwick_tree;
+#[cfg(feature = "std")]
 mod graph;
+#[cfg(feature = "std")]
 mod heap;
+#[cfg(feature = "std")]
+mod implicit_treap;
+#[cfg(feature = "std")]
+mod li_chao_tree;
+#[cfg(feature = "std")]
 mod linked_list;
+#[cfg(feature = "std")]
+mod lru_cache;
+#[cfg(feature = "std")]
+mod merge_sort_tree;
+#[cfg(feature = "std")]
+mod mo_algorithm;
+#[cfg(feature = "std")]
+mod order_statistic_tree;
+#[cfg(feature = "std")]
 mod queue;
+#[cfg(feature = "std")]
 mod rb_tree;
+#[cfg(feature = "std")]
 mod segment_tree;
+#[cfg(feature = "std")]
+mod segment_tree_2d;
+#[cfg(feature = "std")]
+mod segment_tree_affine;
+#[cfg(feature = "std")]
+mod segment_tree_arithmetic;
+#[cfg(feature = "std")]
+mod segment_tree_beats;
+#[cfg(feature = "std")]
+mod segment_tree_bitwise;
+#[cfg(feature = "std")]
+mod segment_tree_bool;
+#[cfg(feature = "std")]
+mod segment_tree_counting;
+#[cfg(feature = "std")]
+mod segment_tree_dyn;
+#[cfg(feature = "std")]
+mod segment_tree_f64;
+#[cfg(feature = "std")]
+mod segment_tree_gcd;
+#[cfg(feature = "std")]
+mod segment_tree_historic_max;
+#[cfg(feature = "std")]
+mod segment_tree_interval_count;
+#[cfg(feature = "std")]
+mod segment_tree_iterative;
+#[cfg(feature = "std")]
+mod segment_tree_lazy;
+#[cfg(feature = "std")]
+mod segment_tree_max;
+#[cfg(feature = "std")]
+mod segment_tree_max_subarray;
+#[cfg(feature = "std")]
+mod segment_tree_min;
+#[cfg(feature = "std")]
+mod segment_tree_min_prefix;
+#[cfg(feature = "std")]
+mod segment_tree_monoid;
+#[cfg(feature = "std")]
+mod segment_tree_nary;
+#[cfg(feature = "std")]
+mod segment_tree_persistent;
+#[cfg(feature = "std")]
+mod segment_tree_sparse;
+mod segment_tree_static;
 mod segment_tree_sum;
+#[cfg(feature = "std")]
+mod skip_list;
+#[cfg(feature = "std")]
+mod sparse_table;
+#[cfg(feature = "std")]
 mod stack_using_singly_linked_list;
+#[cfg(feature = "std")]
 mod trie;
+#[cfg(feature = "std")]
+mod union_find;
+#[cfg(feature = "std")]
+mod wavelet_tree;
 
+#[cfg(feature = "std")]
 pub use self::avl_tree::AVLTree;
+#[cfg(feature = "std")]
 pub use self::b_tree::BTree;
+#[cfg(feature = "std")]
+pub use self::binary_heap::{DaryHeap, Handle};
+#[cfg(feature = "std")]
 pub use self::binary_search_tree::BinarySearchTree;
-pub use self::fenwick_tree::FenwickTree;
+#[cfg(feature = "std")]
+pub use self::bloom_filter::BloomFilter;
+#[cfg(feature = "std")]
+pub use self::cartesian_tree::CartesianTree;
+#[cfg(feature = "std")]
+pub use self::fenwick_tree::{count_inversions, Fenwick2DRange, FenwickTree, RangeFenwickTree};
+#[cfg(feature = "std")]
 pub use self::graph::DirectedGraph;
+#[cfg(feature = "std")]
 pub use self::graph::UndirectedGraph;
+#[cfg(feature = "std")]
 pub use self::heap::{Heap, MaxHeap, MinHeap};
+#[cfg(feature = "std")]
+pub use self::implicit_treap::ImplicitTreap;
+#[cfg(feature = "std")]
+pub use self::li_chao_tree::LiChaoTree;
+#[cfg(feature = "std")]
 pub use self::linked_list::LinkedList;
+#[cfg(feature = "std")]
+pub use self::lru_cache::LruCache;
+#[cfg(feature = "std")]
+pub use self::merge_sort_tree::MergeSortTree;
+#[cfg(feature = "std")]
+pub use self::mo_algorithm::{mo_process, MoState};
+#[cfg(feature = "std")]
+pub use self::order_statistic_tree::OrderStatisticTree;
+#[cfg(feature = "std")]
 pub use self::queue::Queue;
+#[cfg(feature = "std")]
 pub use self::rb_tree::RBTree;
+#[cfg(feature = "std")]
 pub use self::segment_tree::SegmentTree;
-pub use self::segment_tree_sum::RangeSumSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_2d::SegmentTree2D;
+#[cfg(feature = "std")]
+pub use self::segment_tree_affine::RangeAffineSumSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_arithmetic::RangeArithmeticSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_beats::{ChmaxSegmentTreeBeats, SegmentTreeBeats};
+#[cfg(feature = "std")]
+pub use self::segment_tree_bitwise::{
+    RangeAndSegmentTree, RangeOrSegmentTree, RangeXorSegmentTree,
+};
+#[cfg(feature = "std")]
+pub use self::segment_tree_bool::BoolSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_counting::CountingSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_dyn::DynSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_f64::RangeSumSegmentTreeF64;
+#[cfg(feature = "std")]
+pub use self::segment_tree_gcd::{RangeAddGcdSegmentTree, RangeGcdSegmentTree};
+#[cfg(feature = "std")]
+pub use self::segment_tree_historic_max::HistoricMaxSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_interval_count::IntervalCountSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_iterative::IterativeSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_lazy::{Action, LazySegmentTree};
+#[cfg(feature = "std")]
+pub use self::segment_tree_max::{RangeAssignMaxSegmentTree, RangeMaxSegmentTree};
+#[cfg(feature = "std")]
+pub use self::segment_tree_max_subarray::MaxSubarraySegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_min::RangeMinSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_min_prefix::MinPrefixSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_monoid::{Monoid, MonoidSegmentTree};
+#[cfg(feature = "std")]
+pub use self::segment_tree_nary::NaryRangeSumSegmentTree;
+#[cfg(feature = "std")]
+pub use self::segment_tree_persistent::{PersistentSegmentTree, PersistentValueCountTree};
+#[cfg(feature = "std")]
+pub use self::segment_tree_sparse::SparseSegmentTree;
+pub use self::segment_tree_static::StaticSegmentTree;
+pub use self::segment_tree_sum::{
+    Checkpoint, CountedSumSegmentTree, FrozenSegmentTree, LazyPendingError, LeafIndex,
+    ModSumSegmentTree, RangeAssignSumSegmentTree, RangeSumSegmentTree, RangeUpdateSumSegmentTree,
+    SegRange, SegTreeError, SegTreeIter, Summable, ToF64, TransactionError,
+};
+#[cfg(feature = "std")]
+pub use self::skip_list::{SkipList, SkipListIter};
+#[cfg(feature = "std")]
+pub use self::sparse_table::{PlusMinusOneRmq, SparseTable};
+#[cfg(feature = "std")]
 pub use self::stack_using_singly_linked_list::Stack;
+#[cfg(feature = "std")]
 pub use self::trie::Trie;
+#[cfg(feature = "std")]
+pub use self::union_find::{RollbackUnionFind, UnionFind};
+#[cfg(feature = "std")]
+pub use self::wavelet_tree::WaveletTree;
@@ -0,0 +1,164 @@
+/// A multiset of values in [0, max), stored as a Fenwick tree over
+/// the count of each value. Inserting, removing, counting the elements
+/// smaller than a value, and finding the k-th smallest element all take
+/// O(log max).
+pub struct OrderStatisticTree {
+    // Fenwick tree over counts, count of value v is at index v + 1
+    tree: Vec<usize>,
+    // total number of elements
+    len: usize,
+}
+
+impl OrderStatisticTree {
+    /// create an empty multiset over the values [0, max)
+    pub fn new(max: usize) -> Self {
+        Self {
+            tree: vec![0; max + 1],
+            len: 0,
+        }
+    }
+
+    /// return the number of elements, counting duplicates
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the multiset is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// add one occurrence of value
+    pub fn insert(&mut self, value: usize) {
+        assert!(value + 1 < self.tree.len(), "value out of bounds");
+        let mut i = value + 1;
+        while i < self.tree.len() {
+            self.tree[i] += 1;
+            i += lowbit(i);
+        }
+        self.len += 1;
+    }
+
+    /// remove one occurrence of value, returning false if it wasn't present
+    pub fn remove(&mut self, value: usize) -> bool {
+        assert!(value + 1 < self.tree.len(), "value out of bounds");
+        if self.count(value) == 0 {
+            return false;
+        }
+        let mut i = value + 1;
+        while i < self.tree.len() {
+            self.tree[i] -= 1;
+            i += lowbit(i);
+        }
+        self.len -= 1;
+        true
+    }
+
+    /// return the number of occurrences of value
+    pub fn count(&self, value: usize) -> usize {
+        self.rank(value + 1) - self.rank(value)
+    }
+
+    /// return the number of elements strictly smaller than value
+    pub fn rank(&self, value: usize) -> usize {
+        let mut i = value.min(self.tree.len() - 1);
+        let mut res = 0;
+        while i > 0 {
+            res += self.tree[i];
+            i -= lowbit(i);
+        }
+        res
+    }
+
+    /// return the k-th smallest element, 1-based, or
+    /// None if there are fewer than k elements
+    pub fn kth(&self, k: usize) -> Option<usize> {
+        if k == 0 || k > self.len {
+            return None;
+        }
+        // binary lifting: find the longest prefix of values
+        // holding fewer than k elements, the answer is next
+        let mut pos = 0;
+        let mut remaining = k;
+        let mut step = (self.tree.len() - 1).next_power_of_two();
+        while step > 0 {
+            if pos + step < self.tree.len() && self.tree[pos + step] < remaining {
+                pos += step;
+                remaining -= self.tree[pos];
+            }
+            step /= 2;
+        }
+        Some(pos)
+    }
+}
+
+fn lowbit(x: usize) -> usize {
+    x & x.wrapping_neg()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_rank_and_kth() {
+        let mut tree = OrderStatisticTree::new(10);
+        for &v in &[5, 1, 9, 5, 0, 3] {
+            tree.insert(v);
+        }
+        // multiset is {0, 1, 3, 5, 5, 9}
+        assert_eq!(6, tree.len());
+        assert_eq!(0, tree.rank(0));
+        assert_eq!(3, tree.rank(4));
+        assert_eq!(3, tree.rank(5));
+        assert_eq!(5, tree.rank(6));
+        assert_eq!(6, tree.rank(10));
+        assert_eq!(2, tree.count(5));
+
+        let sorted: Vec<_> = (1..=6).map(|k| tree.kth(k).unwrap()).collect();
+        assert_eq!(vec![0, 1, 3, 5, 5, 9], sorted);
+        assert_eq!(None, tree.kth(0));
+        assert_eq!(None, tree.kth(7));
+
+        assert!(tree.remove(5));
+        assert!(!tree.remove(4));
+        // multiset is {0, 1, 3, 5, 9}
+        assert_eq!(1, tree.count(5));
+        assert_eq!(Some(5), tree.kth(4));
+        assert_eq!(Some(9), tree.kth(5));
+        assert_eq!(4, tree.rank(9));
+    }
+
+    #[test]
+    fn test_against_sorted_vec() {
+        let mut rng = PCG32::new_default(271828);
+        let max = 50;
+        let mut tree = OrderStatisticTree::new(max);
+        let mut reference: Vec<usize> = Vec::new();
+        for _ in 0..2000 {
+            let value = rng.get_u32() as usize % max;
+            if rng.get_u32() % 3 == 0 {
+                let pos = reference.iter().position(|&v| v == value);
+                assert_eq!(pos.is_some(), tree.remove(value));
+                if let Some(pos) = pos {
+                    reference.remove(pos);
+                }
+            } else {
+                tree.insert(value);
+                reference.push(value);
+            }
+            reference.sort_unstable();
+            assert_eq!(reference.len(), tree.len());
+            let probe = rng.get_u32() as usize % (max + 1);
+            assert_eq!(
+                reference.iter().filter(|&&v| v < probe).count(),
+                tree.rank(probe)
+            );
+            for (k, &v) in reference.iter().enumerate() {
+                assert_eq!(Some(v), tree.kth(k + 1));
+            }
+        }
+    }
+}
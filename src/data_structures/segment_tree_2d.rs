@@ -0,0 +1,182 @@
+use super::fenwick_tree::FenwickTree;
+use super::segment_tree_sum::calculate_length;
+
+/// A 2D segment tree over a rows x cols grid of i64, supporting:
+/// 1. add delta to the cell (row, col)
+/// 2. query the sum of the rectangle [r1, r2] x [c1, c2]
+///
+/// The outer segment tree is over rows, and each of its nodes holds a
+/// Fenwick tree of the column sums of the rows the node covers. Both
+/// operations visit O(log rows) outer nodes and spend O(log cols) in each,
+/// so they cost O(log rows * log cols).
+///
+/// Rows and columns are 0-indexed and rectangles are inclusive.
+pub struct SegmentTree2D {
+    rows: usize,
+    cols: usize,
+    // representation of the outer tree, where child of inner[p]
+    // is child inner[p * 2] and inner[p * 2 + 1]
+    inner: Vec<FenwickTree<i64>>,
+}
+
+impl SegmentTree2D {
+    /// construct a rows x cols grid of zeros
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let length = calculate_length(rows);
+        SegmentTree2D {
+            rows,
+            cols,
+            inner: (0..length).map(|_| FenwickTree::with_len(cols)).collect(),
+        }
+    }
+
+    /// build tree from a grid, every row must have the same length
+    pub fn from_vec(grid: &[Vec<i64>]) -> Self {
+        let rows = grid.len();
+        let cols = grid.first().map_or(0, |row| row.len());
+        assert!(
+            grid.iter().all(|row| row.len() == cols),
+            "rows must have the same length"
+        );
+        let mut tree = SegmentTree2D::new(rows, cols);
+        if rows > 0 {
+            tree.build_rec(grid, 0, rows - 1, 1);
+        }
+        tree
+    }
+
+    // build the node covering rows [top, bottom], and return its column sums
+    fn build_rec(&mut self, grid: &[Vec<i64>], top: usize, bottom: usize, p: usize) -> Vec<i64> {
+        let sums = if top == bottom {
+            grid[top].clone()
+        } else {
+            let mid = top + (bottom - top) / 2;
+            let upper = self.build_rec(grid, top, mid, p * 2);
+            let lower = self.build_rec(grid, mid + 1, bottom, p * 2 + 1);
+            upper.iter().zip(lower.iter()).map(|(a, b)| a + b).collect()
+        };
+        self.inner[p] = FenwickTree::from_vec(&sums);
+        sums
+    }
+
+    /// add delta to the cell (row, col)
+    pub fn add(&mut self, row: usize, col: usize, delta: i64) {
+        assert!(row < self.rows && col < self.cols, "cell out of bounds");
+        // every outer node on the path to the row covers the cell
+        let (mut top, mut bottom, mut p) = (0, self.rows - 1, 1);
+        loop {
+            self.inner[p].add(col, delta);
+            if top == bottom {
+                return;
+            }
+            let mid = top + (bottom - top) / 2;
+            if row <= mid {
+                bottom = mid;
+                p *= 2;
+            } else {
+                top = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+    }
+
+    /// return the sum of the rectangle [r1, r2] x [c1, c2]
+    pub fn query(&self, r1: usize, c1: usize, r2: usize, c2: usize) -> i64 {
+        assert!(
+            r1 <= r2 && r2 < self.rows && c1 <= c2 && c2 < self.cols,
+            "invalid rectangle"
+        );
+        self.query_rec(r1, r2, (c1, c2), 0, self.rows - 1, 1)
+    }
+
+    fn query_rec(
+        &self,
+        r1: usize,
+        r2: usize,
+        cols: (usize, usize),
+        top: usize,
+        bottom: usize,
+        p: usize,
+    ) -> i64 {
+        if top > r2 || bottom < r1 {
+            return 0;
+        }
+        if top >= r1 && bottom <= r2 {
+            return self.inner[p].range_sum(cols.0, cols.1);
+        }
+        let mid = top + (bottom - top) / 2;
+        self.query_rec(r1, r2, cols, top, mid, p * 2)
+            + self.query_rec(r1, r2, cols, mid + 1, bottom, p * 2 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    // prefix[r][c] is the sum of grid[..r][..c]
+    fn prefix_sums(grid: &[Vec<i64>]) -> Vec<Vec<i64>> {
+        let rows = grid.len();
+        let cols = grid[0].len();
+        let mut prefix = vec![vec![0; cols + 1]; rows + 1];
+        for r in 0..rows {
+            for c in 0..cols {
+                prefix[r + 1][c + 1] =
+                    grid[r][c] + prefix[r][c + 1] + prefix[r + 1][c] - prefix[r][c];
+            }
+        }
+        prefix
+    }
+
+    #[test]
+    fn test_small_grid() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        let mut seg_tree = SegmentTree2D::from_vec(&grid);
+        assert_eq!(45, seg_tree.query(0, 0, 2, 2));
+        assert_eq!(12, seg_tree.query(0, 0, 1, 1));
+        assert_eq!(28, seg_tree.query(1, 1, 2, 2));
+        assert_eq!(15, seg_tree.query(0, 1, 2, 1));
+        seg_tree.add(1, 1, -5);
+        assert_eq!(40, seg_tree.query(0, 0, 2, 2));
+        assert_eq!(0, seg_tree.query(1, 1, 1, 1));
+    }
+
+    #[test]
+    fn test_against_prefix_sums() {
+        let mut rng = PCG32::new_default(662607);
+        for &(rows, cols) in &[(1, 1), (1, 7), (5, 3), (64, 64), (33, 17)] {
+            let mut grid: Vec<Vec<i64>> = (0..rows)
+                .map(|_| (0..cols).map(|_| rng.get_u32() as i64 % 100 - 50).collect())
+                .collect();
+            let mut seg_tree = SegmentTree2D::from_vec(&grid);
+            for _ in 0..20 {
+                let (row, col) = (rng.get_u32() as usize % rows, rng.get_u32() as usize % cols);
+                let delta = rng.get_u32() as i64 % 100 - 50;
+                seg_tree.add(row, col, delta);
+                grid[row][col] += delta;
+
+                let prefix = prefix_sums(&grid);
+                for _ in 0..20 {
+                    let (a, b) = (rng.get_u32() as usize % rows, rng.get_u32() as usize % rows);
+                    let (c, d) = (rng.get_u32() as usize % cols, rng.get_u32() as usize % cols);
+                    let (r1, r2, c1, c2) = (a.min(b), a.max(b), c.min(d), c.max(d));
+                    let expected = prefix[r2 + 1][c2 + 1] - prefix[r1][c2 + 1] - prefix[r2 + 1][c1]
+                        + prefix[r1][c1];
+                    assert_eq!(expected, seg_tree.query(r1, c1, r2, c2));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let mut seg_tree = SegmentTree2D::new(4, 5);
+        assert_eq!(0, seg_tree.query(0, 0, 3, 4));
+        seg_tree.add(3, 4, 2);
+        seg_tree.add(0, 0, 1);
+        assert_eq!(3, seg_tree.query(0, 0, 3, 4));
+        assert_eq!(2, seg_tree.query(1, 1, 3, 4));
+    }
+}
@@ -0,0 +1,220 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// This segment tree is built on an array of i64,
+/// and supports the following operation:
+/// 1. given an index i, j, query the sum of array in range [i, j]
+/// 2. given a range [x, y] and b, c, replace every value v of the
+///    array in range with b * v + c
+///
+/// Range add (b = 1) and range multiply (c = 0) are special cases.
+/// The lazy tag of a node is the pair (b, c), and applying (b2, c2)
+/// after (b1, c1) is the same as applying (b2 * b1, b2 * c1 + c2).
+///
+/// Optionally, every value is kept modulo a given modulus.
+pub struct RangeAffineSumSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1]
+    arr: Vec<i64>,
+    // mark[p] stores the transform that has been applied
+    // to node p but has not been propagated to its child node
+    mark: Vec<(i64, i64)>,
+    modulus: Option<i64>,
+}
+
+impl RangeAffineSumSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        Self::build(values, None)
+    }
+
+    /// build tree from an array of values, keeping
+    /// every value and sum modulo modulus
+    pub fn from_vec_mod(values: &[i64], modulus: i64) -> Self {
+        assert!(modulus > 0, "modulus must be positive");
+        Self::build(values, Some(modulus))
+    }
+
+    fn build(values: &[i64], modulus: Option<i64>) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![0; length],
+            mark: vec![(1, 0); length],
+            modulus,
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i64], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = self.reduce(values[left - 1] as i128);
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = self.add(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    fn reduce(&self, x: i128) -> i64 {
+        match self.modulus {
+            Some(m) => x.rem_euclid(m as i128) as i64,
+            None => x as i64,
+        }
+    }
+
+    fn add(&self, a: i64, b: i64) -> i64 {
+        match self.modulus {
+            Some(_) => self.reduce(a as i128 + b as i128),
+            None => a + b,
+        }
+    }
+
+    fn mul(&self, a: i64, b: i64) -> i64 {
+        match self.modulus {
+            Some(_) => self.reduce(a as i128 * b as i128),
+            None => a * b,
+        }
+    }
+
+    /// replace every value v in range [i, j] with b * v + c
+    pub fn update(&mut self, i: usize, j: usize, b: i64, c: i64) {
+        check_range(i, j, self.len).expect("update range out of bounds");
+        let tag = (self.reduce(b as i128), self.reduce(c as i128));
+        self.update_rec(i, j, 1, self.len, 1, tag)
+    }
+
+    /// multiply every value in range [i, j] by b
+    pub fn multiply(&mut self, i: usize, j: usize, b: i64) {
+        self.update(i, j, b, 0)
+    }
+
+    /// add c to every value in range [i, j]
+    pub fn add_range(&mut self, i: usize, j: usize, c: i64) {
+        self.update(i, j, 1, c)
+    }
+
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, tag: (i64, i64)) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, cr - cl + 1, tag);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, tag);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, tag);
+
+        self.arr[p] = self.add(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    // apply x -> b * x + c to the length elements covered by node p
+    fn apply(&mut self, p: usize, length: usize, (b, c): (i64, i64)) {
+        let scaled = self.mul(c, self.reduce(length as i128));
+        self.arr[p] = self.add(self.mul(b, self.arr[p]), scaled);
+        let (b1, c1) = self.mark[p];
+        self.mark[p] = (self.mul(b, b1), self.add(self.mul(b, c1), c));
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let tag = std::mem::replace(&mut self.mark[p], (1, 0));
+        self.apply(p * 2, length.div_ceil(2), tag);
+        self.apply(p * 2 + 1, length / 2, tag);
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> i64 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i64 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        let left = self.query_rec(l, r, cl, mid, p * 2);
+        let right = self.query_rec(l, r, mid + 1, cr, p * 2 + 1);
+        self.add(left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_multiply_and_add() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeAffineSumSegmentTree::from_vec(&values);
+        seg_tree.multiply(1, 4, 2);
+        // new values should be [2, 4, 6, 8, 5, 6]
+        assert_eq!(31, seg_tree.query(1, 6));
+        seg_tree.add_range(3, 6, 1);
+        // new values should be [2, 4, 7, 9, 6, 7]
+        assert_eq!(35, seg_tree.query(1, 6));
+        assert_eq!(16, seg_tree.query(3, 4));
+        seg_tree.update(2, 5, -1, 10);
+        // new values should be [2, 6, 3, 1, 4, 7]
+        assert_eq!(23, seg_tree.query(1, 6));
+        assert_eq!(6, seg_tree.query(2, 2));
+        assert_eq!(8, seg_tree.query(3, 5));
+    }
+
+    #[test]
+    fn test_modulus() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeAffineSumSegmentTree::from_vec_mod(&values, 7);
+        // 21 mod 7
+        assert_eq!(0, seg_tree.query(1, 6));
+        seg_tree.multiply(1, 6, 3);
+        // new values should be [3, 6, 2, 5, 1, 4]
+        assert_eq!(4, seg_tree.query(1, 3));
+        seg_tree.add_range(1, 2, -4);
+        // new values should be [6, 2, 2, 5, 1, 4]
+        assert_eq!(3, seg_tree.query(1, 3));
+        assert_eq!(6, seg_tree.query(1, 6));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(112358);
+        let modulus = 1_000_000_007;
+        for &n in &[1usize, 4, 31, 100] {
+            let mut values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100).collect();
+            let mut seg_tree = RangeAffineSumSegmentTree::from_vec_mod(&values, modulus);
+            for _ in 0..500 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                if rng.get_u32().is_multiple_of(2) {
+                    let (b, c) = (rng.get_u32() as i64, rng.get_u32() as i64 - (1 << 31));
+                    seg_tree.update(l, r, b, c);
+                    for v in &mut values[l - 1..r] {
+                        *v = ((b as i128 * *v as i128 + c as i128).rem_euclid(modulus as i128))
+                            as i64;
+                    }
+                } else {
+                    let expected = values[l - 1..r].iter().sum::<i64>() % modulus;
+                    assert_eq!(expected, seg_tree.query(l, r));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// This segment tree is built on an array of i64,
+/// and supports the following operation:
+/// 1. given an index i, j, query the sum of array in range [i, j]
+/// 2. given a range [x, y] and start, step, set array[k] to
+///    start + (k - x) * step for every k in range
+///
+/// The lazy tag of a node is the progression its elements were set to,
+/// as the pair (value at the node's left boundary, step). A later
+/// assignment replaces the tag outright, and pushing it down hands the
+/// left child the same tag and the right child the progression moved
+/// forward by the length of the left child. The sum of n elements of a
+/// progression is n * start + step * n * (n - 1) / 2.
+pub struct RangeArithmeticSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1]
+    arr: Vec<i64>,
+    // mark[p] stores the progression that has been assigned
+    // to node p but has not been propagated to its child node
+    mark: Vec<Option<(i64, i64)>>,
+}
+
+// sum of start, start + step, .., start + (n - 1) * step
+fn progression_sum(n: usize, start: i64, step: i64) -> i64 {
+    let n = n as i64;
+    n * start + step * (n * (n - 1) / 2)
+}
+
+impl RangeArithmeticSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![0; length],
+            mark: vec![None; length],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i64], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = values[left - 1];
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// set array[k] to start + (k - i) * step for every k in range [i, j]
+    pub fn assign_progression(&mut self, i: usize, j: usize, start: i64, step: i64) {
+        check_range(i, j, self.len).expect("assign range out of bounds");
+        self.assign_rec(i, j, 1, self.len, 1, (start, step))
+    }
+
+    // assign the progression (start, step), which begins at l
+    fn assign_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, tag: (i64, i64)) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            // the progression at l, moved forward to this node's left boundary
+            let (start, step) = tag;
+            let first = start + (cl - l) as i64 * step;
+            self.apply(p, cr - cl + 1, (first, step));
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.assign_rec(l, r, cl, mid, p * 2, tag);
+        self.assign_rec(l, r, mid + 1, cr, p * 2 + 1, tag);
+
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+    }
+
+    // assign the progression (first, step) to the length elements covered by node p
+    fn apply(&mut self, p: usize, length: usize, (first, step): (i64, i64)) {
+        self.arr[p] = progression_sum(length, first, step);
+        self.mark[p] = Some((first, step));
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        if let Some((first, step)) = self.mark[p].take() {
+            let left_length = length.div_ceil(2);
+            self.apply(p * 2, left_length, (first, step));
+            let right_first = first + left_length as i64 * step;
+            self.apply(p * 2 + 1, length / 2, (right_first, step));
+        }
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn sum(&mut self, i: usize, j: usize) -> i64 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.sum_rec(i, j, 1, self.len, 1)
+    }
+
+    fn sum_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i64 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.sum_rec(l, r, cl, mid, p * 2) + self.sum_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_overlapping_progressions() {
+        let mut seg_tree = RangeArithmeticSegmentTree::from_vec(&[0; 8]);
+        seg_tree.assign_progression(1, 5, 1, 1);
+        // new values should be [1, 2, 3, 4, 5, 0, 0, 0]
+        assert_eq!(15, seg_tree.sum(1, 8));
+        assert_eq!(9, seg_tree.sum(2, 4));
+        seg_tree.assign_progression(4, 8, 10, -2);
+        // new values should be [1, 2, 3, 10, 8, 6, 4, 2]
+        assert_eq!(36, seg_tree.sum(1, 8));
+        assert_eq!(18, seg_tree.sum(4, 5));
+        assert_eq!(12, seg_tree.sum(6, 8));
+        seg_tree.assign_progression(2, 6, 7, 0);
+        // new values should be [1, 7, 7, 7, 7, 7, 4, 2]
+        assert_eq!(42, seg_tree.sum(1, 8));
+        assert_eq!(11, seg_tree.sum(6, 7));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(271828);
+        for &n in &[1usize, 5, 32, 77] {
+            let mut values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100).collect();
+            let mut seg_tree = RangeArithmeticSegmentTree::from_vec(&values);
+            for _ in 0..500 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                if rng.get_u32().is_multiple_of(2) {
+                    let start = rng.get_u32() as i64 % 1000 - 500;
+                    let step = rng.get_u32() as i64 % 21 - 10;
+                    seg_tree.assign_progression(l, r, start, step);
+                    for (k, v) in values[l - 1..r].iter_mut().enumerate() {
+                        *v = start + k as i64 * step;
+                    }
+                } else {
+                    assert_eq!(values[l - 1..r].iter().sum::<i64>(), seg_tree.sum(l, r));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// Segment tree beats (Ji driver segment tree) over an array of i64,
+/// supporting the following operation:
+/// 1. given a range [l, r] and x, set every array[i] in range
+///    to min(array[i], x)
+/// 2. given a range [l, r] and a delta, add delta to every
+///    element of array in range
+/// 3. given an index l, r, query the sum of array in range [l, r]
+///
+/// Every node tracks its maximum, strict second maximum and the number
+/// of elements equal to the maximum. A chmin with x between the second
+/// maximum and the maximum only lowers the maximums, so the node can be
+/// updated in O(1) without descending further. The amortized cost of
+/// every operation is O(log^2 n).
+pub struct SegmentTreeBeats {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of nodes[p]
+    // is child nodes[p * 2] and nodes[p * 2 + 1]
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    sum: i64,
+    max: i64,
+    // largest value strictly less than max, i64::MIN if there is none
+    second_max: i64,
+    max_count: usize,
+    // pending add that has not been propagated to the child nodes
+    add: i64,
+}
+
+impl Node {
+    fn leaf(value: i64) -> Self {
+        Node {
+            sum: value,
+            max: value,
+            second_max: i64::MIN,
+            max_count: 1,
+            add: 0,
+        }
+    }
+
+    fn merge(left: &Node, right: &Node) -> Self {
+        let (max, max_count, second_max) = if left.max == right.max {
+            (
+                left.max,
+                left.max_count + right.max_count,
+                left.second_max.max(right.second_max),
+            )
+        } else if left.max > right.max {
+            (left.max, left.max_count, left.second_max.max(right.max))
+        } else {
+            (right.max, right.max_count, right.second_max.max(left.max))
+        };
+        Node {
+            sum: left.sum + right.sum,
+            max,
+            second_max,
+            max_count,
+            add: 0,
+        }
+    }
+}
+
+impl SegmentTreeBeats {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        let n = values.len();
+        let mut tree = Self {
+            len: n,
+            nodes: vec![Node::leaf(0); calculate_length(n)],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i64], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.nodes[p] = Node::leaf(values[left - 1]);
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.pull(p);
+    }
+
+    fn pull(&mut self, p: usize) {
+        self.nodes[p] = Node::merge(&self.nodes[p * 2], &self.nodes[p * 2 + 1]);
+    }
+
+    // add delta to every element covered by node p of length elements
+    fn apply_add(&mut self, p: usize, length: usize, delta: i64) {
+        let node = &mut self.nodes[p];
+        node.sum += delta * length as i64;
+        node.max += delta;
+        if node.second_max != i64::MIN {
+            node.second_max += delta;
+        }
+        node.add += delta;
+    }
+
+    // lower the maximum of node p to x, requires second_max < x
+    fn apply_chmin(&mut self, p: usize, x: i64) {
+        let node = &mut self.nodes[p];
+        if x < node.max {
+            node.sum -= (node.max - x) * node.max_count as i64;
+            node.max = x;
+        }
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let add = self.nodes[p].add;
+        if add != 0 {
+            self.apply_add(p * 2, length.div_ceil(2), add);
+            self.apply_add(p * 2 + 1, length / 2, add);
+            self.nodes[p].add = 0;
+        }
+        // a pending chmin is the parent's max being below a child's max,
+        // and since node p's second max is below it, so are the children's
+        let max = self.nodes[p].max;
+        self.apply_chmin(p * 2, max);
+        self.apply_chmin(p * 2 + 1, max);
+    }
+
+    /// set every element in range [l, r] to min(array[i], x)
+    pub fn chmin(&mut self, l: usize, r: usize, x: i64) {
+        check_range(l, r, self.len).expect("chmin range out of bounds");
+        self.chmin_rec(l, r, 1, self.len, 1, x)
+    }
+
+    fn chmin_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, x: i64) {
+        if cl > r || cr < l || self.nodes[p].max <= x {
+            return;
+        }
+        if cl >= l && cr <= r && self.nodes[p].second_max < x {
+            self.apply_chmin(p, x);
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.chmin_rec(l, r, cl, mid, p * 2, x);
+        self.chmin_rec(l, r, mid + 1, cr, p * 2 + 1, x);
+        self.pull(p);
+    }
+
+    /// add delta to every element in range [l, r]
+    pub fn add(&mut self, l: usize, r: usize, delta: i64) {
+        check_range(l, r, self.len).expect("add range out of bounds");
+        self.add_rec(l, r, 1, self.len, 1, delta)
+    }
+
+    fn add_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, delta: i64) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply_add(p, cr - cl + 1, delta);
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.add_rec(l, r, cl, mid, p * 2, delta);
+        self.add_rec(l, r, mid + 1, cr, p * 2 + 1, delta);
+        self.pull(p);
+    }
+
+    /// return the range sum of array[l]..array[r] inclusive
+    pub fn range_sum(&mut self, l: usize, r: usize) -> i64 {
+        check_range(l, r, self.len).expect("query range out of bounds");
+        self.sum_rec(l, r, 1, self.len, 1)
+    }
+
+    fn sum_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i64 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.nodes[p].sum;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.sum_rec(l, r, cl, mid, p * 2) + self.sum_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+}
+
+/// The mirror image of `SegmentTreeBeats`, supporting:
+/// 1. given a range [l, r] and x, set every array[i] in range
+///    to max(array[i], x)
+/// 2. given a range [l, r] and a delta, add delta to every
+///    element of array in range
+/// 3. given an index l, r, query the sum of array in range [l, r]
+///
+/// Every node tracks its minimum, strict second minimum and the number
+/// of elements equal to the minimum, and a chmax with x between the
+/// minimum and the second minimum only raises the minimums. Supporting
+/// chmin and chmax in one tree needs both sets of fields and a careful
+/// case analysis for segments of one or two distinct values, so they
+/// are separate trees.
+pub struct ChmaxSegmentTreeBeats {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of nodes[p]
+    // is child nodes[p * 2] and nodes[p * 2 + 1]
+    nodes: Vec<MinNode>,
+}
+
+#[derive(Clone, Copy)]
+struct MinNode {
+    sum: i64,
+    min: i64,
+    // smallest value strictly greater than min, i64::MAX if there is none
+    second_min: i64,
+    min_count: usize,
+    // pending add that has not been propagated to the child nodes
+    add: i64,
+}
+
+impl MinNode {
+    fn leaf(value: i64) -> Self {
+        MinNode {
+            sum: value,
+            min: value,
+            second_min: i64::MAX,
+            min_count: 1,
+            add: 0,
+        }
+    }
+
+    fn merge(left: &MinNode, right: &MinNode) -> Self {
+        let (min, min_count, second_min) = if left.min == right.min {
+            (
+                left.min,
+                left.min_count + right.min_count,
+                left.second_min.min(right.second_min),
+            )
+        } else if left.min < right.min {
+            (left.min, left.min_count, left.second_min.min(right.min))
+        } else {
+            (right.min, right.min_count, right.second_min.min(left.min))
+        };
+        MinNode {
+            sum: left.sum + right.sum,
+            min,
+            second_min,
+            min_count,
+            add: 0,
+        }
+    }
+}
+
+impl ChmaxSegmentTreeBeats {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        let n = values.len();
+        let mut tree = Self {
+            len: n,
+            nodes: vec![MinNode::leaf(0); calculate_length(n)],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i64], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.nodes[p] = MinNode::leaf(values[left - 1]);
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.pull(p);
+    }
+
+    fn pull(&mut self, p: usize) {
+        self.nodes[p] = MinNode::merge(&self.nodes[p * 2], &self.nodes[p * 2 + 1]);
+    }
+
+    // add delta to every element covered by node p of length elements
+    fn apply_add(&mut self, p: usize, length: usize, delta: i64) {
+        let node = &mut self.nodes[p];
+        node.sum += delta * length as i64;
+        node.min += delta;
+        if node.second_min != i64::MAX {
+            node.second_min += delta;
+        }
+        node.add += delta;
+    }
+
+    // raise the minimum of node p to x, requires x < second_min
+    fn apply_chmax(&mut self, p: usize, x: i64) {
+        let node = &mut self.nodes[p];
+        if x > node.min {
+            node.sum += (x - node.min) * node.min_count as i64;
+            node.min = x;
+        }
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let add = self.nodes[p].add;
+        if add != 0 {
+            self.apply_add(p * 2, length.div_ceil(2), add);
+            self.apply_add(p * 2 + 1, length / 2, add);
+            self.nodes[p].add = 0;
+        }
+        // a pending chmax is the parent's min being above a child's min,
+        // and since node p's second min is above it, so are the children's
+        let min = self.nodes[p].min;
+        self.apply_chmax(p * 2, min);
+        self.apply_chmax(p * 2 + 1, min);
+    }
+
+    /// set every element in range [l, r] to max(array[i], x)
+    pub fn chmax(&mut self, l: usize, r: usize, x: i64) {
+        check_range(l, r, self.len).expect("chmax range out of bounds");
+        self.chmax_rec(l, r, 1, self.len, 1, x)
+    }
+
+    fn chmax_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, x: i64) {
+        if cl > r || cr < l || self.nodes[p].min >= x {
+            return;
+        }
+        if cl >= l && cr <= r && self.nodes[p].second_min > x {
+            self.apply_chmax(p, x);
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.chmax_rec(l, r, cl, mid, p * 2, x);
+        self.chmax_rec(l, r, mid + 1, cr, p * 2 + 1, x);
+        self.pull(p);
+    }
+
+    /// add delta to every element in range [l, r]
+    pub fn add(&mut self, l: usize, r: usize, delta: i64) {
+        check_range(l, r, self.len).expect("add range out of bounds");
+        self.add_rec(l, r, 1, self.len, 1, delta)
+    }
+
+    fn add_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, delta: i64) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply_add(p, cr - cl + 1, delta);
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.add_rec(l, r, cl, mid, p * 2, delta);
+        self.add_rec(l, r, mid + 1, cr, p * 2 + 1, delta);
+        self.pull(p);
+    }
+
+    /// return the range sum of array[l]..array[r] inclusive
+    pub fn range_sum(&mut self, l: usize, r: usize) -> i64 {
+        check_range(l, r, self.len).expect("query range out of bounds");
+        self.sum_rec(l, r, 1, self.len, 1)
+    }
+
+    fn sum_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i64 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.nodes[p].sum;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.sum_rec(l, r, cl, mid, p * 2) + self.sum_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_chmin() {
+        let values = [5, 1, 4, 1, 5, 9, 2, 6];
+        let mut seg_tree = SegmentTreeBeats::from_vec(&values);
+        assert_eq!(33, seg_tree.range_sum(1, 8));
+        seg_tree.chmin(1, 8, 4);
+        // new values should be [4, 1, 4, 1, 4, 4, 2, 4]
+        assert_eq!(24, seg_tree.range_sum(1, 8));
+        assert_eq!(5, seg_tree.range_sum(1, 2));
+        seg_tree.add(3, 6, 2);
+        // new values should be [4, 1, 6, 3, 6, 6, 2, 4]
+        assert_eq!(32, seg_tree.range_sum(1, 8));
+        seg_tree.chmin(2, 7, 3);
+        // new values should be [4, 1, 3, 3, 3, 3, 2, 4]
+        assert_eq!(23, seg_tree.range_sum(1, 8));
+        assert_eq!(12, seg_tree.range_sum(3, 6));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(299792);
+        for &n in &[1usize, 2, 13, 100] {
+            let mut values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 1000).collect();
+            let mut seg_tree = SegmentTreeBeats::from_vec(&values);
+            for _ in 0..3000 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                match rng.get_u32() % 3 {
+                    0 => {
+                        let x = rng.get_u32() as i64 % 1000;
+                        seg_tree.chmin(l, r, x);
+                        for v in &mut values[l - 1..r] {
+                            *v = (*v).min(x);
+                        }
+                    }
+                    1 => {
+                        let delta = rng.get_u32() as i64 % 200 - 100;
+                        seg_tree.add(l, r, delta);
+                        for v in &mut values[l - 1..r] {
+                            *v += delta;
+                        }
+                    }
+                    _ => {
+                        let expected: i64 = values[l - 1..r].iter().sum();
+                        assert_eq!(expected, seg_tree.range_sum(l, r));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chmax() {
+        let values = [5, 1, 4, 1, 5, 9, 2, 6];
+        let mut seg_tree = ChmaxSegmentTreeBeats::from_vec(&values);
+        assert_eq!(33, seg_tree.range_sum(1, 8));
+        seg_tree.chmax(1, 8, 4);
+        // new values should be [5, 4, 4, 4, 5, 9, 4, 6]
+        assert_eq!(41, seg_tree.range_sum(1, 8));
+        assert_eq!(9, seg_tree.range_sum(1, 2));
+        seg_tree.add(3, 6, -2);
+        // new values should be [5, 4, 2, 2, 3, 7, 4, 6]
+        assert_eq!(33, seg_tree.range_sum(1, 8));
+        seg_tree.chmax(2, 7, 3);
+        // new values should be [5, 4, 3, 3, 3, 7, 4, 6]
+        assert_eq!(35, seg_tree.range_sum(1, 8));
+        assert_eq!(16, seg_tree.range_sum(3, 6));
+        // a bound below every element changes nothing
+        seg_tree.chmax(1, 8, -10);
+        assert_eq!(35, seg_tree.range_sum(1, 8));
+    }
+
+    #[test]
+    fn test_chmax_against_brute_force() {
+        let mut rng = PCG32::new_default(662607);
+        for &n in &[1usize, 2, 13, 100] {
+            let mut values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 1000).collect();
+            let mut seg_tree = ChmaxSegmentTreeBeats::from_vec(&values);
+            for _ in 0..3000 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                match rng.get_u32() % 3 {
+                    0 => {
+                        let x = rng.get_u32() as i64 % 1000;
+                        seg_tree.chmax(l, r, x);
+                        for v in &mut values[l - 1..r] {
+                            *v = (*v).max(x);
+                        }
+                    }
+                    1 => {
+                        let delta = rng.get_u32() as i64 % 200 - 100;
+                        seg_tree.add(l, r, delta);
+                        for v in &mut values[l - 1..r] {
+                            *v += delta;
+                        }
+                    }
+                    _ => {
+                        let expected: i64 = values[l - 1..r].iter().sum();
+                        assert_eq!(expected, seg_tree.range_sum(l, r));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use super::segment_tree_lazy::{Action, LazySegmentTree};
+use super::segment_tree_monoid::{Monoid, MonoidSegmentTree};
+use super::segment_tree_sum::check_range;
+
+// generate a monoid for a bitwise operator, and a segment tree
+// type over u64 built on top of it
+macro_rules! bitwise_segment_tree {
+    ($(#[$doc:meta])* $tree:ident, $monoid:ident, $identity:expr, $op:tt) => {
+        struct $monoid;
+
+        impl Monoid for $monoid {
+            type Value = u64;
+
+            fn identity() -> u64 {
+                $identity
+            }
+
+            fn combine(a: &u64, b: &u64) -> u64 {
+                a $op b
+            }
+        }
+
+        $(#[$doc])*
+        pub struct $tree {
+            tree: MonoidSegmentTree<$monoid>,
+        }
+
+        impl $tree {
+            /// build tree from an array of values
+            pub fn from_vec(values: &[u64]) -> Self {
+                Self {
+                    tree: MonoidSegmentTree::from_vec(values),
+                }
+            }
+
+            /// set array[i] to value
+            pub fn set(&mut self, i: usize, value: u64) {
+                check_range(i, i, self.tree.len()).expect("index out of bounds");
+                self.tree.set(i - 1, value)
+            }
+
+            /// return the fold of array[i]..array[j] inclusive
+            pub fn query(&self, i: usize, j: usize) -> u64 {
+                check_range(i, j, self.tree.len()).expect("query range out of bounds");
+                self.tree.query(i - 1..j)
+            }
+        }
+    };
+}
+
+/// This segment tree is built on an array of u64,
+/// and supports the following operation:
+/// 1. given an index i, j, query the bitwise xor of array in range [i, j]
+/// 2. given a range [x, y] and a value, xor value into every element
+///    of the array in range
+/// 3. given an index i and a value, set array[i] to value
+///
+/// The lazy tag of a node is the xor of every value applied to it, and
+/// tags compose by xor. Applying x to each of an even number of elements
+/// cancels out in their xor, so a tag only changes the xor of a node
+/// covering an odd number of elements.
+pub struct RangeXorSegmentTree {
+    tree: LazySegmentTree<Xor, Xor>,
+}
+
+struct Xor;
+
+impl Monoid for Xor {
+    type Value = u64;
+
+    fn identity() -> u64 {
+        0
+    }
+
+    fn combine(a: &u64, b: &u64) -> u64 {
+        a ^ b
+    }
+}
+
+impl Action<Xor> for Xor {
+    fn apply(value: &u64, x: &u64, len: usize) -> u64 {
+        if len % 2 == 1 {
+            value ^ x
+        } else {
+            *value
+        }
+    }
+}
+
+impl RangeXorSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[u64]) -> Self {
+        Self {
+            tree: LazySegmentTree::from_vec(values),
+        }
+    }
+
+    /// xor x into every element of array[i]..array[j] inclusive
+    pub fn update(&mut self, i: usize, j: usize, x: u64) {
+        check_range(i, j, self.tree.len()).expect("update range out of bounds");
+        self.tree.update(i - 1..j, x)
+    }
+
+    /// set array[i] to value
+    pub fn set(&mut self, i: usize, value: u64) {
+        check_range(i, i, self.tree.len()).expect("index out of bounds");
+        let old = self.tree.query(i - 1..i);
+        self.tree.update(i - 1..i, old ^ value)
+    }
+
+    /// return the xor of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> u64 {
+        check_range(i, j, self.tree.len()).expect("query range out of bounds");
+        self.tree.query(i - 1..j)
+    }
+}
+
+bitwise_segment_tree!(
+    /// This segment tree is built on an array of u64, and supports
+    /// querying the bitwise and of array in range [i, j] and setting
+    /// array[i] to a value.
+    RangeAndSegmentTree,
+    And,
+    u64::MAX,
+    &
+);
+
+bitwise_segment_tree!(
+    /// This segment tree is built on an array of u64, and supports
+    /// querying the bitwise or of array in range [i, j] and setting
+    /// array[i] to a value.
+    RangeOrSegmentTree,
+    Or,
+    0,
+    |
+);
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_xor() {
+        let values = [0b1010, 0b0110, 0b0110, 0b0001, 0b1111];
+        let mut seg_tree = RangeXorSegmentTree::from_vec(&values);
+        // the duplicated pair cancels out
+        assert_eq!(0, seg_tree.query(2, 3));
+        assert_eq!(0b1010, seg_tree.query(1, 3));
+        assert_eq!(0b1011, seg_tree.query(1, 4));
+        assert_eq!(0b0100, seg_tree.query(1, 5));
+        seg_tree.set(3, 0b0010);
+        assert_eq!(0b0100, seg_tree.query(2, 3));
+        assert_eq!(0b0000, seg_tree.query(1, 5));
+    }
+
+    #[test]
+    fn test_xor_update() {
+        let values = [0b0001, 0b0010, 0b0100, 0b1000, 0b0000, 0b1111];
+        let mut seg_tree = RangeXorSegmentTree::from_vec(&values);
+        // even length, the update cancels out in the xor of the range
+        seg_tree.update(1, 4, 0b1100);
+        // new values should be [0b1101, 0b1110, 0b1000, 0b0100, 0b0000, 0b1111]
+        assert_eq!(0b1111, seg_tree.query(1, 4));
+        assert_eq!(0b0011, seg_tree.query(1, 2));
+        // odd length
+        assert_eq!(0b1011, seg_tree.query(1, 3));
+        assert_eq!(0b0100, seg_tree.query(4, 4));
+        seg_tree.update(2, 6, 0b0001);
+        // new values should be [0b1101, 0b1111, 0b1001, 0b0101, 0b0001, 0b1110]
+        assert_eq!(0b1100, seg_tree.query(2, 6));
+        assert_eq!(0b0001, seg_tree.query(1, 6));
+        assert_eq!(0b1101, seg_tree.query(3, 5));
+        seg_tree.set(4, 0);
+        assert_eq!(0b1000, seg_tree.query(3, 5));
+    }
+
+    #[test]
+    fn test_xor_against_brute_force() {
+        let mut rng = PCG32::new_default(161803);
+        for &n in &[1usize, 6, 33, 64] {
+            let mut values: Vec<u64> = (0..n).map(|_| rng.get_u32() as u64).collect();
+            let mut seg_tree = RangeXorSegmentTree::from_vec(&values);
+            for _ in 0..500 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                if rng.get_u32().is_multiple_of(2) {
+                    let x = rng.get_u32() as u64;
+                    seg_tree.update(l, r, x);
+                    for value in &mut values[l - 1..r] {
+                        *value ^= x;
+                    }
+                } else {
+                    let expected = values[l - 1..r].iter().fold(0, |acc, v| acc ^ v);
+                    assert_eq!(expected, seg_tree.query(l, r));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_and() {
+        let values = [0b1110, 0b0111, 0b1111, u64::MAX];
+        let mut seg_tree = RangeAndSegmentTree::from_vec(&values);
+        assert_eq!(0b0110, seg_tree.query(1, 2));
+        assert_eq!(0b0110, seg_tree.query(1, 4));
+        assert_eq!(0b1111, seg_tree.query(3, 4));
+        assert_eq!(u64::MAX, seg_tree.query(4, 4));
+        seg_tree.set(1, 0b0011);
+        assert_eq!(0b0011, seg_tree.query(1, 3));
+    }
+
+    #[test]
+    fn test_or() {
+        let values = [0b0001, 0b0010, 0b0000, 0b1000];
+        let mut seg_tree = RangeOrSegmentTree::from_vec(&values);
+        assert_eq!(0b0011, seg_tree.query(1, 2));
+        assert_eq!(0b1011, seg_tree.query(1, 4));
+        assert_eq!(0b0000, seg_tree.query(3, 3));
+        seg_tree.set(3, 0b0100);
+        assert_eq!(0b1100, seg_tree.query(3, 4));
+        assert_eq!(0b1111, seg_tree.query(1, 4));
+    }
+}
@@ -0,0 +1,327 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// This segment tree is built on an array of bool,
+/// and supports the following operation:
+/// 1. given an index i and a bool b, set array[i] to b
+/// 2. given an index i, j, test whether any or all of array[i]..array[j]
+///    are set
+/// 3. given an index i, j, count the set elements in range [i, j]
+/// 4. given an index i, j, flip every element in range [i, j]
+///
+/// Every node stores the number of set elements of its segment, so a
+/// segment has one set exactly when its count is positive and all set
+/// exactly when its count is its length. `range_any` and `range_all`
+/// stop at the first segment that decides the answer instead of adding
+/// up the whole range. A count fits in a u32, which keeps the tree at
+/// half the size of a `RangeSumSegmentTree<usize>` over 0/1 values.
+///
+/// Flipping a segment of length len turns its count c into len - c.
+/// Pending flips are kept as a bool per node, and a second flip of the
+/// same node cancels the first, so tags compose by xor.
+pub struct BoolSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1]
+    // arr[p] is the number of set elements covered by node p
+    arr: Vec<u32>,
+    // implement lazy propagation
+    // flip[p] is true if node p has been flipped but the
+    // flip has not been propagated to its child nodes
+    flip: Vec<bool>,
+}
+
+impl BoolSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[bool]) -> Self {
+        let n = values.len();
+        assert!(n <= u32::MAX as usize, "too many elements");
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![0; length],
+            flip: vec![false; length],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[bool], cl: usize, cr: usize, p: usize) {
+        if cl == cr {
+            self.arr[p] = values[cl - 1] as u32;
+            return;
+        }
+        let mid = cl + (cr - cl) / 2;
+        self.build_rec(values, cl, mid, p * 2);
+        self.build_rec(values, mid + 1, cr, p * 2 + 1);
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// set array[i] to b
+    pub fn set(&mut self, i: usize, b: bool) {
+        check_range(i, i, self.len).expect("index out of bounds");
+        self.set_rec(i, 1, self.len, 1, b)
+    }
+
+    fn set_rec(&mut self, i: usize, cl: usize, cr: usize, p: usize, b: bool) {
+        if cl == cr {
+            self.arr[p] = b as u32;
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        if i <= mid {
+            self.set_rec(i, cl, mid, p * 2, b);
+        } else {
+            self.set_rec(i, mid + 1, cr, p * 2 + 1, b);
+        }
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+    }
+
+    fn apply_flip(&mut self, p: usize, length: usize) {
+        self.arr[p] = length as u32 - self.arr[p];
+        self.flip[p] = !self.flip[p];
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        if std::mem::take(&mut self.flip[p]) {
+            self.apply_flip(p * 2, length.div_ceil(2));
+            self.apply_flip(p * 2 + 1, length / 2);
+        }
+    }
+
+    /// flip every element in range [i, j]
+    pub fn range_flip(&mut self, i: usize, j: usize) {
+        check_range(i, j, self.len).expect("update range out of bounds");
+        self.flip_rec(i, j, 1, self.len, 1)
+    }
+
+    fn flip_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply_flip(p, cr - cl + 1);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.flip_rec(l, r, cl, mid, p * 2);
+        self.flip_rec(l, r, mid + 1, cr, p * 2 + 1);
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+    }
+
+    /// return the number of set elements in array[i]..array[j] inclusive
+    pub fn count_set(&mut self, i: usize, j: usize) -> usize {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.count_rec(i, j, 1, self.len, 1) as usize
+    }
+
+    fn count_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> u32 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.count_rec(l, r, cl, mid, p * 2) + self.count_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+
+    /// return true if any of array[i]..array[j] inclusive is set
+    pub fn range_any(&mut self, i: usize, j: usize) -> bool {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.any_rec(i, j, 1, self.len, 1)
+    }
+
+    fn any_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> bool {
+        // nothing below an empty node is set
+        if cl > r || cr < l || self.arr[p] == 0 {
+            return false;
+        }
+        if cl >= l && cr <= r {
+            return true;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.any_rec(l, r, cl, mid, p * 2) || self.any_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+
+    /// return true if all of array[i]..array[j] inclusive are set
+    pub fn range_all(&mut self, i: usize, j: usize) -> bool {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.all_rec(i, j, 1, self.len, 1)
+    }
+
+    fn all_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> bool {
+        // everything below a full node is set
+        if cl > r || cr < l || self.arr[p] as usize == cr - cl + 1 {
+            return true;
+        }
+        if cl >= l && cr <= r {
+            return false;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.all_rec(l, r, cl, mid, p * 2) && self.all_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_set_and_query() {
+        let mut values = [false; 10];
+        for v in values[2..6].iter_mut() {
+            *v = true;
+        }
+        // [0, 0, 1, 1, 1, 1, 0, 0, 0, 0]
+        let mut seg_tree = BoolSegmentTree::from_vec(&values);
+        assert_eq!(10, seg_tree.len());
+        assert_eq!(4, seg_tree.count_set(1, 10));
+        assert!(seg_tree.range_all(3, 6));
+        assert!(!seg_tree.range_all(2, 6));
+        assert!(seg_tree.range_any(2, 3));
+        assert!(!seg_tree.range_any(1, 2));
+        assert!(!seg_tree.range_any(7, 10));
+        assert_eq!(2, seg_tree.count_set(5, 9));
+
+        seg_tree.set(4, false);
+        seg_tree.set(9, true);
+        // [0, 0, 1, 0, 1, 1, 0, 0, 1, 0]
+        assert_eq!(4, seg_tree.count_set(1, 10));
+        assert!(!seg_tree.range_all(3, 6));
+        assert!(seg_tree.range_all(5, 6));
+        assert!(seg_tree.range_any(7, 10));
+        assert!(!seg_tree.range_any(4, 4));
+        assert!(seg_tree.range_all(9, 9));
+
+        // setting a bit that is already set changes nothing
+        seg_tree.set(9, true);
+        assert_eq!(1, seg_tree.count_set(7, 10));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(4242);
+        let n = 50;
+        let mut values: Vec<bool> = (0..n).map(|_| rng.get_u32() % 4 != 0).collect();
+        let mut seg_tree = BoolSegmentTree::from_vec(&values);
+        for _ in 0..1000 {
+            let a = rng.get_u32() as usize % n + 1;
+            let b = rng.get_u32() as usize % n + 1;
+            let (l, r) = (a.min(b), a.max(b));
+            if rng.get_u32().is_multiple_of(2) {
+                let bit = rng.get_u32() % 4 != 0;
+                seg_tree.set(a, bit);
+                values[a - 1] = bit;
+            } else {
+                let range = &values[l - 1..r];
+                let count = range.iter().filter(|&&v| v).count();
+                assert_eq!(count, seg_tree.count_set(l, r));
+                assert_eq!(count > 0, seg_tree.range_any(l, r));
+                assert_eq!(count == r - l + 1, seg_tree.range_all(l, r));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_flip() {
+        // [1, 1, 1, 1, 0, 0, 0, 0]
+        let values = [true, true, true, true, false, false, false, false];
+        let mut seg_tree = BoolSegmentTree::from_vec(&values);
+        seg_tree.range_flip(3, 6);
+        // [1, 1, 0, 0, 1, 1, 0, 0]
+        assert_eq!(4, seg_tree.count_set(1, 8));
+        assert_eq!(2, seg_tree.count_set(2, 5));
+        assert!(!seg_tree.range_any(3, 4));
+        assert!(seg_tree.range_all(5, 6));
+
+        seg_tree.range_flip(1, 8);
+        // [0, 0, 1, 1, 0, 0, 1, 1]
+        assert!(seg_tree.range_all(3, 4));
+        assert!(seg_tree.range_all(7, 8));
+        assert!(!seg_tree.range_any(1, 2));
+        assert_eq!(2, seg_tree.count_set(4, 7));
+
+        // flipping a range twice is a no-op
+        seg_tree.range_flip(2, 7);
+        seg_tree.range_flip(2, 7);
+        assert_eq!(4, seg_tree.count_set(1, 8));
+        assert!(seg_tree.range_all(3, 4));
+        assert!(!seg_tree.range_any(5, 6));
+
+        // a set under a pending flip overrides it
+        seg_tree.range_flip(1, 4);
+        seg_tree.set(2, false);
+        // [1, 0, 0, 0, 0, 0, 1, 1]
+        assert_eq!(1, seg_tree.count_set(1, 4));
+        assert_eq!(3, seg_tree.count_set(1, 8));
+    }
+
+    #[test]
+    fn test_range_flip_against_brute_force() {
+        let mut rng = PCG32::new_default(1357);
+        let n = 45;
+        let mut values: Vec<bool> = (0..n).map(|_| rng.get_u32().is_multiple_of(2)).collect();
+        let mut seg_tree = BoolSegmentTree::from_vec(&values);
+        for _ in 0..1000 {
+            let a = rng.get_u32() as usize % n + 1;
+            let b = rng.get_u32() as usize % n + 1;
+            let (l, r) = (a.min(b), a.max(b));
+            match rng.get_u32() % 4 {
+                0 => {
+                    seg_tree.range_flip(l, r);
+                    for v in values[l - 1..r].iter_mut() {
+                        *v = !*v;
+                    }
+                }
+                1 => {
+                    let bit = rng.get_u32().is_multiple_of(2);
+                    seg_tree.set(a, bit);
+                    values[a - 1] = bit;
+                }
+                _ => {}
+            }
+            let range = &values[l - 1..r];
+            let count = range.iter().filter(|&&v| v).count();
+            assert_eq!(count, seg_tree.count_set(l, r));
+            assert_eq!(count > 0, seg_tree.range_any(l, r));
+            assert_eq!(count == r - l + 1, seg_tree.range_all(l, r));
+            let total = values.iter().filter(|&&v| v).count();
+            assert_eq!(total, seg_tree.count_set(1, n));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_set_out_of_bounds() {
+        let mut seg_tree = BoolSegmentTree::from_vec(&[true, false]);
+        seg_tree.set(3, true);
+    }
+
+    #[test]
+    fn test_empty() {
+        let seg_tree = BoolSegmentTree::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+    }
+}
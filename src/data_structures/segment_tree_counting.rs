@@ -0,0 +1,226 @@
+use super::segment_tree_sum::calculate_length;
+
+/// A multiset of values in [0, max), stored as a segment tree over
+/// the count of each value, and supports the following operation:
+/// 1. insert or erase one occurrence of a value
+/// 2. given a value v, count the elements smaller than v
+/// 3. given k, find the k-th smallest element
+///
+/// all in O(log max), like `OrderStatisticTree`. Every node holds the
+/// number of elements in its range of values, so the k-th smallest is
+/// found by walking down from the root: if the left child holds at
+/// least k elements the answer is in it, otherwise it is the
+/// (k - left)-th of the right child. The Fenwick tree needs binary
+/// lifting over power of two steps for the same search.
+pub struct CountingSegmentTree {
+    // number of values, which are [0, max)
+    max: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1], covering
+    // the 1-indexed range [1, max] at the root, where
+    // value v is at position v + 1
+    arr: Vec<usize>,
+}
+
+impl CountingSegmentTree {
+    /// create an empty multiset over the values [0, max)
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            arr: vec![0; calculate_length(max)],
+        }
+    }
+
+    /// return the number of elements, counting duplicates
+    pub fn len(&self) -> usize {
+        if self.max == 0 {
+            0
+        } else {
+            self.arr[1]
+        }
+    }
+
+    /// return true if the multiset is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// add one occurrence of value
+    pub fn insert(&mut self, value: usize) {
+        assert!(value < self.max, "value out of bounds");
+        self.for_path(value, |count| *count += 1);
+    }
+
+    /// remove one occurrence of value, returning false if it wasn't present
+    pub fn erase(&mut self, value: usize) -> bool {
+        assert!(value < self.max, "value out of bounds");
+        if self.count(value) == 0 {
+            return false;
+        }
+        self.for_path(value, |count| *count -= 1);
+        true
+    }
+
+    // call f on the count of every node from the root down to value
+    fn for_path<F: FnMut(&mut usize)>(&mut self, value: usize, mut f: F) {
+        let i = value + 1;
+        let (mut cl, mut cr, mut p) = (1, self.max, 1);
+        loop {
+            f(&mut self.arr[p]);
+            if cl == cr {
+                return;
+            }
+            let mid = cl + (cr - cl) / 2;
+            if i <= mid {
+                cr = mid;
+                p *= 2;
+            } else {
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+    }
+
+    /// return the number of occurrences of value
+    pub fn count(&self, value: usize) -> usize {
+        self.count_less(value + 1) - self.count_less(value)
+    }
+
+    /// return the number of elements strictly smaller than value
+    pub fn count_less(&self, value: usize) -> usize {
+        let value = value.min(self.max);
+        if value == 0 {
+            return 0;
+        }
+        // sum the left siblings on the path to the leaf at position
+        // value, which holds the largest value below the bound
+        let (mut cl, mut cr, mut p) = (1, self.max, 1);
+        let mut res = 0;
+        while cl != cr {
+            let mid = cl + (cr - cl) / 2;
+            if value <= mid {
+                cr = mid;
+                p *= 2;
+            } else {
+                res += self.arr[p * 2];
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+        res + self.arr[p]
+    }
+
+    /// return the k-th smallest element, 1-based, or
+    /// None if there are fewer than k elements
+    pub fn kth(&self, k: usize) -> Option<usize> {
+        if k == 0 || k > self.len() {
+            return None;
+        }
+        let (mut cl, mut cr, mut p) = (1, self.max, 1);
+        let mut remaining = k;
+        while cl != cr {
+            let mid = cl + (cr - cl) / 2;
+            if self.arr[p * 2] >= remaining {
+                cr = mid;
+                p *= 2;
+            } else {
+                remaining -= self.arr[p * 2];
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+        Some(cl - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use data_structures::OrderStatisticTree;
+    use math::PCG32;
+
+    #[test]
+    fn test_kth_and_count_less() {
+        let mut tree = CountingSegmentTree::new(10);
+        for &v in &[5, 1, 9, 5, 0, 3] {
+            tree.insert(v);
+        }
+        // multiset is {0, 1, 3, 5, 5, 9}
+        assert_eq!(6, tree.len());
+        assert_eq!(0, tree.count_less(0));
+        assert_eq!(1, tree.count_less(1));
+        assert_eq!(3, tree.count_less(4));
+        assert_eq!(3, tree.count_less(5));
+        assert_eq!(5, tree.count_less(6));
+        assert_eq!(6, tree.count_less(10));
+        assert_eq!(6, tree.count_less(100));
+        assert_eq!(2, tree.count(5));
+
+        let sorted: Vec<_> = (1..=6).map(|k| tree.kth(k).unwrap()).collect();
+        assert_eq!(vec![0, 1, 3, 5, 5, 9], sorted);
+        assert_eq!(None, tree.kth(0));
+        assert_eq!(None, tree.kth(7));
+
+        assert!(tree.erase(5));
+        assert!(tree.erase(0));
+        assert!(!tree.erase(0));
+        assert!(!tree.erase(8));
+        // multiset is {1, 3, 5, 9}
+        let sorted: Vec<_> = (1..=4).map(|k| tree.kth(k).unwrap()).collect();
+        assert_eq!(vec![1, 3, 5, 9], sorted);
+        assert_eq!(2, tree.count_less(5));
+        assert_eq!(4, tree.len());
+    }
+
+    #[test]
+    fn test_against_sorted_reference() {
+        let mut rng = PCG32::new_default(602214);
+        for &max in &[1usize, 2, 7, 64, 100] {
+            let mut tree = CountingSegmentTree::new(max);
+            let mut fenwick = OrderStatisticTree::new(max);
+            let mut reference: Vec<usize> = Vec::new();
+            for _ in 0..300 {
+                let v = rng.get_u32() as usize % max;
+                if rng.get_u32() % 3 == 0 {
+                    let present = reference.iter().position(|&x| x == v);
+                    assert_eq!(present.is_some(), tree.erase(v));
+                    if let Some(i) = present {
+                        reference.remove(i);
+                        fenwick.remove(v);
+                    }
+                } else {
+                    tree.insert(v);
+                    fenwick.insert(v);
+                    reference.push(v);
+                }
+                reference.sort_unstable();
+
+                assert_eq!(reference.len(), tree.len());
+                for (k, &value) in reference.iter().enumerate() {
+                    assert_eq!(Some(value), tree.kth(k + 1));
+                }
+                assert_eq!(None, tree.kth(reference.len() + 1));
+                let bound = rng.get_u32() as usize % (max + 1);
+                let less = reference.iter().filter(|&&x| x < bound).count();
+                assert_eq!(less, tree.count_less(bound));
+                assert_eq!(fenwick.rank(bound), tree.count_less(bound));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "value out of bounds")]
+    fn test_insert_out_of_bounds() {
+        let mut tree = CountingSegmentTree::new(4);
+        tree.insert(4);
+    }
+
+    #[test]
+    fn test_empty_domain() {
+        let tree = CountingSegmentTree::new(0);
+        assert!(tree.is_empty());
+        assert_eq!(None, tree.kth(1));
+        assert_eq!(0, tree.count_less(3));
+    }
+}
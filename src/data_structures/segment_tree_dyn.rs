@@ -0,0 +1,189 @@
+use super::segment_tree_sum::{calculate_length, SegRange};
+
+/// This segment tree stores an array of i32, and supports:
+/// 1. set the value of array[i]
+/// 2. fold array[range] with a combine function
+///
+/// like `MonoidSegmentTree`, but the combine function and its identity
+/// are values given to `new` instead of a `Monoid` type, so whether a
+/// tree sums, takes the minimum or the maximum can be decided at
+/// runtime, e.g. from a config file, and trees of different operations
+/// have the same type and fit in one Vec.
+///
+/// The price is that every combine is a call through a pointer the
+/// compiler can't see through, so it can't be inlined or vectorized,
+/// where `MonoidSegmentTree<M>` is compiled for its M and reduces to a
+/// plain addition or comparison. Builds and queries pay one indirect
+/// call per combine, so prefer the monoid tree when the operation is
+/// known at compile time.
+///
+/// Positions and ranges are 0-indexed, ranges are given as
+/// std ranges, e.g. `tree.query(2..5)` or `tree.query(..)`.
+pub struct DynSegmentTree {
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1], covering
+    // the 1-indexed range [1, len] at the root
+    arr: Vec<i32>,
+    identity: i32,
+    combine: Box<dyn Fn(i32, i32) -> i32>,
+}
+
+impl DynSegmentTree {
+    /// build tree from an array of values, where combine must be
+    /// associative and identity its identity element
+    pub fn new<F>(values: &[i32], identity: i32, combine: F) -> Self
+    where
+        F: Fn(i32, i32) -> i32 + 'static,
+    {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![identity; length],
+            identity,
+            combine: Box::new(combine),
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i32], cl: usize, cr: usize, p: usize) {
+        if cl == cr {
+            self.arr[p] = values[cl - 1];
+            return;
+        }
+        let mid = cl + (cr - cl) / 2;
+        self.build_rec(values, cl, mid, p * 2);
+        self.build_rec(values, mid + 1, cr, p * 2 + 1);
+        self.arr[p] = (self.combine)(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// set array[i] to value
+    pub fn set(&mut self, i: usize, value: i32) {
+        assert!(i < self.len, "index out of bounds");
+        self.set_rec(i + 1, 1, self.len, 1, value)
+    }
+
+    fn set_rec(&mut self, i: usize, cl: usize, cr: usize, p: usize, value: i32) {
+        if cl == cr {
+            self.arr[p] = value;
+            return;
+        }
+        let mid = cl + (cr - cl) / 2;
+        if i <= mid {
+            self.set_rec(i, cl, mid, p * 2, value);
+        } else {
+            self.set_rec(i, mid + 1, cr, p * 2 + 1, value);
+        }
+        self.arr[p] = (self.combine)(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    /// return the fold of array[range], or the identity if range is empty.
+    /// Panics if range is reversed or reaches past the end.
+    pub fn query<R: SegRange>(&self, range: R) -> i32 {
+        let (start, end) = range.bounds(self.len);
+        assert!(start <= end && end <= self.len, "query range out of bounds");
+        if start == end {
+            return self.identity;
+        }
+        self.query_rec(start + 1, end, 1, self.len, 1)
+    }
+
+    fn query_rec(&self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i32 {
+        if cl > r || cr < l {
+            return self.identity;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        let mid = cl + (cr - cl) / 2;
+        (self.combine)(
+            self.query_rec(l, r, cl, mid, p * 2),
+            self.query_rec(l, r, mid + 1, cr, p * 2 + 1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // what a config file might ask for
+    fn tree_for(op: &str, values: &[i32]) -> DynSegmentTree {
+        match op {
+            "sum" => DynSegmentTree::new(values, 0, |a, b| a + b),
+            "max" => DynSegmentTree::new(values, i32::MIN, |a, b| a.max(b)),
+            "min" => DynSegmentTree::new(values, i32::MAX, |a, b| a.min(b)),
+            _ => panic!("unknown operation"),
+        }
+    }
+
+    #[test]
+    fn test_sum_and_max_at_runtime() {
+        let mut values = vec![2, 4, 1, 3, 5, 7];
+        let mut trees: Vec<DynSegmentTree> = ["sum", "max"]
+            .iter()
+            .map(|op| tree_for(op, &values))
+            .collect();
+        assert_eq!(22, trees[0].query(..));
+        assert_eq!(7, trees[1].query(..));
+        assert_eq!(8, trees[0].query(1..4));
+        assert_eq!(4, trees[1].query(1..4));
+
+        for (i, value) in [(2, 10), (5, -1), (0, 6)] {
+            values[i] = value;
+            for tree in trees.iter_mut() {
+                tree.set(i, value);
+            }
+        }
+        for i in 0..6 {
+            for j in i + 1..=6 {
+                let range = &values[i..j];
+                assert_eq!(range.iter().sum::<i32>(), trees[0].query(i..j));
+                assert_eq!(*range.iter().max().unwrap(), trees[1].query(i..j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_range_is_identity() {
+        let values = [5, 2, 6];
+        assert_eq!(0, tree_for("sum", &values).query(1..1));
+        assert_eq!(i32::MAX, tree_for("min", &values).query(3..));
+        assert_eq!(2, tree_for("min", &values).query(..));
+        assert!(tree_for("max", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_captured_state() {
+        // the combine may be a closure over runtime values
+        let modulus = 7;
+        let mut tree = DynSegmentTree::new(&[3, 5, 6], 0, move |a, b| (a + b) % modulus);
+        assert_eq!(0, tree.query(..));
+        tree.set(1, 1);
+        assert_eq!(3, tree.query(..));
+        assert_eq!(3, tree.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "query range out of bounds")]
+    fn test_reversed_range() {
+        let seg_tree = tree_for("sum", &[1, 2, 3, 4, 5]);
+        let (start, end) = (4, 2);
+        seg_tree.query(start..end);
+    }
+}
@@ -0,0 +1,260 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// This segment tree supports the same operations as
+/// `RangeSumSegmentTree` over f64:
+/// 1. given an index i, j, query the sum of array in range [i, j]
+/// 2. given a range [x, y] and a diff, update all values of the array
+///    in range by diff
+///
+/// but keeps the rounding error of its additions. Adding a small
+/// value to a large one loses the low bits of the small one, and a
+/// plain f64 tree does that at every level of build, update and query,
+/// so 2^53 + 1 + 1 - 2^53 comes out as 0. Every node here stores its
+/// sum as an unevaluated pair sum + err, where err collects what the
+/// additions rounded off (Kahan-Babuska summation), and the pair is
+/// only rounded to one f64 when a query returns. The result is then
+/// about as accurate as rounding the exact sum once, independent of n,
+/// unless the error terms themselves lose precision, which takes
+/// cancellation on the order of 1 / f64::EPSILON.
+pub struct RangeSumSegmentTreeF64 {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1]
+    arr: Vec<Compensated>,
+    // implement lazy propagation
+    // mark[p] stores the diffs that have been applied
+    // to node p but has not been propagated to its child node
+    mark: Vec<Compensated>,
+}
+
+// the value sum + err, with err much smaller than sum
+#[derive(Clone, Copy, Default)]
+struct Compensated {
+    sum: f64,
+    err: f64,
+}
+
+// s and e with s + e == a + b exactly, where s is a + b rounded
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let b_part = s - a;
+    let a_part = s - b_part;
+    (s, (a - a_part) + (b - b_part))
+}
+
+impl Compensated {
+    fn new(value: f64) -> Self {
+        Compensated {
+            sum: value,
+            err: 0.0,
+        }
+    }
+
+    fn add(self, other: Compensated) -> Self {
+        let (sum, err) = two_sum(self.sum, other.sum);
+        Compensated {
+            sum,
+            err: self.err + other.err + err,
+        }
+    }
+
+    fn scaled(self, n: usize) -> Self {
+        let n = n as f64;
+        let sum = self.sum * n;
+        // the rounding error of the product, exactly
+        let err = self.sum.mul_add(n, -sum);
+        Compensated {
+            sum,
+            err: err + self.err * n,
+        }
+    }
+
+    fn value(self) -> f64 {
+        self.sum + self.err
+    }
+}
+
+impl RangeSumSegmentTreeF64 {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[f64]) -> Self {
+        let n = values.len();
+        // our arr is 1-indexed
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![Compensated::default(); length],
+            mark: vec![Compensated::default(); length],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[f64], cl: usize, cr: usize, p: usize) {
+        if cl == cr {
+            self.arr[p] = Compensated::new(values[cl - 1]);
+            return;
+        }
+        let mid = cl + (cr - cl) / 2;
+        self.build_rec(values, cl, mid, p * 2);
+        self.build_rec(values, mid + 1, cr, p * 2 + 1);
+        self.arr[p] = self.arr[p * 2].add(self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn apply(&mut self, p: usize, length: usize, diff: Compensated) {
+        self.arr[p] = self.arr[p].add(diff.scaled(length));
+        self.mark[p] = self.mark[p].add(diff);
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let mark = std::mem::take(&mut self.mark[p]);
+        self.apply(p * 2, length.div_ceil(2), mark);
+        self.apply(p * 2 + 1, length / 2, mark);
+    }
+
+    /// add diff to all element in range [i, j]
+    pub fn update(&mut self, i: usize, j: usize, diff: f64) {
+        check_range(i, j, self.len).expect("update range out of bounds");
+        self.update_rec(i, j, 1, self.len, 1, Compensated::new(diff))
+    }
+
+    fn update_rec(
+        &mut self,
+        l: usize,
+        r: usize,
+        cl: usize,
+        cr: usize,
+        p: usize,
+        diff: Compensated,
+    ) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, cr - cl + 1, diff);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, diff);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, diff);
+        self.arr[p] = self.arr[p * 2].add(self.arr[p * 2 + 1]);
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> f64 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1).value()
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> Compensated {
+        if cl > r || cr < l {
+            return Compensated::default();
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        let left = self.query_rec(l, r, cl, mid, p * 2);
+        let right = self.query_rec(l, r, mid + 1, cr, p * 2 + 1);
+        left.add(right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_small_values_next_to_large_ones() {
+        let big = 2f64.powi(53);
+        let mut values = vec![big];
+        values.extend(std::iter::repeat_n(1.0, 1000));
+        values.push(-big);
+        let n = values.len();
+
+        // plain summation rounds every 1 away
+        assert_eq!(0.0, values.iter().sum::<f64>());
+
+        let mut seg_tree = RangeSumSegmentTreeF64::from_vec(&values);
+        assert_eq!(n, seg_tree.len());
+        assert_eq!(1000.0, seg_tree.query(1, n));
+        assert_eq!(500.0, seg_tree.query(2, 501));
+        assert_eq!(big + 500.0, seg_tree.query(1, 501));
+
+        seg_tree.update(1, n, 0.5);
+        assert_eq!(1000.0 + 0.5 * n as f64, seg_tree.query(1, n));
+        seg_tree.update(1, 1, -big);
+        assert_eq!(1500.5 - big, seg_tree.query(2, n));
+    }
+
+    #[test]
+    fn test_against_exact_sums() {
+        // every value is a multiple of 2^-30, so an i128 of the value
+        // times 2^30 holds the exact sum of any number of them
+        let scale = 2f64.powi(30);
+        let mut rng = PCG32::new_default(271828);
+        let n = 500;
+        let mut exact: Vec<i128> = (0..n)
+            .map(|_| {
+                // at most 53 bits, so the f64 of every value is exact
+                let magnitude = rng.get_u32() % 44;
+                let units = (rng.get_u32() as i128) << (magnitude / 2);
+                if rng.get_u32().is_multiple_of(2) {
+                    units
+                } else {
+                    -units
+                }
+            })
+            .collect();
+        let values: Vec<f64> = exact.iter().map(|&units| units as f64 / scale).collect();
+        let mut seg_tree = RangeSumSegmentTreeF64::from_vec(&values);
+        for _ in 0..1000 {
+            let a = rng.get_u32() as usize % n + 1;
+            let b = rng.get_u32() as usize % n + 1;
+            let (l, r) = (a.min(b), a.max(b));
+            if rng.get_u32() % 3 == 0 {
+                let units = rng.get_u32() as i128 - (1 << 31);
+                seg_tree.update(l, r, units as f64 / scale);
+                for value in exact[l - 1..r].iter_mut() {
+                    *value += units;
+                }
+            } else {
+                let reference = exact[l - 1..r].iter().sum::<i128>() as f64 / scale;
+                let sum = seg_tree.query(l, r);
+                // at most one rounding away from the reference
+                assert!(
+                    (sum - reference).abs() <= reference.abs() * f64::EPSILON,
+                    "sum {} reference {}",
+                    sum,
+                    reference
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let seg_tree = RangeSumSegmentTreeF64::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+    }
+}
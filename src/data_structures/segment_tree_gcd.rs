@@ -0,0 +1,271 @@
+use super::fenwick_tree::FenwickTree;
+use super::segment_tree_monoid::{Monoid, MonoidSegmentTree};
+use super::segment_tree_sum::check_range;
+
+/// This segment tree is built on an array of u64,
+/// and supports the following operation:
+/// 1. given an index i, j, query the gcd of array in range [i, j]
+/// 2. given an index i and a value, set array[i] to value
+///
+/// gcd doesn't distribute over addition, so range updates
+/// are not supported.
+pub struct RangeGcdSegmentTree {
+    tree: MonoidSegmentTree<Gcd>,
+}
+
+struct Gcd;
+
+// 0 is the identity of gcd, since every number divides 0
+impl Monoid for Gcd {
+    type Value = u64;
+
+    fn identity() -> u64 {
+        0
+    }
+
+    fn combine(a: &u64, b: &u64) -> u64 {
+        binary_gcd(*a, *b)
+    }
+}
+
+// Stein's algorithm, which replaces division with shifts
+fn binary_gcd(mut a: u64, mut b: u64) -> u64 {
+    if a == 0 {
+        return b;
+    }
+    if b == 0 {
+        return a;
+    }
+    // the power of two shared by a and b
+    let shift = (a | b).trailing_zeros();
+    a >>= a.trailing_zeros();
+    loop {
+        b >>= b.trailing_zeros();
+        if a > b {
+            std::mem::swap(&mut a, &mut b);
+        }
+        b -= a;
+        if b == 0 {
+            return a << shift;
+        }
+    }
+}
+
+impl RangeGcdSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[u64]) -> Self {
+        Self {
+            tree: MonoidSegmentTree::from_vec(values),
+        }
+    }
+
+    /// set array[i] to value
+    pub fn set(&mut self, i: usize, value: u64) {
+        check_range(i, i, self.tree.len()).expect("index out of bounds");
+        self.tree.set(i - 1, value)
+    }
+
+    /// return the gcd of array[i]..array[j] inclusive
+    pub fn query(&self, i: usize, j: usize) -> u64 {
+        check_range(i, j, self.tree.len()).expect("query range out of bounds");
+        self.tree.query(i - 1..j)
+    }
+}
+
+/// This segment tree is built on an array of i64,
+/// and supports the following operation:
+/// 1. given an index i, j, query the gcd of array in range [i, j]
+/// 2. given an index i, j and a value, add value to every element
+///    in range [i, j]
+///
+/// both in O(log n). Since gcd(a, b) = gcd(a, b - a), the gcd of
+/// array[i]..array[j] is the gcd of array[i] and the differences
+/// d[k] = array[k] - array[k - 1] for i < k <= j. A range add only
+/// changes d[i] and d[j + 1], so the tree keeps the gcd of the
+/// differences, next to a fenwick tree over them that recovers
+/// array[i] as a prefix sum. Gcds are of absolute values.
+pub struct RangeAddGcdSegmentTree {
+    diffs: Vec<i64>,
+    // gcd of |d| and prefix sums of d, both 0-indexed
+    gcd: MonoidSegmentTree<Gcd>,
+    values: FenwickTree<i64>,
+}
+
+impl RangeAddGcdSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        // d[0] is array[0] itself, an empty array has no differences
+        let mut diffs = Vec::with_capacity(values.len());
+        if let Some(&first) = values.first() {
+            diffs.push(first);
+            diffs.extend(values.windows(2).map(|w| w[1] - w[0]));
+        }
+        let abs_diffs: Vec<u64> = diffs.iter().map(|d| d.unsigned_abs()).collect();
+        Self {
+            gcd: MonoidSegmentTree::from_vec(&abs_diffs),
+            values: FenwickTree::from_vec(&diffs),
+            diffs,
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.diffs.len()
+    }
+
+    /// return true if the tree has no elements
+    pub fn is_empty(&self) -> bool {
+        self.diffs.is_empty()
+    }
+
+    fn add_diff(&mut self, k: usize, delta: i64) {
+        self.diffs[k] += delta;
+        self.gcd.set(k, self.diffs[k].unsigned_abs());
+        self.values.add(k, delta);
+    }
+
+    /// add delta to array[i]..array[j] inclusive
+    pub fn range_add(&mut self, i: usize, j: usize, delta: i64) {
+        check_range(i, j, self.len()).expect("add range out of bounds");
+        self.add_diff(i - 1, delta);
+        if j < self.len() {
+            self.add_diff(j, -delta);
+        }
+    }
+
+    /// return the gcd of array[i]..array[j] inclusive
+    pub fn gcd_query(&self, i: usize, j: usize) -> u64 {
+        check_range(i, j, self.len()).expect("query range out of bounds");
+        let first = self.values.prefix_sum(i - 1).unsigned_abs();
+        // d[i + 1]..d[j] in 1-indexed positions
+        binary_gcd(first, self.gcd.query(i..j))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_binary_gcd() {
+        assert_eq!(0, binary_gcd(0, 0));
+        assert_eq!(7, binary_gcd(0, 7));
+        assert_eq!(7, binary_gcd(7, 0));
+        assert_eq!(6, binary_gcd(12, 18));
+        assert_eq!(1, binary_gcd(17, 5));
+        assert_eq!(1 << 10, binary_gcd(3 << 10, 5 << 12));
+    }
+
+    #[test]
+    fn test_query() {
+        let values = [12, 18, 24, 30, 7, 14];
+        let seg_tree = RangeGcdSegmentTree::from_vec(&values);
+        // common factor of 6
+        assert_eq!(6, seg_tree.query(1, 4));
+        assert_eq!(6, seg_tree.query(2, 3));
+        assert_eq!(12, seg_tree.query(1, 1));
+        // coprime
+        assert_eq!(1, seg_tree.query(4, 5));
+        assert_eq!(1, seg_tree.query(1, 6));
+        assert_eq!(7, seg_tree.query(5, 6));
+    }
+
+    #[test]
+    fn test_set() {
+        let values = [12, 18, 24, 30, 7, 14];
+        let mut seg_tree = RangeGcdSegmentTree::from_vec(&values);
+        seg_tree.set(2, 36);
+        // new values should be [12, 36, 24, 30, 7, 14]
+        assert_eq!(12, seg_tree.query(1, 3));
+        assert_eq!(6, seg_tree.query(1, 4));
+        seg_tree.set(5, 42);
+        // new values should be [12, 36, 24, 30, 42, 14]
+        assert_eq!(2, seg_tree.query(1, 6));
+        assert_eq!(14, seg_tree.query(5, 6));
+    }
+
+    #[test]
+    fn test_large_values() {
+        let max = u64::MAX;
+        // u64::MAX = 3 * 5 * 17 * 257 * 641 * 65537 * 6700417
+        let values = [max, max / 3, max - 1, 1 << 63, (1 << 63) + (1 << 62)];
+        let mut seg_tree = RangeGcdSegmentTree::from_vec(&values);
+        assert_eq!(max, seg_tree.query(1, 1));
+        assert_eq!(max / 3, seg_tree.query(1, 2));
+        // max - 1 is even and max is odd
+        assert_eq!(1, seg_tree.query(1, 3));
+        assert_eq!(1 << 62, seg_tree.query(4, 5));
+        assert_eq!(2, seg_tree.query(3, 5));
+        seg_tree.set(3, max / 5);
+        assert_eq!(max / 15, seg_tree.query(1, 3));
+    }
+
+    #[test]
+    fn test_zeros() {
+        let values = [0, 0, 0, 12, 0, 18, 0, 0];
+        let mut seg_tree = RangeGcdSegmentTree::from_vec(&values);
+        // a range of zeros only has the identity as its gcd
+        assert_eq!(0, seg_tree.query(1, 3));
+        assert_eq!(0, seg_tree.query(7, 8));
+        assert_eq!(12, seg_tree.query(1, 4));
+        assert_eq!(6, seg_tree.query(2, 7));
+        assert_eq!(18, seg_tree.query(5, 8));
+        seg_tree.set(4, 0);
+        seg_tree.set(6, 0);
+        assert_eq!(0, seg_tree.query(1, 8));
+        seg_tree.set(8, u64::MAX);
+        assert_eq!(u64::MAX, seg_tree.query(1, 8));
+    }
+
+    #[test]
+    fn test_range_add_gcd() {
+        let mut seg_tree = RangeAddGcdSegmentTree::from_vec(&[12, 18, 24, 30, 7, 14]);
+        assert_eq!(6, seg_tree.gcd_query(1, 4));
+        assert_eq!(7, seg_tree.gcd_query(5, 6));
+        seg_tree.range_add(5, 6, -1);
+        // new values should be [12, 18, 24, 30, 6, 13]
+        assert_eq!(6, seg_tree.gcd_query(1, 5));
+        assert_eq!(13, seg_tree.gcd_query(6, 6));
+        seg_tree.range_add(1, 6, -18);
+        // new values should be [-6, 0, 6, 12, -12, -5]
+        assert_eq!(6, seg_tree.gcd_query(1, 5));
+        assert_eq!(6, seg_tree.gcd_query(2, 3));
+        assert_eq!(0, seg_tree.gcd_query(2, 2));
+        assert_eq!(1, seg_tree.gcd_query(1, 6));
+    }
+
+    #[test]
+    fn test_range_add_gcd_empty() {
+        let seg_tree = RangeAddGcdSegmentTree::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+        assert_eq!(0, seg_tree.len());
+    }
+
+    #[test]
+    fn test_range_add_gcd_against_brute_force() {
+        let mut rng = PCG32::new_default(314159);
+        let n = 40;
+        let mut values: Vec<i64> = (0..n).map(|_| (rng.get_u32() % 10 * 6) as i64).collect();
+        let mut seg_tree = RangeAddGcdSegmentTree::from_vec(&values);
+        for _ in 0..2000 {
+            let a = rng.get_u32() as usize % n + 1;
+            let b = rng.get_u32() as usize % n + 1;
+            let (l, r) = (a.min(b), a.max(b));
+            if rng.get_u32().is_multiple_of(2) {
+                // multiples of 3 keep some ranges with a common factor
+                let delta = (rng.get_u32() % 7) as i64 * 3 - 9;
+                seg_tree.range_add(l, r, delta);
+                for value in &mut values[l - 1..r] {
+                    *value += delta;
+                }
+            } else {
+                let expected = values[l - 1..r]
+                    .iter()
+                    .fold(0, |g, v| binary_gcd(g, v.unsigned_abs()));
+                assert_eq!(expected, seg_tree.gcd_query(l, r));
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// This segment tree is built on an array of i64, and supports the
+/// following operation:
+/// 1. given a range [i, j] and a delta, add delta to every element
+///    of array in range
+/// 2. given an index i, j, query the maximum of array in range [i, j]
+/// 3. given an index i, j, query the largest value any element of array
+///    in range [i, j] has held since the tree was built
+///
+/// Besides the pending add of a node, the lazy tag also keeps the largest
+/// prefix sum of the adds that make up the pending add. Elements below
+/// the node peaked at their current value plus that prefix sum, so the
+/// historic maximum survives adds being merged into a single tag.
+pub struct HistoricMaxSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of nodes[p]
+    // is child nodes[p * 2] and nodes[p * 2 + 1]
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Node {
+    max: i64,
+    historic_max: i64,
+    // pending add that has not been propagated to the child nodes
+    add: i64,
+    // largest prefix sum of the adds merged into add, at least 0
+    peak_add: i64,
+}
+
+impl HistoricMaxSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        let n = values.len();
+        let mut tree = Self {
+            len: n,
+            nodes: vec![Node::default(); calculate_length(n)],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i64], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.nodes[p].max = values[left - 1];
+            self.nodes[p].historic_max = values[left - 1];
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.pull(p);
+    }
+
+    /// add delta to all elements in range [i, j]
+    pub fn add(&mut self, i: usize, j: usize, delta: i64) {
+        check_range(i, j, self.len).expect("add range out of bounds");
+        self.add_rec(i, j, 1, self.len, 1, delta)
+    }
+
+    fn add_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, delta: i64) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, delta, delta.max(0));
+            return;
+        }
+        self.push_down(p);
+        let mid = cl + (cr - cl) / 2;
+        self.add_rec(l, r, cl, mid, p * 2, delta);
+        self.add_rec(l, r, mid + 1, cr, p * 2 + 1, delta);
+        self.pull(p);
+    }
+
+    // apply a sequence of adds summing to add, whose largest
+    // prefix sum is peak_add, after the tag already on node p
+    fn apply(&mut self, p: usize, add: i64, peak_add: i64) {
+        let node = &mut self.nodes[p];
+        node.historic_max = node.historic_max.max(node.max + peak_add);
+        node.peak_add = node.peak_add.max(node.add + peak_add);
+        node.max += add;
+        node.add += add;
+    }
+
+    fn push_down(&mut self, p: usize) {
+        let Node { add, peak_add, .. } = self.nodes[p];
+        if add != 0 || peak_add != 0 {
+            self.apply(p * 2, add, peak_add);
+            self.apply(p * 2 + 1, add, peak_add);
+            self.nodes[p].add = 0;
+            self.nodes[p].peak_add = 0;
+        }
+    }
+
+    fn pull(&mut self, p: usize) {
+        let (left, right) = (self.nodes[p * 2], self.nodes[p * 2 + 1]);
+        self.nodes[p].max = left.max.max(right.max);
+        self.nodes[p].historic_max = left.historic_max.max(right.historic_max);
+    }
+
+    /// return the maximum of array[i]..array[j] inclusive
+    pub fn query_max(&mut self, i: usize, j: usize) -> i64 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1).0
+    }
+
+    /// return the largest value array[i]..array[j] inclusive have ever held
+    pub fn query_historic_max(&mut self, i: usize, j: usize) -> i64 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1).1
+    }
+
+    // (max, historic max) over [l, r]
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> (i64, i64) {
+        if cl > r || cr < l {
+            return (i64::MIN, i64::MIN);
+        }
+        if cl >= l && cr <= r {
+            return (self.nodes[p].max, self.nodes[p].historic_max);
+        }
+        self.push_down(p);
+        let mid = cl + (cr - cl) / 2;
+        let left = self.query_rec(l, r, cl, mid, p * 2);
+        let right = self.query_rec(l, r, mid + 1, cr, p * 2 + 1);
+        (left.0.max(right.0), left.1.max(right.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_historic_max() {
+        let values = [1, 5, 2, 4, 3];
+        let mut seg_tree = HistoricMaxSegmentTree::from_vec(&values);
+        assert_eq!(5, seg_tree.query_max(1, 5));
+        assert_eq!(5, seg_tree.query_historic_max(1, 5));
+
+        seg_tree.add(3, 5, 10);
+        // values should be [1, 5, 12, 14, 13]
+        seg_tree.add(3, 5, -20);
+        // values should be [1, 5, -8, -6, -7]
+        assert_eq!(-6, seg_tree.query_max(3, 5));
+        assert_eq!(14, seg_tree.query_historic_max(3, 5));
+        assert_eq!(12, seg_tree.query_historic_max(3, 3));
+        assert_eq!(5, seg_tree.query_max(1, 5));
+        assert_eq!(14, seg_tree.query_historic_max(1, 5));
+
+        seg_tree.add(1, 3, 3);
+        // values should be [4, 8, -5, -6, -7]
+        assert_eq!(8, seg_tree.query_max(1, 5));
+        assert_eq!(8, seg_tree.query_historic_max(1, 2));
+        assert_eq!(12, seg_tree.query_historic_max(2, 3));
+        assert_eq!(-5, seg_tree.query_max(3, 5));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(141421);
+        for &n in &[1usize, 7, 50] {
+            let mut values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100).collect();
+            let mut peaks = values.clone();
+            let mut seg_tree = HistoricMaxSegmentTree::from_vec(&values);
+            for _ in 0..1000 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                match rng.get_u32() % 3 {
+                    0 => {
+                        let delta = rng.get_u32() as i64 % 41 - 20;
+                        seg_tree.add(l, r, delta);
+                        for k in l - 1..r {
+                            values[k] += delta;
+                            peaks[k] = peaks[k].max(values[k]);
+                        }
+                    }
+                    1 => assert_eq!(
+                        *values[l - 1..r].iter().max().unwrap(),
+                        seg_tree.query_max(l, r)
+                    ),
+                    _ => assert_eq!(
+                        *peaks[l - 1..r].iter().max().unwrap(),
+                        seg_tree.query_historic_max(l, r)
+                    ),
+                }
+            }
+        }
+    }
+}
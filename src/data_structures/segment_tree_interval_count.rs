@@ -0,0 +1,134 @@
+use super::segment_tree_lazy::{Action, LazySegmentTree};
+use super::segment_tree_monoid::Monoid;
+use super::segment_tree_sum::check_range;
+
+/// Counts how many intervals cover each position of [1, len], and
+/// supports the following operation:
+/// 1. given an interval [l, r], add it
+/// 2. given an interval [l, r] that was added before, remove it
+/// 3. given a position x, count the intervals covering x
+///
+/// all in O(log n), e.g. the number of bookings overlapping a time
+/// slot. Adding an interval is a range add of +1, removing one a
+/// range add of -1, and the count at x is the value of array[x], so
+/// this is a `LazySegmentTree` of sums with an add action.
+pub struct IntervalCountSegmentTree {
+    tree: LazySegmentTree<Sum, Add>,
+}
+
+struct Sum;
+
+impl Monoid for Sum {
+    type Value = i64;
+
+    fn identity() -> i64 {
+        0
+    }
+
+    fn combine(a: &i64, b: &i64) -> i64 {
+        a + b
+    }
+}
+
+struct Add;
+
+impl Monoid for Add {
+    type Value = i64;
+
+    fn identity() -> i64 {
+        0
+    }
+
+    fn combine(outer: &i64, inner: &i64) -> i64 {
+        outer + inner
+    }
+}
+
+impl Action<Sum> for Add {
+    fn apply(value: &i64, diff: &i64, len: usize) -> i64 {
+        value + diff * len as i64
+    }
+}
+
+impl IntervalCountSegmentTree {
+    /// create a tree of positions [1, len] with no intervals
+    pub fn new(len: usize) -> Self {
+        Self {
+            tree: LazySegmentTree::from_vec(&vec![0; len]),
+        }
+    }
+
+    /// return the number of positions
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// return true if there are no positions
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// add the interval [l, r]
+    pub fn add_interval(&mut self, l: usize, r: usize) {
+        check_range(l, r, self.len()).expect("interval out of bounds");
+        self.tree.update(l - 1..r, 1)
+    }
+
+    /// remove the interval [l, r], which must have been added
+    pub fn remove_interval(&mut self, l: usize, r: usize) {
+        check_range(l, r, self.len()).expect("interval out of bounds");
+        self.tree.update(l - 1..r, -1)
+    }
+
+    /// return the number of intervals covering position x
+    pub fn point_count(&mut self, x: usize) -> usize {
+        check_range(x, x, self.len()).expect("index out of bounds");
+        let count = self.tree.query(x - 1..x);
+        assert!(count >= 0, "removed an interval that was never added");
+        count as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_overlapping_intervals() {
+        let mut tree = IntervalCountSegmentTree::new(10);
+        tree.add_interval(2, 5);
+        tree.add_interval(4, 8);
+        tree.add_interval(5, 5);
+        let counts: Vec<usize> = (1..=10).map(|x| tree.point_count(x)).collect();
+        assert_eq!(vec![0, 1, 1, 2, 3, 1, 1, 1, 0, 0], counts);
+
+        // boundaries are covered, the positions next to them are not
+        assert_eq!(1, tree.point_count(2));
+        assert_eq!(0, tree.point_count(1));
+        assert_eq!(1, tree.point_count(8));
+        assert_eq!(0, tree.point_count(9));
+
+        tree.remove_interval(4, 8);
+        let counts: Vec<usize> = (1..=10).map(|x| tree.point_count(x)).collect();
+        assert_eq!(vec![0, 1, 1, 1, 2, 0, 0, 0, 0, 0], counts);
+
+        tree.add_interval(1, 10);
+        tree.add_interval(1, 10);
+        assert_eq!(4, tree.point_count(5));
+        assert_eq!(2, tree.point_count(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "interval out of bounds")]
+    fn test_interval_out_of_bounds() {
+        let mut tree = IntervalCountSegmentTree::new(5);
+        tree.add_interval(3, 6);
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree = IntervalCountSegmentTree::new(0);
+        assert!(tree.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use super::segment_tree_sum::Summable;
+
+/// This segment tree supports point updates and range sum queries without
+/// any recursion. The leaves are stored at buf[n..2n] and the parent of
+/// node i is i / 2, so the tree needs only 2n elements.
+///
+/// Positions are 0-indexed, and query ranges are half-open [l, r).
+pub struct IterativeSegmentTree<T: Summable> {
+    len: usize,
+    buf: Vec<T>,
+}
+
+impl<T: Summable> IterativeSegmentTree<T> {
+    /// construct a tree of n zeros
+    pub fn new(n: usize) -> Self {
+        IterativeSegmentTree {
+            len: n,
+            buf: vec![T::default(); 2 * n],
+        }
+    }
+
+    /// build tree from an array of values
+    pub fn from_vec(values: &[T]) -> Self {
+        let len = values.len();
+        let mut buf = vec![T::default(); 2 * len];
+        buf[len..].copy_from_slice(values);
+        for i in (1..len).rev() {
+            buf[i] = buf[2 * i] + buf[2 * i + 1];
+        }
+        IterativeSegmentTree { len, buf }
+    }
+
+    /// set array[i] to value
+    pub fn update(&mut self, i: usize, value: T) {
+        assert!(i < self.len, "index out of bounds");
+        let mut i = i + self.len;
+        self.buf[i] = value;
+        while i > 1 {
+            i /= 2;
+            self.buf[i] = self.buf[2 * i] + self.buf[2 * i + 1];
+        }
+    }
+
+    /// return the sum of array[l..r]
+    pub fn query(&self, l: usize, r: usize) -> T {
+        assert!(l <= r && r <= self.len, "invalid range");
+        let mut res = T::default();
+        let (mut l, mut r) = (l + self.len, r + self.len);
+        // climb while [l, r) is non-empty, adding the nodes
+        // sticking out of the left and right boundaries
+        while l < r {
+            if l % 2 == 1 {
+                res += self.buf[l];
+                l += 1;
+            }
+            if r % 2 == 1 {
+                r -= 1;
+                res += self.buf[r];
+            }
+            l /= 2;
+            r /= 2;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use data_structures::RangeSumSegmentTree;
+    use math::PCG32;
+
+    #[test]
+    fn test_query() {
+        let mut seg_tree = IterativeSegmentTree::new(6);
+        for (i, &value) in [1, 2, 3, 4, 5, 6].iter().enumerate() {
+            seg_tree.update(i, value);
+        }
+        assert_eq!(21, seg_tree.query(0, 6));
+        assert_eq!(5, seg_tree.query(1, 3));
+        assert_eq!(4, seg_tree.query(3, 4));
+        assert_eq!(12, seg_tree.query(2, 5));
+        assert_eq!(0, seg_tree.query(2, 2));
+    }
+
+    #[test]
+    fn test_against_recursive() {
+        let mut rng = PCG32::new_default(141421);
+        for &n in &[1usize, 2, 5, 64, 99] {
+            let values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100 - 50).collect();
+            let mut seg_tree = IterativeSegmentTree::from_vec(&values);
+            let mut rec_tree = RangeSumSegmentTree::from_vec(&values);
+            for _ in 0..200 {
+                let i = rng.get_u32() as usize % n;
+                let value = rng.get_u32() as i64 % 100 - 50;
+                seg_tree.update(i, value);
+                rec_tree.set_at(i + 1, value);
+
+                let a = rng.get_u32() as usize % n;
+                let b = rng.get_u32() as usize % n;
+                let (l, r) = (a.min(b), a.max(b));
+                assert_eq!(rec_tree.query(l + 1, r + 1), seg_tree.query(l, r + 1));
+            }
+        }
+    }
+}
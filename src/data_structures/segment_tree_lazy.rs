@@ -0,0 +1,297 @@
+use super::segment_tree_monoid::Monoid;
+use super::segment_tree_sum::{calculate_length, SegRange};
+
+/// An `Action` is a monoid of lazy tags that act on the values of
+/// another monoid `M`. `combine(outer, inner)` must return the tag
+/// equivalent to applying `inner` first and then `outer`, and
+/// `identity()` must be the tag that leaves every value unchanged.
+///
+/// `apply` updates the fold of `len` consecutive elements, which is
+/// how e.g. a range add scales its diff by the length of a segment.
+/// For the tree to be correct, applying a tag to a fold must equal
+/// the fold of the tag applied to each element.
+pub trait Action<M: Monoid>: Monoid {
+    fn apply(value: &M::Value, tag: &Self::Value, len: usize) -> M::Value;
+}
+
+/// This segment tree stores an array of `M::Value`, and supports:
+/// 1. apply a tag of `L` to every element of array[range]
+/// 2. fold array[range] with `M::combine`
+///
+/// Range add with range sum, range assign, affine updates and
+/// many more can be expressed by choosing `M` and `L`.
+///
+/// Positions and ranges are 0-indexed, ranges are given as
+/// std ranges, e.g. `tree.query(2..5)` or `tree.query(..)`.
+pub struct LazySegmentTree<M: Monoid, L: Action<M>> {
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1], covering
+    // the 1-indexed range [1, len] at the root
+    arr: Vec<M::Value>,
+    // lazy[p] stores the tag that has been applied to node p
+    // but has not been propagated to its child node
+    lazy: Vec<L::Value>,
+}
+
+impl<M: Monoid, L: Action<M>> LazySegmentTree<M, L> {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[M::Value]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![M::identity(); length],
+            lazy: vec![L::identity(); length],
+        };
+
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[M::Value], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = values[left - 1].clone();
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = M::combine(&self.arr[p * 2], &self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// apply tag to every element of array[range]. Panics if range is
+    /// reversed or reaches past the end.
+    pub fn update<R: SegRange>(&mut self, range: R, tag: L::Value) {
+        let (start, end) = range.bounds(self.len);
+        assert!(
+            start <= end && end <= self.len,
+            "update range out of bounds"
+        );
+        if start == end {
+            return;
+        }
+        self.update_rec(start + 1, end, 1, self.len, 1, &tag)
+    }
+
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, tag: &L::Value) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, cr - cl + 1, tag);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, tag);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, tag);
+
+        self.arr[p] = M::combine(&self.arr[p * 2], &self.arr[p * 2 + 1]);
+    }
+
+    // apply tag to node p covering length elements
+    fn apply(&mut self, p: usize, length: usize, tag: &L::Value) {
+        self.arr[p] = L::apply(&self.arr[p], tag, length);
+        // leaves have no child to propagate to
+        if length > 1 {
+            self.lazy[p] = L::combine(tag, &self.lazy[p]);
+        }
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let tag = std::mem::replace(&mut self.lazy[p], L::identity());
+        self.apply(p * 2, length.div_ceil(2), &tag);
+        self.apply(p * 2 + 1, length / 2, &tag);
+    }
+
+    /// return the fold of array[range], or the identity if range is empty.
+    /// Panics if range is reversed or reaches past the end.
+    pub fn query<R: SegRange>(&mut self, range: R) -> M::Value {
+        let (start, end) = range.bounds(self.len);
+        assert!(start <= end && end <= self.len, "query range out of bounds");
+        if start == end {
+            return M::identity();
+        }
+        self.query_rec(start + 1, end, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> M::Value {
+        if cl > r || cr < l {
+            return M::identity();
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p].clone();
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        M::combine(
+            &self.query_rec(l, r, cl, mid, p * 2),
+            &self.query_rec(l, r, mid + 1, cr, p * 2 + 1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use data_structures::RangeSumSegmentTree;
+    use math::PCG32;
+
+    // RangeSumSegmentTree expressed as a LazySegmentTree:
+    // the values are summed, and a tag is a diff added to every element
+    struct Sum;
+
+    impl Monoid for Sum {
+        type Value = i64;
+
+        fn identity() -> i64 {
+            0
+        }
+
+        fn combine(a: &i64, b: &i64) -> i64 {
+            a + b
+        }
+    }
+
+    struct Add;
+
+    impl Monoid for Add {
+        type Value = i64;
+
+        fn identity() -> i64 {
+            0
+        }
+
+        fn combine(outer: &i64, inner: &i64) -> i64 {
+            outer + inner
+        }
+    }
+
+    impl Action<Sum> for Add {
+        fn apply(value: &i64, diff: &i64, len: usize) -> i64 {
+            value + diff * len as i64
+        }
+    }
+
+    // range assign over range max, a tag of None leaves values unchanged
+    struct Max;
+
+    impl Monoid for Max {
+        type Value = i64;
+
+        fn identity() -> i64 {
+            i64::MIN
+        }
+
+        fn combine(a: &i64, b: &i64) -> i64 {
+            *a.max(b)
+        }
+    }
+
+    struct Assign;
+
+    impl Monoid for Assign {
+        type Value = Option<i64>;
+
+        fn identity() -> Option<i64> {
+            None
+        }
+
+        fn combine(outer: &Option<i64>, inner: &Option<i64>) -> Option<i64> {
+            outer.or(*inner)
+        }
+    }
+
+    impl Action<Max> for Assign {
+        fn apply(value: &i64, tag: &Option<i64>, _len: usize) -> i64 {
+            tag.unwrap_or(*value)
+        }
+    }
+
+    #[test]
+    fn test_range_add_range_sum() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = LazySegmentTree::<Sum, Add>::from_vec(&values);
+        assert_eq!(22, seg_tree.query(..));
+        seg_tree.update(1..4, 1);
+        // new values should be [2, 5, 2, 4, 5, 7]
+        assert_eq!(11, seg_tree.query(1..4));
+        assert_eq!(9, seg_tree.query(..3));
+        seg_tree.update(2.., -2);
+        // new values should be [2, 5, 0, 2, 3, 5]
+        assert_eq!(8, seg_tree.query(4..6));
+        assert_eq!(17, seg_tree.query(..));
+        assert_eq!(0, seg_tree.query(3..3));
+    }
+
+    #[test]
+    fn test_equivalent_to_range_sum_tree() {
+        let mut rng = PCG32::new_default(314159);
+        for &n in &[1usize, 2, 7, 64, 100] {
+            let values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100 - 50).collect();
+            let mut lazy_tree = LazySegmentTree::<Sum, Add>::from_vec(&values);
+            let mut sum_tree = RangeSumSegmentTree::from_vec(&values);
+            for _ in 0..200 {
+                let a = rng.get_u32() as usize % n;
+                let b = rng.get_u32() as usize % n;
+                let (i, j) = (a.min(b), a.max(b));
+                if rng.get_u32().is_multiple_of(2) {
+                    let diff = rng.get_u32() as i64 % 20 - 10;
+                    lazy_tree.update(i..=j, diff);
+                    sum_tree.update(i + 1, j + 1, diff);
+                } else {
+                    assert_eq!(sum_tree.query(i + 1, j + 1), lazy_tree.query(i..=j));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_assign_range_max() {
+        let values = [5, 2, 6, 4, 1, 3];
+        let mut seg_tree = LazySegmentTree::<Max, Assign>::from_vec(&values);
+        assert_eq!(6, seg_tree.query(..));
+        seg_tree.update(1..4, Some(0));
+        // new values should be [5, 0, 0, 0, 1, 3]
+        assert_eq!(0, seg_tree.query(1..4));
+        assert_eq!(5, seg_tree.query(..));
+        seg_tree.update(..5, Some(-1));
+        // new values should be [-1, -1, -1, -1, -1, 3]
+        assert_eq!(-1, seg_tree.query(..5));
+        assert_eq!(3, seg_tree.query(..));
+        assert_eq!(i64::MIN, seg_tree.query(2..2));
+    }
+
+    #[test]
+    #[should_panic(expected = "update range out of bounds")]
+    fn test_update_reversed_range() {
+        let mut seg_tree = LazySegmentTree::<Sum, Add>::from_vec(&[1, 2, 3, 4, 5]);
+        let (start, end) = (4, 2);
+        seg_tree.update(start..end, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "query range out of bounds")]
+    fn test_query_reversed_range() {
+        let mut seg_tree = LazySegmentTree::<Sum, Add>::from_vec(&[1, 2, 3, 4, 5]);
+        let (start, end) = (4, 2);
+        seg_tree.query(start..end);
+    }
+}
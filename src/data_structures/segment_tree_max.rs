@@ -0,0 +1,380 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// This implementation of segment tree is built on
+/// an array of i32, and supports the following operation:
+/// 1. given an index i, j, query the maximum of array in range [i, j],
+///    as well as the leftmost index at which it is attained
+/// 2. given a range [x, y] and a diff, update all values of the array
+///    in range by diff
+pub struct RangeMaxSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1], and arr[p] is the
+    // maximum of the range covered by p along with the leftmost
+    // index achieving it
+    arr: Vec<(i32, usize)>,
+    // mark[p] stores the diffs that have been applied
+    // to node p but has not been propagated to its child node
+    mark: Vec<i32>,
+}
+
+// keep the left index on ties so that the answer is the leftmost one
+fn combine(left: (i32, usize), right: (i32, usize)) -> (i32, usize) {
+    if left.0 >= right.0 {
+        left
+    } else {
+        right
+    }
+}
+
+impl RangeMaxSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i32]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![(0, 0); length],
+            mark: vec![0; length],
+        };
+
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i32], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = (values[left - 1], left);
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = combine(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// add diff to all element in range [i, j]
+    pub fn update(&mut self, i: usize, j: usize, diff: i32) {
+        check_range(i, j, self.len).expect("update range out of bounds");
+        self.update_rec(i, j, 1, self.len, 1, diff)
+    }
+
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, diff: i32) {
+        if cl > r || cr < l {
+            return;
+        }
+
+        // a uniform diff shifts the maximum but doesn't move it
+        if cl >= l && cr <= r {
+            self.arr[p].0 += diff;
+            if cl < cr {
+                self.mark[p] += diff;
+            }
+            return;
+        }
+
+        self.push_down(p);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, diff);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, diff);
+
+        self.arr[p] = combine(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    fn push_down(&mut self, p: usize) {
+        let mark = self.mark[p];
+        self.mark[p * 2] += mark;
+        self.mark[p * 2 + 1] += mark;
+        self.arr[p * 2].0 += mark;
+        self.arr[p * 2 + 1].0 += mark;
+        self.mark[p] = 0;
+    }
+
+    /// return the maximum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> i32 {
+        self.query_argmax(i, j).1
+    }
+
+    /// return the leftmost index in [i, j] holding the maximum
+    /// of array[i]..array[j], along with the maximum itself
+    pub fn query_argmax(&mut self, i: usize, j: usize) -> (usize, i32) {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        let (max, index) = self
+            .query_rec(i, j, 1, self.len, 1)
+            .expect("a valid range is never empty");
+        (index, max)
+    }
+
+    fn query_rec(
+        &mut self,
+        l: usize,
+        r: usize,
+        cl: usize,
+        cr: usize,
+        p: usize,
+    ) -> Option<(i32, usize)> {
+        if cl > r || cr < l {
+            return None;
+        }
+        if cl >= l && cr <= r {
+            return Some(self.arr[p]);
+        }
+        self.push_down(p);
+        let mid = cl + (cr - cl) / 2;
+        match (
+            self.query_rec(l, r, cl, mid, p * 2),
+            self.query_rec(l, r, mid + 1, cr, p * 2 + 1),
+        ) {
+            (Some(left), Some(right)) => Some(combine(left, right)),
+            (left, right) => left.or(right),
+        }
+    }
+}
+
+/// This segment tree is built on an array of i32,
+/// and supports the following operation:
+/// 1. given an index i, j, query the maximum of array in range [i, j]
+/// 2. given a range [x, y] and a value, set all values of the array
+///    in range to value
+///
+/// Unlike an add, which shifts the maximum of a segment, an assignment
+/// replaces it: a segment set to v has maximum v whatever it held
+/// before, even if that was larger. A pending assignment likewise
+/// replaces, rather than adds to, the marks of the children.
+pub struct RangeAssignMaxSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1]
+    arr: Vec<i32>,
+    // mark[p] is Some(v) if every element covered by node p
+    // has been set to v but the assignment has not been
+    // propagated to its child nodes
+    mark: Vec<Option<i32>>,
+}
+
+impl RangeAssignMaxSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i32]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![i32::MIN; length],
+            mark: vec![None; length],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i32], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = values[left - 1];
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = self.arr[p * 2].max(self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// set all elements in range [i, j] to value
+    pub fn assign(&mut self, i: usize, j: usize, value: i32) {
+        check_range(i, j, self.len).expect("assign range out of bounds");
+        self.assign_rec(i, j, 1, self.len, 1, value)
+    }
+
+    fn assign_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, value: i32) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.arr[p] = value;
+            if cl < cr {
+                self.mark[p] = Some(value);
+            }
+            return;
+        }
+
+        self.push_down(p);
+
+        let mid = cl + (cr - cl) / 2;
+        self.assign_rec(l, r, cl, mid, p * 2, value);
+        self.assign_rec(l, r, mid + 1, cr, p * 2 + 1, value);
+
+        self.arr[p] = self.arr[p * 2].max(self.arr[p * 2 + 1]);
+    }
+
+    // overwrite, rather than accumulate into, the children
+    fn push_down(&mut self, p: usize) {
+        if let Some(value) = self.mark[p].take() {
+            self.mark[p * 2] = Some(value);
+            self.mark[p * 2 + 1] = Some(value);
+            self.arr[p * 2] = value;
+            self.arr[p * 2 + 1] = value;
+        }
+    }
+
+    /// return the maximum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> i32 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i32 {
+        if cl > r || cr < l {
+            return i32::MIN;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p);
+        let mid = cl + (cr - cl) / 2;
+        self.query_rec(l, r, cl, mid, p * 2)
+            .max(self.query_rec(l, r, mid + 1, cr, p * 2 + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let values = [5, 2, 6, 4, 1, 6];
+        let mut seg_tree = RangeMaxSegmentTree::from_vec(&values);
+        assert_eq!(6, seg_tree.query(1, 6));
+        assert_eq!(6, seg_tree.query(2, 3));
+        assert_eq!(4, seg_tree.query(4, 5));
+        assert_eq!(5, seg_tree.query(1, 2));
+    }
+
+    #[test]
+    fn test_argmax() {
+        let values = [5, 2, 6, 4, 1, 6];
+        let mut seg_tree = RangeMaxSegmentTree::from_vec(&values);
+        // ties resolve to the leftmost index
+        assert_eq!((3, 6), seg_tree.query_argmax(1, 6));
+        assert_eq!((6, 6), seg_tree.query_argmax(4, 6));
+        assert_eq!((1, 5), seg_tree.query_argmax(1, 2));
+
+        seg_tree.update(4, 6, 1);
+        // new values should be [5, 2, 6, 5, 2, 7]
+        assert_eq!((6, 7), seg_tree.query_argmax(1, 6));
+        assert_eq!((3, 6), seg_tree.query_argmax(1, 5));
+
+        seg_tree.update(1, 2, 2);
+        // new values should be [7, 4, 6, 5, 2, 7]
+        assert_eq!((1, 7), seg_tree.query_argmax(1, 6));
+        assert_eq!((3, 6), seg_tree.query_argmax(2, 5));
+
+        seg_tree.update(3, 3, -2);
+        // new values should be [7, 4, 4, 5, 2, 7]
+        assert_eq!((4, 5), seg_tree.query_argmax(2, 5));
+        assert_eq!((2, 4), seg_tree.query_argmax(2, 3));
+    }
+
+    #[test]
+    fn test_build() {
+        for length in 10..1000 {
+            let values = vec![2; length];
+            let mut seg_tree = RangeMaxSegmentTree::from_vec(&values[..]);
+            assert_eq!((1, 2), seg_tree.query_argmax(1, length));
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let seg_tree = RangeMaxSegmentTree::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+        assert_eq!(0, seg_tree.len());
+    }
+
+    #[test]
+    fn test_assign() {
+        let values = [5, 2, 6, 4, 1, 6];
+        let mut seg_tree = RangeAssignMaxSegmentTree::from_vec(&values);
+        assert_eq!(6, seg_tree.query(1, 6));
+        assert_eq!(5, seg_tree.query(1, 2));
+
+        seg_tree.assign(2, 4, 9);
+        // new values should be [5, 9, 9, 9, 1, 6]
+        assert_eq!(9, seg_tree.query(1, 6));
+        assert_eq!(9, seg_tree.query(4, 5));
+        assert_eq!(6, seg_tree.query(5, 6));
+
+        // a smaller value takes the maximum down with it
+        seg_tree.assign(1, 6, 3);
+        assert_eq!(3, seg_tree.query(1, 6));
+        seg_tree.assign(3, 5, -2);
+        // new values should be [3, 3, -2, -2, -2, 3]
+        assert_eq!(-2, seg_tree.query(3, 5));
+        assert_eq!(3, seg_tree.query(2, 3));
+        assert_eq!(3, seg_tree.query(5, 6));
+
+        seg_tree.assign(4, 4, 7);
+        seg_tree.assign(5, 6, -5);
+        // new values should be [3, 3, -2, 7, -5, -5]
+        assert_eq!(7, seg_tree.query(1, 6));
+        assert_eq!(-2, seg_tree.query(3, 3));
+        assert_eq!(-5, seg_tree.query(5, 6));
+        assert_eq!(6, seg_tree.len());
+    }
+
+    #[test]
+    fn test_assign_against_brute_force() {
+        let n = 40;
+        let mut values: Vec<i32> = (0..n).map(|i| (i * 7 % 13) as i32).collect();
+        let mut seg_tree = RangeAssignMaxSegmentTree::from_vec(&values);
+        for step in 0..600usize {
+            let a = step * 17 % n + 1;
+            let b = step * 29 % n + 1;
+            let (l, r) = (a.min(b), a.max(b));
+            if step % 2 == 0 {
+                // both raise and lower parts of the array
+                let value = (step % 21) as i32 - 10;
+                seg_tree.assign(l, r, value);
+                for v in values[l - 1..r].iter_mut() {
+                    *v = value;
+                }
+            } else {
+                let max = *values[l - 1..r].iter().max().unwrap();
+                assert_eq!(max, seg_tree.query(l, r));
+            }
+        }
+    }
+
+    #[test]
+    fn test_assign_empty() {
+        let seg_tree = RangeAssignMaxSegmentTree::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use super::segment_tree_monoid::{Monoid, MonoidSegmentTree};
+use super::segment_tree_sum::check_range;
+
+/// This segment tree is built on an array of i64,
+/// and supports the following operation:
+/// 1. given an index i, j, query the maximum sum of any
+///    non-empty contiguous subarray of array in range [i, j]
+/// 2. given an index i and a value, set array[i] to value
+pub struct MaxSubarraySegmentTree {
+    tree: MonoidSegmentTree<MaxSubarray>,
+}
+
+// summary of a non-empty segment of the array
+#[derive(Clone, Copy)]
+struct Segment {
+    // sum of the whole segment
+    sum: i64,
+    // best sum of a subarray starting at the left end
+    prefix: i64,
+    // best sum of a subarray ending at the right end
+    suffix: i64,
+    // best sum of any subarray
+    best: i64,
+}
+
+impl Segment {
+    fn single(value: i64) -> Self {
+        Segment {
+            sum: value,
+            prefix: value,
+            suffix: value,
+            best: value,
+        }
+    }
+}
+
+// None stands for the empty segment, so that queries never
+// have to fall back to an empty subarray
+struct MaxSubarray;
+
+impl Monoid for MaxSubarray {
+    type Value = Option<Segment>;
+
+    fn identity() -> Option<Segment> {
+        None
+    }
+
+    fn combine(a: &Option<Segment>, b: &Option<Segment>) -> Option<Segment> {
+        match (a, b) {
+            (Some(l), Some(r)) => Some(Segment {
+                sum: l.sum + r.sum,
+                prefix: l.prefix.max(l.sum + r.prefix),
+                suffix: r.suffix.max(r.sum + l.suffix),
+                // the best subarray lies entirely in one half,
+                // or straddles the boundary between them
+                best: l.best.max(r.best).max(l.suffix + r.prefix),
+            }),
+            (a, b) => a.or(*b),
+        }
+    }
+}
+
+impl MaxSubarraySegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        let segments: Vec<Option<Segment>> = values
+            .iter()
+            .map(|&value| Some(Segment::single(value)))
+            .collect();
+        Self {
+            tree: MonoidSegmentTree::from_vec(&segments),
+        }
+    }
+
+    /// set array[i] to value
+    pub fn set(&mut self, i: usize, value: i64) {
+        check_range(i, i, self.tree.len()).expect("index out of bounds");
+        self.tree.set(i - 1, Some(Segment::single(value)))
+    }
+
+    /// return the maximum sum of a non-empty contiguous subarray
+    /// of array[i]..array[j] inclusive
+    pub fn query(&self, i: usize, j: usize) -> i64 {
+        check_range(i, j, self.tree.len()).expect("query range out of bounds");
+        self.tree
+            .query(i - 1..j)
+            .expect("a valid range is never empty")
+            .best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn brute_force(values: &[i64], i: usize, j: usize) -> i64 {
+        let mut best = i64::MIN;
+        for l in i..=j {
+            let mut sum = 0;
+            for value in &values[l - 1..j] {
+                sum += value;
+                best = best.max(sum);
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_all_negative() {
+        let values = [-3, -1, -4, -1, -5, -9];
+        let seg_tree = MaxSubarraySegmentTree::from_vec(&values);
+        assert_eq!(-1, seg_tree.query(1, 6));
+        assert_eq!(-3, seg_tree.query(1, 1));
+        assert_eq!(-4, seg_tree.query(3, 3));
+        assert_eq!(-5, seg_tree.query(5, 6));
+    }
+
+    #[test]
+    fn test_straddle_midpoint() {
+        // the best subarray [4, -1, 2, 1] crosses the root's midpoint
+        let values = [-2, 1, -3, 4, -1, 2, 1, -5, 4];
+        let seg_tree = MaxSubarraySegmentTree::from_vec(&values);
+        assert_eq!(6, seg_tree.query(1, 9));
+        assert_eq!(4, seg_tree.query(1, 5));
+        assert_eq!(4, seg_tree.query(5, 9));
+        for i in 1..=9 {
+            for j in i..=9 {
+                assert_eq!(brute_force(&values, i, j), seg_tree.query(i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_set() {
+        let mut values = vec![-2, 1, -3, 4, -1, 2, 1, -5, 4];
+        let mut seg_tree = MaxSubarraySegmentTree::from_vec(&values);
+        seg_tree.set(8, 5);
+        values[7] = 5;
+        // whole array but the first three elements
+        assert_eq!(15, seg_tree.query(1, 9));
+        seg_tree.set(5, -10);
+        values[4] = -10;
+        assert_eq!(12, seg_tree.query(1, 9));
+        for i in 1..=9 {
+            for j in i..=9 {
+                assert_eq!(brute_force(&values, i, j), seg_tree.query(i, j));
+            }
+        }
+    }
+}
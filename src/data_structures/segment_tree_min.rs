@@ -0,0 +1,172 @@
+use super::segment_tree_sum::{calculate_length, check_range};
+
+/// This implementation of segment tree is built on
+/// an array of i32, and supports the following operation:
+/// 1. given an index i, j, query the minimum of array in range [i, j]
+/// 2. given a range [x, y] and a diff, update all values of the array
+///    in range by diff
+pub struct RangeMinSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1], and arr[p]
+    // is the minimum of the range covered by p
+    arr: Vec<i32>,
+    // mark[p] stores the diffs that have been applied
+    // to node p but has not been propagated to its child node
+    mark: Vec<i32>,
+}
+
+impl RangeMinSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i32]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![0; length],
+            mark: vec![0; length],
+        };
+
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i32], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = values[left - 1];
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = self.arr[p * 2].min(self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// add diff to all element in range [i, j]
+    pub fn update(&mut self, i: usize, j: usize, diff: i32) {
+        check_range(i, j, self.len).expect("update range out of bounds");
+        self.update_rec(i, j, 1, self.len, 1, diff)
+    }
+
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, diff: i32) {
+        if cl > r || cr < l {
+            return;
+        }
+
+        // adding diff to every element shifts the minimum by diff,
+        // regardless of the length of the segment
+        if cl >= l && cr <= r {
+            self.arr[p] += diff;
+            if cl < cr {
+                self.mark[p] += diff;
+            }
+            return;
+        }
+
+        self.push_down(p);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, diff);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, diff);
+
+        self.arr[p] = self.arr[p * 2].min(self.arr[p * 2 + 1]);
+    }
+
+    fn push_down(&mut self, p: usize) {
+        let mark = self.mark[p];
+        self.mark[p * 2] += mark;
+        self.mark[p * 2 + 1] += mark;
+        self.arr[p * 2] += mark;
+        self.arr[p * 2 + 1] += mark;
+        self.mark[p] = 0;
+    }
+
+    /// return the minimum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> i32 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i32 {
+        // i32::MAX is the identity of min
+        if cl > r || cr < l {
+            return i32::MAX;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p);
+        let mid = cl + (cr - cl) / 2;
+        self.query_rec(l, r, cl, mid, p * 2)
+            .min(self.query_rec(l, r, mid + 1, cr, p * 2 + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let values = [5, 2, 6, 4, 1, 3];
+        let mut seg_tree = RangeMinSegmentTree::from_vec(&values);
+        assert_eq!(1, seg_tree.query(1, 6));
+        assert_eq!(2, seg_tree.query(2, 3));
+        assert_eq!(4, seg_tree.query(4, 4));
+        assert_eq!(1, seg_tree.query(3, 5));
+        assert_eq!(5, seg_tree.query(1, 1));
+    }
+
+    #[test]
+    fn test_update() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = RangeMinSegmentTree::from_vec(&values);
+
+        assert_eq!(1, seg_tree.query(2, 4));
+
+        seg_tree.update(2, 4, 3);
+        // new values should be [2, 7, 4, 6, 5, 7]
+        assert_eq!(4, seg_tree.query(2, 4));
+        assert_eq!(7, seg_tree.query(2, 2));
+        assert_eq!(4, seg_tree.query(3, 3));
+        assert_eq!(5, seg_tree.query(4, 6));
+        assert_eq!(2, seg_tree.query(1, 3));
+
+        seg_tree.update(1, 5, -4);
+        // new values should be [-2, 3, 0, 2, 1, 7]
+        assert_eq!(1, seg_tree.query(4, 6));
+        assert_eq!(-2, seg_tree.query(1, 6));
+        assert_eq!(0, seg_tree.query(2, 5));
+    }
+
+    #[test]
+    fn test_build() {
+        for length in 10..1000 {
+            let values = vec![2; length];
+            let mut seg_tree = RangeMinSegmentTree::from_vec(&values[..]);
+            assert_eq!(2, seg_tree.query(1, length));
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let seg_tree = RangeMinSegmentTree::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+        assert_eq!(0, seg_tree.len());
+    }
+}
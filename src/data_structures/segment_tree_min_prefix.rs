@@ -0,0 +1,213 @@
+use super::segment_tree_lazy::{Action, LazySegmentTree};
+use super::segment_tree_monoid::Monoid;
+use super::segment_tree_sum::check_range;
+
+/// This segment tree is built on an array of i64,
+/// and supports the following operation:
+/// 1. given a range [x, y] and a value, set all values of the array
+///    in range to value
+/// 2. given an index i, j, query the sum of array in range [i, j]
+/// 3. given an index i, j, query the minimum of the running sums
+///    array[i], array[i] + array[i + 1], .., array[i] + .. + array[j]
+///
+/// With +1 for an opening and -1 for a closing bracket, [i, j] is
+/// balanced exactly when its sum is 0 and its minimum prefix is not
+/// negative, and with daily price changes the minimum prefix is the
+/// deepest drop below the starting price.
+///
+/// Every node stores the sum and minimum prefix of its segment, and
+/// the minimum prefix of two segments is the smaller of the left one
+/// and the left sum plus the right one. An assignment fixes both for a
+/// whole segment: v * len and min(v, v * len). A range add doesn't,
+/// adding d moves the k-th prefix by d * k, so that the minimum may
+/// move to another prefix, which is why updates are assignments.
+pub struct MinPrefixSegmentTree {
+    tree: LazySegmentTree<MinPrefix, Assign>,
+}
+
+// summary of a non-empty segment of the array
+#[derive(Clone, Copy)]
+struct Segment {
+    sum: i64,
+    // minimum sum of a non-empty prefix of the segment
+    min_prefix: i64,
+}
+
+impl Segment {
+    // len elements that are all value
+    fn repeated(value: i64, len: usize) -> Self {
+        let sum = value * len as i64;
+        Segment {
+            sum,
+            min_prefix: value.min(sum),
+        }
+    }
+}
+
+// None stands for the empty segment, which has no non-empty prefix
+struct MinPrefix;
+
+impl Monoid for MinPrefix {
+    type Value = Option<Segment>;
+
+    fn identity() -> Option<Segment> {
+        None
+    }
+
+    fn combine(a: &Option<Segment>, b: &Option<Segment>) -> Option<Segment> {
+        match (a, b) {
+            (Some(l), Some(r)) => Some(Segment {
+                sum: l.sum + r.sum,
+                min_prefix: l.min_prefix.min(l.sum + r.min_prefix),
+            }),
+            (a, b) => a.or(*b),
+        }
+    }
+}
+
+struct Assign;
+
+impl Monoid for Assign {
+    type Value = Option<i64>;
+
+    fn identity() -> Option<i64> {
+        None
+    }
+
+    fn combine(outer: &Option<i64>, inner: &Option<i64>) -> Option<i64> {
+        outer.or(*inner)
+    }
+}
+
+impl Action<MinPrefix> for Assign {
+    fn apply(value: &Option<Segment>, tag: &Option<i64>, len: usize) -> Option<Segment> {
+        match tag {
+            Some(v) => value.map(|_| Segment::repeated(*v, len)),
+            None => *value,
+        }
+    }
+}
+
+impl MinPrefixSegmentTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i64]) -> Self {
+        let segments: Vec<Option<Segment>> = values
+            .iter()
+            .map(|&value| Some(Segment::repeated(value, 1)))
+            .collect();
+        Self {
+            tree: LazySegmentTree::from_vec(&segments),
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// return true if the tree has no elements
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// set all elements in range [i, j] to value
+    pub fn assign(&mut self, i: usize, j: usize, value: i64) {
+        check_range(i, j, self.len()).expect("assign range out of bounds");
+        self.tree.update(i - 1..j, Some(value))
+    }
+
+    fn segment(&mut self, i: usize, j: usize) -> Segment {
+        check_range(i, j, self.len()).expect("query range out of bounds");
+        self.tree
+            .query(i - 1..j)
+            .expect("a valid range is never empty")
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> i64 {
+        self.segment(i, j).sum
+    }
+
+    /// return the minimum of array[i] + .. + array[k] over i <= k <= j
+    pub fn min_prefix(&mut self, i: usize, j: usize) -> i64 {
+        self.segment(i, j).min_prefix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_brackets() {
+        // ( ( ) ( ) ) ) (
+        let values = [1, 1, -1, 1, -1, -1, -1, 1];
+        let mut seg_tree = MinPrefixSegmentTree::from_vec(&values);
+        assert_eq!(-1, seg_tree.min_prefix(1, 8));
+        assert_eq!(0, seg_tree.query(1, 8));
+        // ( ( ) ( ) ) is balanced
+        assert_eq!(0, seg_tree.min_prefix(1, 6));
+        assert_eq!(0, seg_tree.query(1, 6));
+        // ) ( ) is not
+        assert_eq!(-1, seg_tree.min_prefix(3, 5));
+        assert_eq!(-1, seg_tree.min_prefix(7, 7));
+        assert_eq!(1, seg_tree.min_prefix(8, 8));
+
+        // ( ( ( ( ( ( ) (
+        seg_tree.assign(3, 6, 1);
+        assert_eq!(1, seg_tree.min_prefix(1, 8));
+        assert_eq!(6, seg_tree.query(1, 8));
+        // ( ( ( ) ) ) ) (
+        seg_tree.assign(4, 7, -1);
+        assert_eq!(-1, seg_tree.min_prefix(1, 8));
+        assert_eq!(-4, seg_tree.min_prefix(4, 7));
+        assert_eq!(0, seg_tree.query(1, 6));
+        assert_eq!(0, seg_tree.min_prefix(1, 6));
+        assert_eq!(1, seg_tree.min_prefix(2, 3));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(8675309);
+        let n = 60;
+        let mut values: Vec<i64> = (0..n)
+            .map(|_| {
+                if rng.get_u32().is_multiple_of(2) {
+                    1
+                } else {
+                    -1
+                }
+            })
+            .collect();
+        let mut seg_tree = MinPrefixSegmentTree::from_vec(&values);
+        for _ in 0..1000 {
+            let a = rng.get_u32() as usize % n + 1;
+            let b = rng.get_u32() as usize % n + 1;
+            let (l, r) = (a.min(b), a.max(b));
+            if rng.get_u32() % 3 == 0 {
+                let value = rng.get_u32() as i64 % 7 - 3;
+                seg_tree.assign(l, r, value);
+                for v in values[l - 1..r].iter_mut() {
+                    *v = value;
+                }
+            } else {
+                let mut sum = 0;
+                let mut min_prefix = i64::MAX;
+                for v in &values[l - 1..r] {
+                    sum += v;
+                    min_prefix = min_prefix.min(sum);
+                }
+                assert_eq!(sum, seg_tree.query(l, r));
+                assert_eq!(min_prefix, seg_tree.min_prefix(l, r));
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let seg_tree = MinPrefixSegmentTree::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+    }
+}
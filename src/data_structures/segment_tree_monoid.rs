@@ -0,0 +1,237 @@
+use super::segment_tree_sum::{calculate_length, SegRange};
+
+/// A monoid is a set with an associative binary operation `combine`
+/// and an `identity` element such that `combine(identity, x) == x`
+/// and `combine(x, identity) == x`. Sum, min, max and gcd are all
+/// monoids, so a single `MonoidSegmentTree` can answer any of them.
+///
+/// The operation doesn't have to be commutative: `combine` is always
+/// called with the left part of the range first.
+pub trait Monoid {
+    type Value: Clone;
+
+    fn identity() -> Self::Value;
+
+    fn combine(a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+/// This segment tree stores an array of `M::Value`, and supports:
+/// 1. set the value of array[i]
+/// 2. fold array[range] with `M::combine`
+///
+/// Positions and ranges are 0-indexed, ranges are given as
+/// std ranges, e.g. `tree.query(2..5)` or `tree.query(..)`.
+pub struct MonoidSegmentTree<M: Monoid> {
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1], covering
+    // the 1-indexed range [1, len] at the root
+    arr: Vec<M::Value>,
+}
+
+impl<M: Monoid> MonoidSegmentTree<M> {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[M::Value]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![M::identity(); length],
+        };
+
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[M::Value], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = values[left - 1].clone();
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = M::combine(&self.arr[p * 2], &self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// set array[i] to value
+    pub fn set(&mut self, i: usize, value: M::Value) {
+        assert!(i < self.len, "index out of bounds");
+        self.set_rec(i + 1, 1, self.len, 1, value)
+    }
+
+    fn set_rec(&mut self, i: usize, cl: usize, cr: usize, p: usize, value: M::Value) {
+        if cl == cr {
+            self.arr[p] = value;
+            return;
+        }
+        let mid = cl + (cr - cl) / 2;
+        if i <= mid {
+            self.set_rec(i, cl, mid, p * 2, value);
+        } else {
+            self.set_rec(i, mid + 1, cr, p * 2 + 1, value);
+        }
+        self.arr[p] = M::combine(&self.arr[p * 2], &self.arr[p * 2 + 1]);
+    }
+
+    /// return the fold of array[range], or the identity if range is empty.
+    /// Panics if range is reversed or reaches past the end.
+    pub fn query<R: SegRange>(&self, range: R) -> M::Value {
+        let (start, end) = range.bounds(self.len);
+        assert!(start <= end && end <= self.len, "query range out of bounds");
+        if start == end {
+            return M::identity();
+        }
+        self.query_rec(start + 1, end, 1, self.len, 1)
+    }
+
+    fn query_rec(&self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> M::Value {
+        if cl > r || cr < l {
+            return M::identity();
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p].clone();
+        }
+        let mid = cl + (cr - cl) / 2;
+        M::combine(
+            &self.query_rec(l, r, cl, mid, p * 2),
+            &self.query_rec(l, r, mid + 1, cr, p * 2 + 1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use data_structures::{RangeMinSegmentTree, RangeSumSegmentTree};
+
+    struct Sum;
+
+    impl Monoid for Sum {
+        type Value = i32;
+
+        fn identity() -> i32 {
+            0
+        }
+
+        fn combine(a: &i32, b: &i32) -> i32 {
+            a + b
+        }
+    }
+
+    struct Min;
+
+    impl Monoid for Min {
+        type Value = i32;
+
+        fn identity() -> i32 {
+            i32::MAX
+        }
+
+        fn combine(a: &i32, b: &i32) -> i32 {
+            *a.min(b)
+        }
+    }
+
+    // string concatenation is not commutative
+    struct Concat;
+
+    impl Monoid for Concat {
+        type Value = String;
+
+        fn identity() -> String {
+            String::new()
+        }
+
+        fn combine(a: &String, b: &String) -> String {
+            format!("{}{}", a, b)
+        }
+    }
+
+    #[test]
+    fn test_sum() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = MonoidSegmentTree::<Sum>::from_vec(&values);
+        let mut sum_tree = RangeSumSegmentTree::from_vec(&values);
+        for i in 0..6 {
+            for j in i + 1..=6 {
+                assert_eq!(sum_tree.query(i + 1, j), seg_tree.query(i..j));
+            }
+        }
+
+        seg_tree.set(2, 10);
+        sum_tree.set_at(3, 10);
+        for i in 0..6 {
+            for j in i + 1..=6 {
+                assert_eq!(sum_tree.query(i + 1, j), seg_tree.query(i..j));
+            }
+        }
+        assert_eq!(0, seg_tree.query(3..3));
+        assert_eq!(31, seg_tree.query(..));
+    }
+
+    #[test]
+    fn test_min() {
+        let values = [5, 2, 6, 4, 1, 3];
+        let mut seg_tree = MonoidSegmentTree::<Min>::from_vec(&values);
+        let mut min_tree = RangeMinSegmentTree::from_vec(&values);
+        for i in 0..6 {
+            for j in i + 1..=6 {
+                assert_eq!(min_tree.query(i + 1, j), seg_tree.query(i..j));
+            }
+        }
+
+        seg_tree.set(4, 8);
+        min_tree.update(5, 5, 7);
+        for i in 0..6 {
+            for j in i + 1..=6 {
+                assert_eq!(min_tree.query(i + 1, j), seg_tree.query(i..j));
+            }
+        }
+        assert_eq!(i32::MAX, seg_tree.query(2..2));
+    }
+
+    #[test]
+    fn test_empty() {
+        let seg_tree = MonoidSegmentTree::<Sum>::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+        assert_eq!(0, seg_tree.len());
+        assert_eq!(0, seg_tree.query(..));
+    }
+
+    #[test]
+    #[should_panic(expected = "query range out of bounds")]
+    fn test_reversed_range() {
+        let seg_tree = MonoidSegmentTree::<Sum>::from_vec(&[1, 2, 3, 4, 5]);
+        let (start, end) = (4, 2);
+        seg_tree.query(start..end);
+    }
+
+    #[test]
+    fn test_non_commutative() {
+        let values: Vec<String> = "segment".chars().map(|c| c.to_string()).collect();
+        let mut seg_tree = MonoidSegmentTree::<Concat>::from_vec(&values);
+        assert_eq!("segment", seg_tree.query(..));
+        assert_eq!("gmen", seg_tree.query(2..6));
+        seg_tree.set(0, "f".to_string());
+        assert_eq!(
+            "fragment",
+            format!("{}ra{}", &seg_tree.query(..1), seg_tree.query(2..))
+        );
+        assert_eq!(7, seg_tree.len());
+    }
+}
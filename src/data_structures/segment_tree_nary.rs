@@ -0,0 +1,242 @@
+use super::segment_tree_sum::{check_range, scaled, sum_of, Summable};
+
+/// This segment tree supports the same operations as
+/// `RangeSumSegmentTree`, but every node has B children instead of 2:
+/// 1. given an index i, j, query the sum of array in range [i, j]
+/// 2. given a range [x, y] and a diff, update all values of the array
+///    in range by diff
+///
+/// A wider node makes the tree log_B(n) deep instead of log_2(n), so a
+/// query visits fewer levels, and the children of a node sit next to
+/// each other in memory where a single cache line can hold several of
+/// their sums. In exchange each level scans up to B children, so small
+/// powers of two like 4 or 8 are the sweet spot. B = 2 is an ordinary
+/// binary segment tree.
+///
+/// The array is padded to B^h elements. Node p has the children
+/// p * B + 1 ..= p * B + B and the root is node 0, with every child
+/// covering an equal slice of its parent. Padding elements belong to
+/// no real index and are skipped.
+pub struct NaryRangeSumSegmentTree<T: Summable, const B: usize> {
+    // store total range [1, len]
+    len: usize,
+    // number of elements covered by the root, a power of B
+    capacity: usize,
+    arr: Vec<T>,
+    // implement lazy propagation
+    // mark[p] stores the diffs that have been applied
+    // to node p but has not been propagated to its child node
+    mark: Vec<T>,
+}
+
+impl<T: Summable, const B: usize> NaryRangeSumSegmentTree<T, B> {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[T]) -> Self {
+        assert!(B >= 2, "branching factor must be at least 2");
+        let n = values.len();
+        // a complete B-ary tree with capacity leaves
+        let (mut capacity, mut nodes) = (1, 1);
+        while capacity < n {
+            capacity *= B;
+            nodes += capacity;
+        }
+        let mut tree = Self {
+            len: n,
+            capacity,
+            arr: vec![T::default(); nodes],
+            mark: vec![T::default(); nodes],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, capacity, 0);
+        }
+        tree
+    }
+
+    // node p covers [cl, cl + size - 1]
+    fn build_rec(&mut self, values: &[T], cl: usize, size: usize, p: usize) {
+        if size == 1 {
+            self.arr[p] = values[cl - 1];
+            return;
+        }
+        let child_size = size / B;
+        let mut sum = T::default();
+        for k in 0..B {
+            let child_cl = cl + k * child_size;
+            if child_cl > self.len {
+                break;
+            }
+            self.build_rec(values, child_cl, child_size, p * B + 1 + k);
+            sum = sum_of(sum, self.arr[p * B + 1 + k]);
+        }
+        self.arr[p] = sum;
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // number of real elements in [cl, cl + size - 1]
+    fn real_length(&self, cl: usize, size: usize) -> usize {
+        (cl + size - 1).min(self.len) + 1 - cl
+    }
+
+    fn apply(&mut self, p: usize, length: usize, diff: T) {
+        self.arr[p] = sum_of(self.arr[p], scaled(diff, length));
+        self.mark[p] = sum_of(self.mark[p], diff);
+    }
+
+    fn push_down(&mut self, cl: usize, size: usize, p: usize) {
+        let mark = std::mem::take(&mut self.mark[p]);
+        // nothing pending, and scaling zero by a length that doesn't
+        // fit in T would be reported as an overflow
+        if mark == T::default() {
+            return;
+        }
+        let child_size = size / B;
+        for k in 0..B {
+            let child_cl = cl + k * child_size;
+            if child_cl > self.len {
+                break;
+            }
+            let length = self.real_length(child_cl, child_size);
+            self.apply(p * B + 1 + k, length, mark);
+        }
+    }
+
+    /// add diff to all element in range [i, j]
+    pub fn update(&mut self, i: usize, j: usize, diff: T) {
+        check_range(i, j, self.len).expect("update range out of bounds");
+        self.update_rec(i, j, 1, self.capacity, 0, diff)
+    }
+
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, size: usize, p: usize, diff: T) {
+        let cr = cl + size - 1;
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            let length = self.real_length(cl, size);
+            self.apply(p, length, diff);
+            return;
+        }
+
+        self.push_down(cl, size, p);
+
+        let child_size = size / B;
+        let mut sum = T::default();
+        for k in 0..B {
+            let child_cl = cl + k * child_size;
+            if child_cl > self.len {
+                break;
+            }
+            self.update_rec(l, r, child_cl, child_size, p * B + 1 + k, diff);
+            sum = sum_of(sum, self.arr[p * B + 1 + k]);
+        }
+        self.arr[p] = sum;
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> T {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.capacity, 0)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, size: usize, p: usize) -> T {
+        let cr = cl + size - 1;
+        if cl > r || cr < l {
+            return T::default();
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+
+        self.push_down(cl, size, p);
+
+        let child_size = size / B;
+        let mut sum = T::default();
+        for k in 0..B {
+            let child_cl = cl + k * child_size;
+            // children past r can't intersect the range
+            if child_cl > r {
+                break;
+            }
+            sum += self.query_rec(l, r, child_cl, child_size, p * B + 1 + k);
+        }
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use data_structures::RangeSumSegmentTree;
+    use math::PCG32;
+
+    #[test]
+    fn test_query_and_update() {
+        let values = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let mut seg_tree = NaryRangeSumSegmentTree::<i32, 4>::from_vec(&values);
+        assert_eq!(9, seg_tree.len());
+        assert_eq!(45, seg_tree.query(1, 9));
+        assert_eq!(18, seg_tree.query(3, 6));
+        seg_tree.update(2, 8, 10);
+        // new values should be [1, 12, 13, 14, 15, 16, 17, 18, 9]
+        assert_eq!(115, seg_tree.query(1, 9));
+        assert_eq!(9, seg_tree.query(9, 9));
+        seg_tree.update(4, 9, -5);
+        // new values should be [1, 12, 13, 9, 10, 11, 12, 13, 4]
+        assert_eq!(30, seg_tree.query(4, 6));
+        assert_eq!(85, seg_tree.query(1, 9));
+    }
+
+    #[test]
+    fn test_empty() {
+        let seg_tree = NaryRangeSumSegmentTree::<i64, 8>::from_vec(&[]);
+        assert!(seg_tree.is_empty());
+    }
+
+    #[test]
+    fn test_small_int_long_tree() {
+        // 300 elements don't fit in i8, only the sums have to
+        let mut seg_tree = NaryRangeSumSegmentTree::<i8, 4>::from_vec(&[0; 300]);
+        assert_eq!(0, seg_tree.query(1, 1));
+        seg_tree.update(100, 101, 3);
+        assert_eq!(6, seg_tree.query(1, 300));
+        assert_eq!(3, seg_tree.query(101, 250));
+    }
+
+    fn check_against_binary<const B: usize>(seed: u64) {
+        let mut rng = PCG32::new_default(seed);
+        for &n in &[1usize, 2, 7, 16, 65, 100] {
+            let values: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 100).collect();
+            let mut nary = NaryRangeSumSegmentTree::<i64, B>::from_vec(&values);
+            let mut binary = RangeSumSegmentTree::from_vec(&values);
+            for _ in 0..300 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                if rng.get_u32().is_multiple_of(2) {
+                    let diff = rng.get_u32() as i64 % 201 - 100;
+                    nary.update(l, r, diff);
+                    binary.update(l, r, diff);
+                } else {
+                    assert_eq!(binary.query(l, r), nary.query(l, r));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_against_binary_tree() {
+        check_against_binary::<2>(2);
+        check_against_binary::<4>(4);
+        check_against_binary::<8>(8);
+    }
+}
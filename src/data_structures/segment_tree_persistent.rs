@@ -0,0 +1,338 @@
+use super::segment_tree_sum::check_range;
+
+/// A persistent segment tree over an array of i64, supporting:
+/// 1. given a version v, an index i and a value, create a new
+///    version equal to v except that array[i] is set to value
+/// 2. given a version v and an index i, j, query the sum of
+///    array in range [i, j] as it was in version v
+///
+/// An update copies only the O(log n) nodes on the path from the root
+/// to the updated leaf, and shares every other node with the old version,
+/// so no version is ever modified once created.
+pub struct PersistentSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // every node of every version
+    nodes: Vec<Node>,
+    // roots[v] is the index of the root node of version v
+    roots: Vec<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    // indices of the children in nodes, unused for leaves
+    left: usize,
+    right: usize,
+    sum: i64,
+}
+
+impl PersistentSegmentTree {
+    /// build tree from an array of values, as version 0
+    pub fn from_vec(values: &[i64]) -> Self {
+        let mut tree = Self {
+            len: values.len(),
+            nodes: Vec::with_capacity(2 * values.len()),
+            roots: Vec::new(),
+        };
+        let root = if values.is_empty() {
+            tree.push(Node {
+                left: 0,
+                right: 0,
+                sum: 0,
+            })
+        } else {
+            tree.build_rec(values, 1, values.len())
+        };
+        tree.roots.push(root);
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[i64], left: usize, right: usize) -> usize {
+        if left == right {
+            return self.push(Node {
+                left: 0,
+                right: 0,
+                sum: values[left - 1],
+            });
+        }
+        let mid = left + (right - left) / 2;
+        let l = self.build_rec(values, left, mid);
+        let r = self.build_rec(values, mid + 1, right);
+        self.push(Node {
+            left: l,
+            right: r,
+            sum: self.nodes[l].sum + self.nodes[r].sum,
+        })
+    }
+
+    // add a node to the arena and return its index
+    fn push(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// create a new version from `version` with array[i] set to value,
+    /// and return the id of the new version
+    pub fn update(&mut self, version: usize, i: usize, value: i64) -> usize {
+        check_range(i, i, self.len).expect("index out of bounds");
+        let root = self.roots[version];
+        let new_root = self.update_rec(root, 1, self.len, i, value);
+        self.roots.push(new_root);
+        self.roots.len() - 1
+    }
+
+    // return the index of the copy of node p with array[i] set to value
+    fn update_rec(&mut self, p: usize, cl: usize, cr: usize, i: usize, value: i64) -> usize {
+        if cl == cr {
+            return self.push(Node {
+                left: 0,
+                right: 0,
+                sum: value,
+            });
+        }
+        let mut node = self.nodes[p];
+        let mid = cl + (cr - cl) / 2;
+        if i <= mid {
+            node.left = self.update_rec(node.left, cl, mid, i, value);
+        } else {
+            node.right = self.update_rec(node.right, mid + 1, cr, i, value);
+        }
+        node.sum = self.nodes[node.left].sum + self.nodes[node.right].sum;
+        self.push(node)
+    }
+
+    /// create a new version equal to `version` in O(1), sharing all of
+    /// its nodes, and return the id of the new version
+    pub fn fork(&mut self, version: usize) -> usize {
+        let root = self.roots[version];
+        self.roots.push(root);
+        self.roots.len() - 1
+    }
+
+    /// return the number of versions
+    pub fn version_count(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// return the number of nodes of all versions together
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// return the number of nodes reachable from the root of `version`.
+    /// Every version has the shape of the tree it was built as, so
+    /// this is 2n - 1 for n elements, or the lone root of an empty
+    /// tree, without walking the tree. node_count staying far below
+    /// reachable_node_count times version_count shows how much sharing
+    /// saves.
+    pub fn reachable_node_count(&self, version: usize) -> usize {
+        assert!(version < self.roots.len(), "version out of range");
+        if self.len == 0 {
+            1
+        } else {
+            2 * self.len - 1
+        }
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive in `version`
+    pub fn query(&self, version: usize, i: usize, j: usize) -> i64 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(self.roots[version], 1, self.len, i, j)
+    }
+
+    fn query_rec(&self, p: usize, cl: usize, cr: usize, l: usize, r: usize) -> i64 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.nodes[p].sum;
+        }
+        let mid = cl + (cr - cl) / 2;
+        self.query_rec(self.nodes[p].left, cl, mid, l, r)
+            + self.query_rec(self.nodes[p].right, mid + 1, cr, l, r)
+    }
+}
+
+/// Counts values by position and by value at once, supporting:
+/// 1. given a position range [l, r] and a value range [lo, hi],
+///    count the elements of array[l..=r] with lo <= value <= hi
+///
+/// in O(log n). This is a persistent segment tree indexed by value,
+/// where version k counts how often each value occurs among the first
+/// k elements, and version k is version k - 1 with one count raised.
+/// The count over positions [l, r] is then the difference of versions
+/// r and l - 1. Values are compressed to their sorted distinct values,
+/// so the tree has one leaf per distinct value.
+pub struct PersistentValueCountTree {
+    // sorted distinct values, leaf k counts values[k - 1]
+    values: Vec<i64>,
+    // version k counts array[1..=k]
+    counts: PersistentSegmentTree,
+}
+
+impl PersistentValueCountTree {
+    /// build the prefix versions of an array of values
+    pub fn from_vec(array: &[i64]) -> Self {
+        let mut values = array.to_vec();
+        values.sort_unstable();
+        values.dedup();
+        let mut counts = PersistentSegmentTree::from_vec(&vec![0; values.len()]);
+        for (k, value) in array.iter().enumerate() {
+            let leaf = values.binary_search(value).unwrap() + 1;
+            let count = counts.query(k, leaf, leaf);
+            counts.update(k, leaf, count + 1);
+        }
+        Self { values, counts }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        // version 0 is the empty prefix
+        self.counts.roots.len() - 1
+    }
+
+    /// return true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// return the number of elements of array[l..=r] with lo <= value <= hi
+    pub fn count_in_value_range(&self, l: usize, r: usize, lo: i64, hi: i64) -> usize {
+        check_range(l, r, self.len()).expect("query range out of bounds");
+        // leaves of the values in [lo, hi]
+        let first = self.values.partition_point(|&v| v < lo) + 1;
+        let last = self.values.partition_point(|&v| v <= hi);
+        if first > last {
+            return 0;
+        }
+        let count = self.counts.query(r, first, last) - self.counts.query(l - 1, first, last);
+        count as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_versions() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = PersistentSegmentTree::from_vec(&values);
+        assert_eq!(21, seg_tree.query(0, 1, 6));
+
+        let v1 = seg_tree.update(0, 3, 10);
+        // version 1 should be [1, 2, 10, 4, 5, 6]
+        assert_eq!(28, seg_tree.query(v1, 1, 6));
+        assert_eq!(21, seg_tree.query(0, 1, 6));
+
+        let v2 = seg_tree.update(v1, 6, 0);
+        // version 2 should be [1, 2, 10, 4, 5, 0]
+        assert_eq!(22, seg_tree.query(v2, 1, 6));
+        assert_eq!(9, seg_tree.query(v2, 4, 6));
+
+        // branch off the original array again
+        let v3 = seg_tree.update(0, 1, -1);
+        // version 3 should be [-1, 2, 3, 4, 5, 6]
+        assert_eq!(19, seg_tree.query(v3, 1, 6));
+        assert_eq!(4, seg_tree.query(v3, 1, 3));
+
+        // older versions still return their original sums
+        assert_eq!(6, seg_tree.query(0, 1, 3));
+        assert_eq!(13, seg_tree.query(v1, 1, 3));
+        assert_eq!(15, seg_tree.query(v1, 4, 6));
+        assert_eq!(13, seg_tree.query(v2, 1, 3));
+        for i in 1..=6 {
+            assert_eq!(values[i - 1], seg_tree.query(0, i, i));
+        }
+    }
+
+    #[test]
+    fn test_many_versions() {
+        let n = 50;
+        let mut seg_tree = PersistentSegmentTree::from_vec(&vec![0; n]);
+        // version v has array[i] = 1 for i <= v
+        for i in 1..=n {
+            let version = seg_tree.update(i - 1, i, 1);
+            assert_eq!(i, version);
+        }
+        for version in 0..=n {
+            assert_eq!(version as i64, seg_tree.query(version, 1, n));
+            for i in 1..=n {
+                let expected = if i <= version { 1 } else { 0 };
+                assert_eq!(expected, seg_tree.query(version, i, i));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fork_and_node_stats() {
+        let n = 100;
+        let mut seg_tree = PersistentSegmentTree::from_vec(&vec![1; n]);
+        assert_eq!(1, seg_tree.version_count());
+        assert_eq!(2 * n - 1, seg_tree.node_count());
+        assert_eq!(2 * n - 1, seg_tree.reachable_node_count(0));
+
+        // a tree of 100 elements is at most 8 levels deep, and an
+        // update copies one node per level
+        let mut version = 0;
+        for i in 1..=20 {
+            let before = seg_tree.node_count();
+            version = seg_tree.update(version, i * 5, 0);
+            let added = seg_tree.node_count() - before;
+            assert!(7 <= added && added <= 8, "update added {} nodes", added);
+            assert_eq!(2 * n - 1, seg_tree.reachable_node_count(version));
+        }
+        assert_eq!(21, seg_tree.version_count());
+
+        // forking adds a version but no node
+        let before = seg_tree.node_count();
+        let fork = seg_tree.fork(version);
+        assert_eq!(22, seg_tree.version_count());
+        assert_eq!(before, seg_tree.node_count());
+        assert_eq!(80, seg_tree.query(fork, 1, n));
+        let updated = seg_tree.update(fork, 1, 10);
+        assert_eq!(89, seg_tree.query(updated, 1, n));
+        assert_eq!(80, seg_tree.query(version, 1, n));
+        assert_eq!(80, seg_tree.query(fork, 1, n));
+
+        let empty = PersistentSegmentTree::from_vec(&[]);
+        assert_eq!(1, empty.reachable_node_count(0));
+    }
+
+    #[test]
+    fn test_count_in_value_range() {
+        let tree = PersistentValueCountTree::from_vec(&[5, 1, 4, 1, 5, 9, 2, 6]);
+        assert_eq!(8, tree.len());
+        assert_eq!(8, tree.count_in_value_range(1, 8, 0, 10));
+        assert_eq!(3, tree.count_in_value_range(1, 8, 1, 2));
+        // [4, 1, 5, 9] has 4 and 5 in [3, 5]
+        assert_eq!(2, tree.count_in_value_range(3, 6, 3, 5));
+        assert_eq!(1, tree.count_in_value_range(6, 6, 9, 9));
+        assert_eq!(0, tree.count_in_value_range(1, 8, 7, 8));
+        assert_eq!(0, tree.count_in_value_range(1, 8, 10, 20));
+        assert_eq!(0, tree.count_in_value_range(1, 8, 5, 4));
+    }
+
+    #[test]
+    fn test_count_in_value_range_against_brute_force() {
+        let mut rng = PCG32::new_default(141421);
+        for &n in &[1usize, 10, 64, 150] {
+            let array: Vec<i64> = (0..n).map(|_| rng.get_u32() as i64 % 50 - 25).collect();
+            let tree = PersistentValueCountTree::from_vec(&array);
+            for _ in 0..300 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                let lo = rng.get_u32() as i64 % 60 - 30;
+                let hi = lo + rng.get_u32() as i64 % 30;
+                let expected = array[l - 1..r]
+                    .iter()
+                    .filter(|&&v| lo <= v && v <= hi)
+                    .count();
+                assert_eq!(expected, tree.count_in_value_range(l, r, lo, hi));
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+/// A segment tree over the positions [0, max) that only allocates
+/// the nodes it needs, supporting:
+/// 1. add delta to the value at a position
+/// 2. query the sum of values in the range [l, r]
+///
+/// Every position starts at 0 and nodes are created on the path to a
+/// position when it is first updated, so memory is O(k log max) after k
+/// updates even when max is far too large for a dense tree.
+pub struct SparseSegmentTree {
+    max: usize,
+    // nodes[0] is the root, covering [0, max)
+    nodes: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    // indices of the children in nodes, None if never touched
+    left: Option<usize>,
+    right: Option<usize>,
+    sum: i64,
+}
+
+impl SparseSegmentTree {
+    /// construct a tree over positions [0, max), all zero
+    pub fn new(max: usize) -> Self {
+        assert!(max > 0, "tree must cover at least one position");
+        SparseSegmentTree {
+            max,
+            nodes: vec![Node::default()],
+        }
+    }
+
+    /// return the number of allocated nodes
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// add delta to the value at pos
+    pub fn add(&mut self, pos: usize, delta: i64) {
+        assert!(pos < self.max, "position out of bounds");
+        let (mut cl, mut cr, mut p) = (0, self.max - 1, 0);
+        loop {
+            self.nodes[p].sum += delta;
+            if cl == cr {
+                return;
+            }
+            let mid = cl + (cr - cl) / 2;
+            let go_left = pos <= mid;
+            let child = if go_left {
+                self.nodes[p].left
+            } else {
+                self.nodes[p].right
+            };
+            let child = match child {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    if go_left {
+                        self.nodes[p].left = Some(child);
+                    } else {
+                        self.nodes[p].right = Some(child);
+                    }
+                    child
+                }
+            };
+            if go_left {
+                cr = mid;
+            } else {
+                cl = mid + 1;
+            }
+            p = child;
+        }
+    }
+
+    /// return the sum of values in [l, r]
+    pub fn range_sum(&self, l: usize, r: usize) -> i64 {
+        assert!(l <= r && r < self.max, "invalid range");
+        self.query_rec(Some(0), 0, self.max - 1, l, r)
+    }
+
+    fn query_rec(&self, p: Option<usize>, cl: usize, cr: usize, l: usize, r: usize) -> i64 {
+        // untouched subtrees sum to zero
+        let p = match p {
+            Some(p) => p,
+            None => return 0,
+        };
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.nodes[p].sum;
+        }
+        let mid = cl + (cr - cl) / 2;
+        self.query_rec(self.nodes[p].left, cl, mid, l, r)
+            + self.query_rec(self.nodes[p].right, mid + 1, cr, l, r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_far_apart_positions() {
+        let max = 1_000_000_000;
+        let mut seg_tree = SparseSegmentTree::new(max);
+        seg_tree.add(0, 5);
+        seg_tree.add(123_456_789, 7);
+        seg_tree.add(999_999_999, -2);
+        seg_tree.add(500_000_000, 1);
+        seg_tree.add(123_456_789, 3);
+
+        assert_eq!(14, seg_tree.range_sum(0, max - 1));
+        assert_eq!(5, seg_tree.range_sum(0, 123_456_788));
+        assert_eq!(15, seg_tree.range_sum(0, 123_456_789));
+        assert_eq!(11, seg_tree.range_sum(1, 500_000_000));
+        assert_eq!(-1, seg_tree.range_sum(200_000_000, max - 1));
+        assert_eq!(0, seg_tree.range_sum(600_000_000, 999_999_998));
+        assert_eq!(-2, seg_tree.range_sum(999_999_999, 999_999_999));
+    }
+
+    #[test]
+    fn test_memory_proportional_to_updates() {
+        let max = 1 << 30;
+        let mut seg_tree = SparseSegmentTree::new(max);
+        let updates = 1000;
+        for k in 0..updates {
+            seg_tree.add(k * 1_000_003 % max, 1);
+        }
+        // each update allocates at most one node per level
+        assert!(seg_tree.node_count() <= 1 + updates * 30);
+        assert_eq!(updates as i64, seg_tree.range_sum(0, max - 1));
+    }
+}
@@ -0,0 +1,185 @@
+use super::segment_tree_sum::{calculate_length, check_range, scaled, sum_of, SegTreeError};
+
+/// This segment tree supports the same operations as
+/// `RangeSumSegmentTree<i32>`:
+/// 1. given an index i, j, query the sum of array in range [i, j]
+/// 2. given a range [x, y] and a diff, update all values of the array
+///    in range by diff
+///
+/// but keeps its nodes in fixed size arrays of CAP elements instead of
+/// a Vec, so the tree lives wherever it is declared, e.g. on the stack
+/// or in a static, and never touches the heap, with a bound on memory
+/// known at compile time. The crate itself still needs alloc for the
+/// other trees.
+/// A tree of n elements needs 2 * n.next_power_of_two() nodes, and
+/// `from_slice` fails for arrays that don't fit in CAP.
+pub struct StaticSegmentTree<const CAP: usize> {
+    // store total range [1, len]
+    len: usize,
+    // representation of the tree, where child of arr[p]
+    // is child arr[p * 2] and arr[p * 2 + 1]
+    arr: [i32; CAP],
+    // implement lazy propagation
+    // mark[p] stores the diffs that have been applied
+    // to node p but has not been propagated to its child node
+    mark: [i32; CAP],
+}
+
+impl<const CAP: usize> StaticSegmentTree<CAP> {
+    /// build tree from an array of values, or return `TooLarge` if
+    /// the tree needs more than CAP nodes
+    pub fn from_slice(values: &[i32]) -> Result<Self, SegTreeError> {
+        let n = values.len();
+        if calculate_length(n) > CAP {
+            return Err(SegTreeError::TooLarge { len: n });
+        }
+        let mut tree = Self {
+            len: n,
+            arr: [0; CAP],
+            mark: [0; CAP],
+        };
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+        Ok(tree)
+    }
+
+    fn build_rec(&mut self, values: &[i32], cl: usize, cr: usize, p: usize) {
+        if cl == cr {
+            self.arr[p] = values[cl - 1];
+            return;
+        }
+        let mid = cl + (cr - cl) / 2;
+        self.build_rec(values, cl, mid, p * 2);
+        self.build_rec(values, mid + 1, cr, p * 2 + 1);
+        self.arr[p] = sum_of(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn apply(&mut self, p: usize, length: usize, diff: i32) {
+        self.arr[p] = sum_of(self.arr[p], scaled(diff, length));
+        self.mark[p] = sum_of(self.mark[p], diff);
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let mark = core::mem::take(&mut self.mark[p]);
+        self.apply(p * 2, length.div_ceil(2), mark);
+        self.apply(p * 2 + 1, length / 2, mark);
+    }
+
+    /// add diff to all element in range [i, j]
+    pub fn update(&mut self, i: usize, j: usize, diff: i32) {
+        check_range(i, j, self.len).expect("update range out of bounds");
+        self.update_rec(i, j, 1, self.len, 1, diff)
+    }
+
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, diff: i32) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, cr - cl + 1, diff);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, diff);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, diff);
+        self.arr[p] = sum_of(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: usize, j: usize) -> i32 {
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i32 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        let left = self.query_rec(l, r, cl, mid, p * 2);
+        sum_of(left, self.query_rec(l, r, mid + 1, cr, p * 2 + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use data_structures::RangeSumSegmentTree;
+
+    #[test]
+    fn test_query_and_update() {
+        let values = [2, 5, 0, 2, 3, 5];
+        // 6 elements need 16 nodes
+        let mut seg_tree = StaticSegmentTree::<16>::from_slice(&values).unwrap();
+        assert_eq!(6, seg_tree.len());
+        assert_eq!(17, seg_tree.query(1, 6));
+        assert_eq!(5, seg_tree.query(2, 3));
+        seg_tree.update(2, 4, 1);
+        // new values should be [2, 6, 1, 3, 3, 5]
+        assert_eq!(20, seg_tree.query(1, 6));
+        assert_eq!(4, seg_tree.query(3, 4));
+    }
+
+    #[test]
+    fn test_over_capacity() {
+        let values = [1; 9];
+        assert_eq!(
+            Err(SegTreeError::TooLarge { len: 9 }),
+            StaticSegmentTree::<16>::from_slice(&values).map(|_| ())
+        );
+        assert!(StaticSegmentTree::<32>::from_slice(&values).is_ok());
+        assert!(StaticSegmentTree::<16>::from_slice(&values[..8]).is_ok());
+        assert!(StaticSegmentTree::<2>::from_slice(&[]).unwrap().is_empty());
+        assert!(StaticSegmentTree::<0>::from_slice(&[]).is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "segment tree sum overflow")]
+    fn test_update_overflow() {
+        let mut seg_tree = StaticSegmentTree::<16>::from_slice(&[0; 8]).unwrap();
+        // i32::MAX / 4 * 8 doesn't fit in i32
+        seg_tree.update(1, 8, i32::MAX / 4);
+    }
+
+    #[test]
+    fn test_against_heap_tree() {
+        let n = 37;
+        let values: Vec<i32> = (0..n).map(|i| (i * i * 7 % 19) as i32 - 9).collect();
+        let mut static_tree = StaticSegmentTree::<128>::from_slice(&values).unwrap();
+        let mut heap_tree = RangeSumSegmentTree::from_vec(&values);
+        for step in 0..500usize {
+            let a = step * 17 % n + 1;
+            let b = step * 31 % n + 1;
+            let (l, r) = (a.min(b), a.max(b));
+            if step % 3 == 0 {
+                let diff = (step % 11) as i32 - 5;
+                static_tree.update(l, r, diff);
+                heap_tree.update(l, r, diff);
+            } else {
+                assert_eq!(heap_tree.query(l, r), static_tree.query(l, r));
+            }
+        }
+    }
+}
@@ -1,58 +1,460 @@
-use std::convert::TryInto;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::error::Error;
+use core::fmt;
+use core::iter::FromIterator;
+use core::ops::{Add, AddAssign, Bound, Mul, RangeBounds, Sub};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Errors returned by the checked segment tree operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegTreeError {
+    /// `index` lies past the last element of a tree of `len` elements
+    IndexOutOfBounds { index: usize, len: usize },
+    /// [left, right] is not a valid 1-indexed inclusive range
+    InvalidRange { left: usize, right: usize },
+    /// a tree of `len` elements needs more nodes than can be allocated
+    TooLarge { len: usize },
+    /// bytes given to `from_bytes` are not an encoded tree
+    InvalidEncoding,
+}
+
+impl fmt::Display for SegTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SegTreeError::IndexOutOfBounds { index, len } => {
+                write!(f, "index {} out of bounds for length {}", index, len)
+            }
+            SegTreeError::InvalidRange { left, right } => {
+                write!(f, "invalid range [{}, {}]", left, right)
+            }
+            SegTreeError::TooLarge { len } => {
+                write!(f, "segment tree of length {} is too large", len)
+            }
+            SegTreeError::InvalidEncoding => write!(f, "invalid segment tree encoding"),
+        }
+    }
+}
+
+impl Error for SegTreeError {}
+
+/// Error returned by `RangeSumSegmentTree::leaves` when some update
+/// is still pending as a lazy mark, so the leaves are out of date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LazyPendingError;
+
+impl fmt::Display for LazyPendingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "segment tree has pending lazy marks")
+    }
+}
+
+impl Error for LazyPendingError {}
+
+/// Errors returned by the transaction methods of `RangeSumSegmentTree`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionError {
+    /// `begin_transaction` was called while a transaction is open
+    AlreadyActive,
+    /// `commit` or `rollback` was called with no transaction open
+    NotActive,
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransactionError::AlreadyActive => write!(f, "a transaction is already open"),
+            TransactionError::NotActive => write!(f, "no transaction is open"),
+        }
+    }
+}
+
+impl Error for TransactionError {}
+
+/// A position in a `RangeSumSegmentTree`, spelling out whether it
+/// counts from 0 or from 1. The tree itself counts from 1, and plain
+/// `usize` positions convert as one-based, so `tree.query(1, 6)` and
+/// `tree.query(LeafIndex::from_zero_based(0), 6)` are the same query.
+///
+/// Every position parameter of `RangeSumSegmentTree`, `FrozenSegmentTree`,
+/// `RangeAssignSumSegmentTree`, `RangeUpdateSumSegmentTree`,
+/// `CountedSumSegmentTree` and `ModSumSegmentTree` takes
+/// `impl Into<LeafIndex>`. The `SegRange` methods take 0-indexed
+/// std ranges instead, and positions returned by the trees, like
+/// `weighted_median`, are plain one-based `usize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LeafIndex(usize);
+
+impl LeafIndex {
+    /// the position i, where the first element is 0
+    pub fn from_zero_based(i: usize) -> Self {
+        LeafIndex(i + 1)
+    }
+
+    /// the position i, where the first element is 1
+    pub fn from_one_based(i: usize) -> Self {
+        LeafIndex(i)
+    }
+
+    /// return the position counting from 1
+    pub fn one_based(self) -> usize {
+        self.0
+    }
+}
+
+/// one-based, like every position the trees take
+impl From<usize> for LeafIndex {
+    fn from(i: usize) -> Self {
+        LeafIndex::from_one_based(i)
+    }
+}
+
+/// Numeric types that can be stored in a `RangeSumSegmentTree`.
+/// `T::default()` is used as zero.
+pub trait Summable:
+    Copy + Default + PartialEq + Add<Output = Self> + AddAssign + Mul<Output = Self>
+{
+    /// convert a segment length into `Self`, used to scale
+    /// a diff by the number of elements it is applied to
+    fn from_usize(n: usize) -> Self;
+
+    /// self + rhs, or None on overflow. Types that can't overflow
+    /// can rely on the default.
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    /// self * n, or None on overflow. Types that can't overflow
+    /// can rely on the default.
+    fn checked_scale(self, n: usize) -> Option<Self> {
+        Some(self * Self::from_usize(n))
+    }
+}
+
+/// Conversion of an element type to `f64`, rounding to the nearest
+/// `f64` where the value has more bits than it can hold. Used for
+/// averages, since `Into<f64>` leaves out 64 bit integers.
+pub trait ToF64 {
+    /// return self as the nearest f64
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_summable {
+    ($($t:ty),*) => {
+        $(
+            impl ToF64 for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+
+            impl Summable for $t {
+                fn from_usize(n: usize) -> Self {
+                    n as $t
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_scale(self, n: usize) -> Option<Self> {
+                    // zero scales to zero even when n doesn't fit in the type
+                    if self == 0 {
+                        return Some(0);
+                    }
+                    <$t as TryFrom<usize>>::try_from(n)
+                        .ok()
+                        .and_then(|n| <$t>::checked_mul(self, n))
+                }
+            }
+        )*
+    };
+}
+
+const OVERFLOW_MESSAGE: &str = "segment tree sum overflow";
+
+// a + b, checked for overflow in debug builds
+pub(crate) fn sum_of<T: Summable>(a: T, b: T) -> T {
+    if cfg!(debug_assertions) {
+        a.checked_add(b).expect(OVERFLOW_MESSAGE)
+    } else {
+        a + b
+    }
+}
+
+// diff * length, checked for overflow in debug builds
+pub(crate) fn scaled<T: Summable>(diff: T, length: usize) -> T {
+    if cfg!(debug_assertions) {
+        diff.checked_scale(length).expect(OVERFLOW_MESSAGE)
+    } else {
+        diff * T::from_usize(length)
+    }
+}
+
+impl_summable!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Ranges accepted by `query_range` and `update_range`. Unlike the
+/// 1-indexed inclusive `query` and `update`, these are 0-indexed, so
+/// `tree.query_range(2..5)` sums the 3rd, 4th and 5th element.
+/// Implemented for all std range types (`a..b`, `a..=b`, `a..`, `..b`, `..`).
+pub trait SegRange {
+    /// resolve the range to 0-indexed half-open bounds [start, end)
+    /// for a tree of `len` elements
+    fn bounds(&self, len: usize) -> (usize, usize);
+}
+
+impl<R: RangeBounds<usize>> SegRange for R {
+    fn bounds(&self, len: usize) -> (usize, usize) {
+        let start = match self.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match self.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => len,
+        };
+        (start, end)
+    }
+}
+
 /// This implementation of segment tree is built on
-/// an array of i32, and supports the following operation:
+/// an array of `T`, and supports the following operation:
 /// 1. given an index i, j, query the sum of array in range [i, j]
 /// 2. given a range [x, y] and a diff, update all values of the array
-/// in range by diff
+///    in range by diff
+///
 /// Any range sum query [i, j] can be easily answered by calling query(j) - query(i)
 ///
+/// Every range sum, and every diff times the length of the range it is
+/// added to, must fit in `T`. Debug builds panic with a clear message
+/// when an update breaks this, release builds wrap around like `T` does.
+///
+/// With the `serde` feature enabled the tree can be serialized and
+/// deserialized as is, including pending lazy marks. Trees of integers
+/// also have a compact binary encoding, see `to_bytes`.
 ///
-pub struct RangeSumSegmentTree {
+/// Cloning copies the tree along with its pending marks. Two trees
+/// compare equal when they hold the same array, no matter how the
+/// updates that produced it were split into lazy marks.
+///
+/// Changes can be made tentatively between `begin_transaction` and
+/// `rollback`, which restores the tree exactly as it was, e.g. to back
+/// out of a branch of a search. While a transaction is open, every
+/// node a method writes to is first recorded in an undo log, including
+/// the nodes that queries push marks down to, so the log grows by
+/// O(log n) entries per operation until `commit` or `rollback` frees it.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "RawRangeSumSegmentTree<T>",
+        bound(deserialize = "T: Summable + Deserialize<'de>")
+    )
+)]
+pub struct RangeSumSegmentTree<T: Summable> {
     // store total range [1, len]
     len: usize,
     // representation of the tree, where child of arr[p]
     // is child arr[p * 2] and arr[p * 2 + 1]
-    arr: Vec<i32>,
+    arr: Vec<T>,
     // implement lazy propagation
     // mark[p] stores the diffs that have been applied
     // to node p but has not been propagated to its child node
-    mark: Vec<i32>,
+    mark: Vec<T>,
+    // (p, arr[p], mark[p]) of every node written to since
+    // begin_transaction, None outside of a transaction
+    #[cfg_attr(feature = "serde", serde(skip))]
+    undo: Option<Vec<(usize, T, T)>>,
 }
 
-impl RangeSumSegmentTree {
+impl<T: Summable> RangeSumSegmentTree<T> {
     /// build tree from an array of values
-    pub fn from_vec(values: &[i32]) -> Self {
+    pub fn from_vec(values: &[T]) -> Self {
         let n = values.len();
         // our arr is 1-indexed
         let length = calculate_length(n);
         let mut tree = Self {
             len: n,
-            arr: vec![0; length],
-            mark: vec![0; length],
+            arr: vec![T::default(); length],
+            mark: vec![T::default(); length],
+            undo: None,
         };
 
-        tree.build_rec(values, 1, n, 1);
+        build_rec(&mut tree.arr, values, 1, n, 1);
 
         tree
     }
 
-    fn build_rec(&mut self, values: &[i32], left: usize, right: usize, p: usize) {
-        if left == right {
-            self.arr[p] = values[left - 1];
+    /// build tree from the projections map(&values[0]), map(&values[1]),
+    /// .. without collecting them first, e.g. to sum one field of every
+    /// element of a slice of structs
+    pub fn from_vec_mapped<U>(values: &[U], map: impl Fn(&U) -> T) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![T::default(); length],
+            mark: vec![T::default(); length],
+            undo: None,
+        };
+
+        build_mapped_rec(&mut tree.arr, values, &map, 1, n, 1);
+
+        tree
+    }
+
+    /// build tree from an array of values like `from_vec`, or return
+    /// `TooLarge` if the nodes of the tree would not fit in memory
+    /// instead of overflowing or aborting on allocation failure
+    pub fn try_from_vec(values: &[T]) -> Result<Self, SegTreeError> {
+        // arr and mark together must stay below isize::MAX bytes
+        let max_nodes = isize::MAX as usize / 2 / core::mem::size_of::<T>().max(1);
+        Self::try_from_vec_with_max_nodes(values, max_nodes)
+    }
+
+    /// build tree from an array of values like `from_vec`, or return
+    /// `TooLarge` if the tree would have more than max_nodes nodes
+    pub fn try_from_vec_with_max_nodes(
+        values: &[T],
+        max_nodes: usize,
+    ) -> Result<Self, SegTreeError> {
+        checked_length(values.len(), max_nodes)?;
+        Ok(Self::from_vec(values))
+    }
+
+    /// create an empty tree with storage reserved for trees of up to
+    /// max_len elements, to be filled by `build_into`. Rebuilding at
+    /// any length up to max_len then never allocates.
+    pub fn with_capacity(max_len: usize) -> Self {
+        let length = calculate_length(max_len);
+        let mut tree = Self::from_vec(&[]);
+        tree.arr.reserve_exact(length);
+        tree.mark.reserve_exact(length);
+        tree
+    }
+
+    /// rebuild the tree from values in O(n), as if by `from_vec`, in
+    /// the storage reserved by `with_capacity`. Returns `TooLarge` and
+    /// leaves the tree unchanged if values needs more nodes than that,
+    /// use `reset_from` to grow the storage instead.
+    pub fn build_into(&mut self, values: &[T]) -> Result<(), SegTreeError> {
+        let length = calculate_length(values.len());
+        if length > self.arr.capacity() || length > self.mark.capacity() {
+            return Err(SegTreeError::TooLarge { len: values.len() });
+        }
+        self.reset_from(values);
+        Ok(())
+    }
+
+    /// build a tree of len elements that are zero except at the given
+    /// (index, value) entries, in O(len + k log len) for k entries.
+    /// Values given for the same index are summed.
+    pub fn from_sparse(len: usize, entries: impl IntoIterator<Item = (usize, T)>) -> Self {
+        let length = calculate_length(len);
+        let mut tree = Self {
+            len,
+            arr: vec![T::default(); length],
+            mark: vec![T::default(); length],
+            undo: None,
+        };
+        for (i, value) in entries {
+            tree.add_at(i, value);
+        }
+        tree
+    }
+
+    /// set every element to value in O(n), reusing the tree's storage
+    pub fn clear_to(&mut self, value: T) {
+        if self.len > 0 {
+            self.fill_rec(value, 1, self.len, 1);
+        }
+    }
+
+    fn fill_rec(&mut self, value: T, cl: usize, cr: usize, p: usize) {
+        self.record(p);
+        self.arr[p] = value * T::from_usize(cr - cl + 1);
+        self.mark[p] = T::default();
+        if cl == cr {
             return;
         }
-        let mid = left + (right - left) / 2;
-        self.build_rec(values, left, mid, p * 2);
-        self.build_rec(values, mid + 1, right, p * 2 + 1);
-        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+        let mid = cl + (cr - cl) / 2;
+        self.fill_rec(value, cl, mid, p * 2);
+        self.fill_rec(value, mid + 1, cr, p * 2 + 1);
+    }
+
+    /// rebuild the tree from values in O(n), as if by `from_vec`. The
+    /// existing storage is reused, and only grows if values needs more.
+    pub fn reset_from(&mut self, values: &[T]) {
+        assert!(
+            self.undo.is_none(),
+            "cannot rebuild a tree inside a transaction"
+        );
+        let n = values.len();
+        let length = calculate_length(n);
+        self.len = n;
+        self.arr.clear();
+        self.arr.resize(length, T::default());
+        self.mark.clear();
+        self.mark.resize(length, T::default());
+        build_rec(&mut self.arr, values, 1, n, 1);
+    }
+
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
     /// add diff to all element in range [i, j]
-    pub fn update(&mut self, i: usize, j: usize, diff: i32) {
-        self.update_rec(i, j, 1, self.len, 1, diff)
+    pub fn update(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>, diff: T) {
+        self.try_update(i, j, diff)
+            .expect("update range out of bounds")
+    }
+
+    /// add diff to all element in range [i, j], returning an error
+    /// if [i, j] is not a valid range of the tree
+    pub fn try_update(
+        &mut self,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+        diff: T,
+    ) -> Result<(), SegTreeError> {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len)?;
+        self.update_rec(i, j, 1, self.len, 1, diff);
+        Ok(())
+    }
+
+    /// add diff to all elements in range [i, j] for every (i, j, diff)
+    /// in ops, in order. All ranges are checked before any is applied,
+    /// so an invalid range leaves the tree unchanged.
+    ///
+    /// The ops are applied one by one for now. Since range adds commute,
+    /// overlapping or adjacent ops could be coalesced first, e.g. through
+    /// a difference array over their endpoints, so that the tree is only
+    /// touched once per maximal run of equal total diff.
+    pub fn apply_batch<I: Copy + Into<LeafIndex>>(&mut self, ops: &[(I, I, T)]) {
+        let ops = ops
+            .iter()
+            .map(|&(i, j, diff)| (i.into().one_based(), j.into().one_based(), diff));
+        for (i, j, _) in ops.clone() {
+            check_range(i, j, self.len).expect("update range out of bounds");
+        }
+        for (i, j, diff) in ops {
+            self.update_rec(i, j, 1, self.len, 1, diff);
+        }
     }
 
-    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, diff: i32) {
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, diff: T) {
         // no intersection of current segment and target segment
         if cl > r || cr < l {
             return;
@@ -60,105 +462,3671 @@ impl RangeSumSegmentTree {
 
         // current segment is contained in target segment
         if cl >= l && cr <= r {
-            self.arr[p] += diff * (cr - cl + 1) as i32;
-            if l < r {
-                self.mark[p] += diff;
+            self.record(p);
+            self.arr[p] = sum_of(self.arr[p], scaled(diff, cr - cl + 1));
+            // leaves have no child to propagate to
+            if cl < cr {
+                self.mark[p] = sum_of(self.mark[p], diff);
             }
             return;
         }
 
-        self.push_down(p, (cr - cl + 1) as i32);
+        self.push_down(p, cr - cl + 1);
 
         let mid = cl + (cr - cl) / 2;
         self.update_rec(l, r, cl, mid, p * 2, diff);
         self.update_rec(l, r, mid + 1, cr, p * 2 + 1, diff);
 
-        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+        self.arr[p] = Self::combine(self.arr[p * 2], self.arr[p * 2 + 1]);
     }
 
-    fn push_down(&mut self, p: usize, length: i32) {
-        self.mark[p * 2] += self.mark[p];
-        self.mark[p * 2 + 1] += self.mark[p];
-        self.arr[p * 2] += self.mark[p] * ((length + 1) / 2);
-        self.arr[p * 2 + 1] += self.mark[p] * (length / 2);
-        self.mark[p] = 0;
+    fn push_down(&mut self, p: usize, length: usize) {
+        self.record(p);
+        self.record(p * 2);
+        self.record(p * 2 + 1);
+        let mark = self.mark[p];
+        let (left_length, right_length) = (length.div_ceil(2), length / 2);
+        // leaves never hold a mark
+        if left_length > 1 {
+            self.mark[p * 2] = sum_of(self.mark[p * 2], mark);
+        }
+        if right_length > 1 {
+            self.mark[p * 2 + 1] = sum_of(self.mark[p * 2 + 1], mark);
+        }
+        self.arr[p * 2] = sum_of(self.arr[p * 2], scaled(mark, left_length));
+        self.arr[p * 2 + 1] = sum_of(self.arr[p * 2 + 1], scaled(mark, right_length));
+        self.mark[p] = T::default();
     }
 
     /// return the range sum of array[i]..array[j] inclusive
-    pub fn query(&mut self, i: usize, j: usize) -> i32 {
-        self.query_rec(i, j, 1, self.len, 1)
+    pub fn query(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> T {
+        self.try_query(i, j).expect("query range out of bounds")
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive, or an
+    /// error if [i, j] is not a valid range of the tree
+    pub fn try_query(
+        &mut self,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+    ) -> Result<T, SegTreeError> {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len)?;
+        Ok(self.query_rec(i, j, 1, self.len, 1))
+    }
+
+    /// return the range sum of every inclusive range [i, j] in ranges,
+    /// in the same order. All ranges are checked before any is answered.
+    ///
+    /// Queries are answered one by one, which is already cheaper than it
+    /// looks: the first query through a node pushes its mark down, so
+    /// later queries sharing that path find nothing left to push. Sorting
+    /// the ranges and answering them in a single traversal would also share
+    /// the descent itself.
+    pub fn query_many<I: Copy + Into<LeafIndex>>(&mut self, ranges: &[(I, I)]) -> Vec<T> {
+        let ranges = ranges
+            .iter()
+            .map(|&(i, j)| (i.into().one_based(), j.into().one_based()));
+        for (i, j) in ranges.clone() {
+            check_range(i, j, self.len).expect("query range out of bounds");
+        }
+        ranges
+            .map(|(i, j)| self.query_rec(i, j, 1, self.len, 1))
+            .collect()
+    }
+
+    /// return the range sum over a 0-indexed range, e.g. `query_range(2..5)`
+    /// or `query_range(..)`. An empty range sums to zero, a reversed
+    /// range like `4..2` panics like `query` does.
+    pub fn query_range<R: SegRange>(&mut self, range: R) -> T {
+        let (start, end) = range.bounds(self.len);
+        if start == end && end <= self.len {
+            return self.identity();
+        }
+        self.query(start + 1, end)
+    }
+
+    /// add diff to all elements of a 0-indexed range, e.g. `update_range(2..5, 1)`.
+    /// An empty range leaves the tree unchanged, a reversed range
+    /// like `4..2` panics like `update` does.
+    pub fn update_range<R: SegRange>(&mut self, range: R, diff: T) {
+        let (start, end) = range.bounds(self.len);
+        if start == end && end <= self.len {
+            return;
+        }
+        self.update(start + 1, end, diff)
+    }
+
+    // the sum of an empty range
+    fn identity(&self) -> T {
+        T::default()
+    }
+
+    // the sum of two adjacent ranges with sums a and b
+    fn combine(a: T, b: T) -> T {
+        sum_of(a, b)
     }
 
-    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> i32 {
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> T {
         // no intersection of current segment and target segment
         if cl > r || cr < l {
-            return 0;
+            return self.identity();
         }
         // current segment is contained in target segment
         if cl >= l && cr <= r {
             return self.arr[p];
         }
         // push down
-        self.push_down(p, (cr - cl + 1) as i32);
+        self.push_down(p, cr - cl + 1);
         let mid = cl + (cr - cl) / 2;
-        self.query_rec(l, r, cl, mid, p * 2) + self.query_rec(l, r, mid + 1, cr, p * 2 + 1)
+        let left = self.query_rec(l, r, cl, mid, p * 2);
+        Self::combine(left, self.query_rec(l, r, mid + 1, cr, p * 2 + 1))
     }
-}
 
-// calculate the length needed for
-// a segmentree covering range [1, n]
-fn calculate_length(n: usize) -> usize {
-    let mut h = 1;
-    let mut cur = n;
-    while cur != 1 {
-        cur = (cur + 1) / 2;
-        h += 1;
+    /// call f(node_left, node_right, node_sum) for every node of the
+    /// canonical cover of [i, j], the O(log n) maximal nodes contained
+    /// in it, from left to right. Their sums add up to the range sum,
+    /// and are up to date, as pending marks above them are pushed down.
+    pub fn visit<F: FnMut(usize, usize, T)>(
+        &mut self,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+        mut f: F,
+    ) {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.visit_rec(i, j, 1, self.len, 1, &mut f)
     }
-    2usize.pow(h.try_into().unwrap())
-}
 
-#[cfg(test)]
-mod tests {
+    fn visit_rec<F: FnMut(usize, usize, T)>(
+        &mut self,
+        l: usize,
+        r: usize,
+        cl: usize,
+        cr: usize,
+        p: usize,
+        f: &mut F,
+    ) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            f(cl, cr, self.arr[p]);
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.visit_rec(l, r, cl, mid, p * 2, f);
+        self.visit_rec(l, r, mid + 1, cr, p * 2 + 1, f);
+    }
 
-    use super::*;
+    /// add diff to array[i]
+    pub fn add_at(&mut self, i: impl Into<LeafIndex>, diff: T) {
+        let i = i.into().one_based();
+        check_range(i, i, self.len).expect("index out of bounds");
+        // every node on the path from the root to leaf i covers i,
+        // and pending marks commute with addition, so no push down is needed
+        let (mut cl, mut cr, mut p) = (1, self.len, 1);
+        loop {
+            self.record(p);
+            self.arr[p] = sum_of(self.arr[p], diff);
+            if cl == cr {
+                return;
+            }
+            let mid = cl + (cr - cl) / 2;
+            if i <= mid {
+                cr = mid;
+                p *= 2;
+            } else {
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+    }
 
-    #[test]
-    fn test_query() {
-        let values = [1, 2, 3, 4, 5, 6];
-        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
-        assert_eq!(21, seg_tree.query(1, 6));
-        assert_eq!(5, seg_tree.query(2, 3));
-        assert_eq!(4, seg_tree.query(4, 4));
-        assert_eq!(12, seg_tree.query(3, 5));
+    /// return array[i]
+    pub fn get(&self, i: impl Into<LeafIndex>) -> T {
+        let i = i.into().one_based();
+        check_range(i, i, self.len).expect("index out of bounds");
+        // the leaf value plus every diff still pending on its ancestors
+        let (mut cl, mut cr, mut p) = (1, self.len, 1);
+        let mut pending = T::default();
+        while cl != cr {
+            pending += self.mark[p];
+            let mid = cl + (cr - cl) / 2;
+            if i <= mid {
+                cr = mid;
+                p *= 2;
+            } else {
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+        sum_of(self.arr[p], pending)
     }
 
-    #[test]
-    fn test_update() {
-        let values = [2, 4, 1, 3, 5, 7];
-        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+    /// set array[i] to value
+    pub fn set_at(&mut self, i: impl Into<LeafIndex>, value: T) {
+        let i = i.into().one_based();
+        check_range(i, i, self.len).expect("index out of bounds");
+        self.set_at_rec(i, 1, self.len, 1, value)
+    }
 
-        assert_eq!(8, seg_tree.query(2, 4));
+    fn set_at_rec(&mut self, i: usize, cl: usize, cr: usize, p: usize, value: T) {
+        if cl == cr {
+            self.record(p);
+            self.arr[p] = value;
+            self.mark[p] = T::default();
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        if i <= mid {
+            self.set_at_rec(i, cl, mid, p * 2, value);
+        } else {
+            self.set_at_rec(i, mid + 1, cr, p * 2 + 1, value);
+        }
+        self.arr[p] = Self::combine(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
 
-        // update
-        seg_tree.update(2, 4, 1);
-        // new values should be [2, 5, 2, 4, 5, 7]
-        assert_eq!(11, seg_tree.query(2, 4));
-        assert_eq!(5, seg_tree.query(2, 2));
-        assert_eq!(2, seg_tree.query(3, 3));
-        assert_eq!(4, seg_tree.query(4, 4));
-        assert_eq!(9, seg_tree.query(1, 3));
+    /// return the current values of the array, resolving
+    /// all pending lazy marks along the way
+    pub fn to_vec(&mut self) -> Vec<T> {
+        let mut values = Vec::with_capacity(self.len);
+        if self.len > 0 {
+            self.collect_rec(1, self.len, 1, &mut values);
+        }
+        values
+    }
 
-        seg_tree.update(3, 6, -2);
-        // new values should be [2, 5, 0, 2, 3, 5]
-        assert_eq!(8, seg_tree.query(5, 6));
-        assert_eq!(17, seg_tree.query(0, 6));
+    /// iterate over the elements in index order. Lazy marks are pushed
+    /// down as the iterator reaches them so every value is accurate, which
+    /// is why this needs `&mut self` even though no value changes.
+    pub fn iter(&mut self) -> SegTreeIter<'_, T> {
+        let stack = if self.len > 0 {
+            vec![(1, self.len, 1)]
+        } else {
+            Vec::new()
+        };
+        SegTreeIter { tree: self, stack }
     }
 
-    #[test]
-    fn test_build() {
-        for length in 10..10000 {
-            let values = vec![2; length];
-            let _seg_tree = RangeSumSegmentTree::from_vec(&values[..]);
+    /// iterate over the nodes of the tree as (p, cl, cr, arr[p], mark[p]),
+    /// where node p covers [cl, cr], parents before their children. This
+    /// shows the raw state, pending marks are not pushed down, e.g. for
+    /// visualizers or for checking invariants in tests.
+    pub fn debug_nodes(&self) -> impl Iterator<Item = (usize, usize, usize, T, T)> + '_ {
+        let mut stack = if self.len > 0 {
+            vec![(1, self.len, 1)]
+        } else {
+            Vec::new()
+        };
+        core::iter::from_fn(move || {
+            let (cl, cr, p) = stack.pop()?;
+            if cl < cr {
+                let mid = cl + (cr - cl) / 2;
+                stack.push((mid + 1, cr, p * 2 + 1));
+                stack.push((cl, mid, p * 2));
+            }
+            Some((p, cl, cr, self.arr[p], self.mark[p]))
+        })
+    }
+
+    fn collect_rec(&mut self, cl: usize, cr: usize, p: usize, values: &mut Vec<T>) {
+        if cl == cr {
+            values.push(self.arr[p]);
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.collect_rec(cl, mid, p * 2, values);
+        self.collect_rec(mid + 1, cr, p * 2 + 1, values);
+    }
+
+    /// push every pending lazy mark down to the leaves in O(n),
+    /// leaving every mark at zero
+    pub fn flush(&mut self) {
+        if self.len > 0 {
+            self.flush_rec(1, self.len, 1);
+        }
+        debug_assert!(self.mark.iter().all(|&mark| mark == T::default()));
+    }
+
+    fn flush_rec(&mut self, cl: usize, cr: usize, p: usize) {
+        if cl == cr {
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.flush_rec(cl, mid, p * 2);
+        self.flush_rec(mid + 1, cr, p * 2 + 1);
+    }
+
+    /// start recording changes so that `rollback` can undo them.
+    /// Transactions don't nest, so this fails if one is already open.
+    pub fn begin_transaction(&mut self) -> Result<(), TransactionError> {
+        if self.undo.is_some() {
+            return Err(TransactionError::AlreadyActive);
+        }
+        self.undo = Some(Vec::new());
+        Ok(())
+    }
+
+    /// keep the changes made since `begin_transaction`
+    pub fn commit(&mut self) -> Result<(), TransactionError> {
+        self.undo
+            .take()
+            .map(|_| ())
+            .ok_or(TransactionError::NotActive)
+    }
+
+    /// undo every change made since `begin_transaction`
+    pub fn rollback(&mut self) -> Result<(), TransactionError> {
+        let log = self.undo.take().ok_or(TransactionError::NotActive)?;
+        // the oldest record of a node is restored last
+        for &(p, arr, mark) in log.iter().rev() {
+            self.arr[p] = arr;
+            self.mark[p] = mark;
+        }
+        Ok(())
+    }
+
+    /// return true if a transaction is open
+    pub fn in_transaction(&self) -> bool {
+        self.undo.is_some()
+    }
+
+    // save node p to the undo log of an open transaction
+    fn record(&mut self, p: usize) {
+        if let Some(log) = self.undo.as_mut() {
+            log.push((p, self.arr[p], self.mark[p]));
+        }
+    }
+
+    /// flush the tree and return a read-only
+    /// tree whose queries take `&self`
+    pub fn freeze(mut self) -> FrozenSegmentTree<T> {
+        self.flush();
+        FrozenSegmentTree { tree: self }
+    }
+
+    /// iterate over the elements in index order without pushing
+    /// marks down, which only works if no mark is pending. Trees
+    /// that were only queried since their last `flush`, `to_vec`
+    /// or `iter` qualify.
+    pub fn leaves(&self) -> Result<impl Iterator<Item = T> + '_, LazyPendingError> {
+        if self.mark.iter().any(|&mark| mark != T::default()) {
+            return Err(LazyPendingError);
+        }
+        // (cl, cr, p) of the nodes left to visit, leftmost on top
+        let mut stack = if self.len > 0 {
+            vec![(1, self.len, 1)]
+        } else {
+            Vec::new()
+        };
+        Ok(core::iter::from_fn(move || loop {
+            let (cl, cr, p) = stack.pop()?;
+            if cl == cr {
+                return Some(self.arr[p]);
+            }
+            let mid = cl + (cr - cl) / 2;
+            stack.push((mid + 1, cr, p * 2 + 1));
+            stack.push((cl, mid, p * 2));
+        }))
+    }
+}
+
+impl<T: Summable + ToF64> RangeSumSegmentTree<T> {
+    /// return the average of array[i]..array[j] inclusive
+    pub fn range_average(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> f64 {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        self.query(i, j).to_f64() / (j - i + 1) as f64
+    }
+}
+
+impl<T: Summable + Sub<Output = T>> RangeSumSegmentTree<T> {
+    /// record the current values of the array, to compare
+    /// later range sums against with `diff_since`
+    pub fn checkpoint(&mut self) -> Checkpoint<T> {
+        let mut prefix = Vec::with_capacity(self.len + 1);
+        let mut sum = T::default();
+        prefix.push(sum);
+        for value in self.to_vec() {
+            sum += value;
+            prefix.push(sum);
+        }
+        Checkpoint { prefix }
+    }
+
+    /// return how much the sum of array[i]..array[j] inclusive has
+    /// changed since checkpoint was taken of this tree
+    pub fn diff_since(
+        &mut self,
+        checkpoint: &Checkpoint<T>,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+    ) -> T {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        assert_eq!(
+            checkpoint.len(),
+            self.len,
+            "checkpoint is of a tree of another length"
+        );
+        check_range(i, j, self.len).expect("query range out of bounds");
+        let before = checkpoint.prefix[j] - checkpoint.prefix[i - 1];
+        self.query(i, j) - before
+    }
+}
+
+// searches that descend the tree comparing partial sums,
+// these assume that every element of the array is nonnegative
+impl<T: Summable + PartialOrd + Sub<Output = T>> RangeSumSegmentTree<T> {
+    /// return the smallest index k such that
+    /// array[1] + .. + array[k] >= target, or None if the
+    /// sum of the whole array is less than target
+    pub fn lower_bound_prefix(&mut self, target: T) -> Option<usize> {
+        if self.len == 0 || self.arr[1] < target {
+            return None;
         }
+        let (mut cl, mut cr, mut p) = (1, self.len, 1);
+        let mut target = target;
+        while cl != cr {
+            self.push_down(p, cr - cl + 1);
+            let mid = cl + (cr - cl) / 2;
+            if self.arr[p * 2] >= target {
+                cr = mid;
+                p *= 2;
+            } else {
+                // the whole left half is in the prefix
+                target = target - self.arr[p * 2];
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+        Some(cl)
+    }
+
+    /// return the smallest m in [i, j] such that array[i] + .. + array[m]
+    /// is at least half of array[i] + .. + array[j], the weighted median
+    /// of [i, j], or None if every weight in [i, j] is zero
+    pub fn weighted_median(
+        &mut self,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+    ) -> Option<usize> {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        let total = self.query(i, j);
+        if total <= T::default() {
+            return None;
+        }
+        let before = if i > 1 {
+            self.query(1, i - 1)
+        } else {
+            T::default()
+        };
+        // x = array[i] + .. + array[m] is at least half of total when
+        // x >= total - x, which needs no doubling that could overflow.
+        // Prefix sums before i fall short, and x past total always reaches.
+        let reaches_half = |prefix: T| {
+            prefix >= before && {
+                let x = prefix - before;
+                x >= total || x >= total - x
+            }
+        };
+        // the smallest m whose prefix sum reaches half,
+        // found like lower_bound_prefix
+        let (mut cl, mut cr, mut p) = (1, self.len, 1);
+        // array[1] + .. + array[cl - 1]
+        let mut prefix = T::default();
+        while cl != cr {
+            self.push_down(p, cr - cl + 1);
+            let mid = cl + (cr - cl) / 2;
+            let through_mid = sum_of(prefix, self.arr[p * 2]);
+            if reaches_half(through_mid) {
+                cr = mid;
+                p *= 2;
+            } else {
+                prefix = through_mid;
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+        Some(cl)
+    }
+
+    /// return the shortest range [start, end] whose sum is exactly
+    /// target, or None if the sums from start skip over target. A
+    /// target of zero is met by the empty range (start, start - 1).
+    pub fn find_subrange_with_sum(
+        &mut self,
+        start: impl Into<LeafIndex>,
+        target: T,
+    ) -> Option<(usize, usize)> {
+        let start = start.into().one_based();
+        check_range(start, start, self.len).expect("index out of bounds");
+        if target == T::default() {
+            return Some((start, start - 1));
+        }
+        // the sums from start only grow, so the first end reaching
+        // target is the only candidate
+        let before = if start > 1 {
+            self.query(1, start - 1)
+        } else {
+            T::default()
+        };
+        let end = self.lower_bound_prefix(before + target)?;
+        // a negative target is reached before start, if at all
+        if end < start {
+            return None;
+        }
+        if self.query(start, end) == target {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    /// return the largest end such that array[start] + .. + array[end]
+    /// <= limit, which is start - 1 if array[start] alone exceeds limit,
+    /// and len if the whole suffix from start fits
+    pub fn max_reach(&mut self, start: impl Into<LeafIndex>, limit: T) -> usize {
+        let start = start.into().one_based();
+        check_range(start, start, self.len).expect("index out of bounds");
+        let mut remaining = limit;
+        self.max_reach_rec(start, 1, self.len, 1, &mut remaining)
+            .unwrap_or(self.len)
+    }
+
+    // consume the elements of [max(start, cl), cr] from remaining, and
+    // return the answer if an element that doesn't fit is found here
+    fn max_reach_rec(
+        &mut self,
+        start: usize,
+        cl: usize,
+        cr: usize,
+        p: usize,
+        remaining: &mut T,
+    ) -> Option<usize> {
+        if cr < start {
+            return None;
+        }
+        // the whole segment fits
+        if cl >= start && self.arr[p] <= *remaining {
+            *remaining = *remaining - self.arr[p];
+            return None;
+        }
+        if cl == cr {
+            return Some(cl - 1);
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.max_reach_rec(start, cl, mid, p * 2, remaining)
+            .or_else(|| self.max_reach_rec(start, mid + 1, cr, p * 2 + 1, remaining))
+    }
+}
+
+// The fields of a deserialized RangeSumSegmentTree, before checking
+// that they describe a well formed tree
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawRangeSumSegmentTree<T> {
+    len: usize,
+    arr: Vec<T>,
+    mark: Vec<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Summable> TryFrom<RawRangeSumSegmentTree<T>> for RangeSumSegmentTree<T> {
+    type Error = &'static str;
+
+    fn try_from(raw: RawRangeSumSegmentTree<T>) -> Result<Self, Self::Error> {
+        if raw.arr.len() != raw.mark.len() {
+            return Err("arr and mark have different lengths");
+        }
+        if raw.arr.len() != calculate_length(raw.len) {
+            return Err("arr length does not match len");
+        }
+        Ok(Self {
+            len: raw.len,
+            arr: raw.arr,
+            mark: raw.mark,
+            undo: None,
+        })
+    }
+}
+
+// The binary encoding of a tree is
+//
+//     b"SEGT" | version | len | array[1] | .. | array[len]
+//
+// where version is a single byte, len is an unsigned LEB128 and the
+// elements are LEB128 too, zigzag encoded for signed types so that
+// small negative values stay short. Only the leaves are stored, with
+// pending marks applied, and the inner nodes are rebuilt on load.
+const ENCODING_MAGIC: &[u8; 4] = b"SEGT";
+const ENCODING_VERSION: u8 = 1;
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+// read a LEB128 from the front of bytes, advancing past it
+fn read_leb128(bytes: &mut &[u8]) -> Result<u64, SegTreeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(SegTreeError::InvalidEncoding)?;
+        *bytes = rest;
+        let bits = u64::from(byte & 0x7f);
+        // the 10th byte may only hold the top bit of a u64
+        if shift == 63 && bits > 1 {
+            return Err(SegTreeError::InvalidEncoding);
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(SegTreeError::InvalidEncoding)
+}
+
+macro_rules! impl_bytes {
+    ($to_u64:expr, $from_u64:expr, $($t:ty),*) => {
+        $(
+            impl RangeSumSegmentTree<$t> {
+                /// encode the current values of the array, resolving all
+                /// pending marks, in a compact binary format that stores
+                /// the len elements instead of the whole tree
+                pub fn to_bytes(&mut self) -> Vec<u8> {
+                    let mut out = Vec::with_capacity(ENCODING_MAGIC.len() + 1 + self.len + 1);
+                    out.extend_from_slice(ENCODING_MAGIC);
+                    out.push(ENCODING_VERSION);
+                    write_leb128(&mut out, self.len as u64);
+                    for value in self.to_vec() {
+                        write_leb128(&mut out, $to_u64(value as i128));
+                    }
+                    out
+                }
+
+                /// rebuild a tree encoded by `to_bytes`, or return
+                /// `InvalidEncoding` if bytes is truncated, corrupt or
+                /// holds values that don't fit in the element type
+                pub fn from_bytes(bytes: &[u8]) -> Result<Self, SegTreeError> {
+                    let mut bytes = bytes
+                        .strip_prefix(&ENCODING_MAGIC[..])
+                        .ok_or(SegTreeError::InvalidEncoding)?;
+                    let (&version, rest) =
+                        bytes.split_first().ok_or(SegTreeError::InvalidEncoding)?;
+                    if version != ENCODING_VERSION {
+                        return Err(SegTreeError::InvalidEncoding);
+                    }
+                    bytes = rest;
+                    let len = read_leb128(&mut bytes)?;
+                    // every element takes at least one byte, which also
+                    // keeps a corrupt len from allocating a huge array
+                    if len > bytes.len() as u64 {
+                        return Err(SegTreeError::InvalidEncoding);
+                    }
+                    let mut values = Vec::with_capacity(len as usize);
+                    for _ in 0..len {
+                        let value: i128 = $from_u64(read_leb128(&mut bytes)?);
+                        let value =
+                            <$t>::try_from(value).map_err(|_| SegTreeError::InvalidEncoding)?;
+                        values.push(value);
+                    }
+                    if !bytes.is_empty() {
+                        return Err(SegTreeError::InvalidEncoding);
+                    }
+                    Ok(Self::from_vec(&values))
+                }
+            }
+        )*
+    };
+}
+
+// zigzag maps 0, -1, 1, -2, .. to 0, 1, 2, 3, ..
+impl_bytes!(
+    |value: i128| ((value << 1) ^ (value >> 127)) as u64,
+    |value: u64| (value >> 1) as i128 ^ -((value & 1) as i128),
+    i8,
+    i16,
+    i32,
+    i64,
+    isize
+);
+impl_bytes!(
+    |value: i128| value as u64,
+    |value: u64| value as i128,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize
+);
+
+#[cfg(feature = "std")]
+impl<T: Summable + core::str::FromStr> RangeSumSegmentTree<T> {
+    /// build a tree of len elements parsed from whitespace separated
+    /// numbers read from reader, e.g. a file or a socket. The numbers
+    /// are parsed straight into the leaves as they are read, so no
+    /// buffer of all the values is held in the meantime. Whatever
+    /// follows the first len numbers is left unread.
+    ///
+    /// Fails with `UnexpectedEof` if the reader holds fewer than len
+    /// numbers, and with `InvalidData` on a token that doesn't parse or
+    /// when the sum of a range of the numbers doesn't fit in T.
+    pub fn from_reader<R: std::io::BufRead>(mut reader: R, len: usize) -> std::io::Result<Self> {
+        let length = calculate_length(len);
+        let mut tree = Self {
+            len,
+            arr: vec![T::default(); length],
+            mark: vec![T::default(); length],
+            undo: None,
+        };
+        let mut token = Vec::new();
+        let mut next = || -> std::io::Result<T> {
+            if !read_token(&mut reader, &mut token)? {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "fewer numbers than the length of the tree",
+                ));
+            }
+            core::str::from_utf8(&token)
+                .ok()
+                .and_then(|text| text.parse().ok())
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("invalid number {:?}", String::from_utf8_lossy(&token)),
+                    )
+                })
+        };
+        if len > 0 {
+            build_read_rec(&mut tree.arr, &mut next, 1, len, 1)?;
+        }
+        Ok(tree)
+    }
+}
+
+// from_reader's build_rec, where leaf k is the k-th value returned by
+// next. The recursion reaches the leaves from left to right.
+#[cfg(feature = "std")]
+fn build_read_rec<T: Summable>(
+    arr: &mut [T],
+    next: &mut impl FnMut() -> std::io::Result<T>,
+    left: usize,
+    right: usize,
+    p: usize,
+) -> std::io::Result<()> {
+    if left == right {
+        arr[p] = next()?;
+        return Ok(());
+    }
+    let mid = left + (right - left) / 2;
+    build_read_rec(arr, next, left, mid, p * 2)?;
+    build_read_rec(arr, next, mid + 1, right, p * 2 + 1)?;
+    arr[p] = arr[p * 2]
+        .checked_add(arr[p * 2 + 1])
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, OVERFLOW_MESSAGE))?;
+    Ok(())
+}
+
+// read the next whitespace separated token of reader into token,
+// returning false if the reader ends before one starts
+#[cfg(feature = "std")]
+fn read_token<R: std::io::BufRead>(reader: &mut R, token: &mut Vec<u8>) -> std::io::Result<bool> {
+    token.clear();
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(!token.is_empty());
+        }
+        let mut used = 0;
+        let mut done = false;
+        for &byte in buf {
+            used += 1;
+            if !byte.is_ascii_whitespace() {
+                token.push(byte);
+            } else if !token.is_empty() {
+                done = true;
+                break;
+            }
+        }
+        reader.consume(used);
+        if done {
+            return Ok(true);
+        }
+    }
+}
+
+impl<T: Summable> FromIterator<T> for RangeSumSegmentTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let values: Vec<T> = iter.into_iter().collect();
+        Self::from_vec(&values)
+    }
+}
+
+/// Appending elements rebuilds the tree, costing O(n + k) for k new
+/// elements. The tree lives in an implicit array where the shape of
+/// every node depends on len, so a new element shifts the boundaries
+/// of every segment on its path and can't be patched in place.
+impl<T: Summable> Extend<T> for RangeSumSegmentTree<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter().peekable();
+        if iter.peek().is_none() {
+            return;
+        }
+        assert!(
+            self.undo.is_none(),
+            "cannot rebuild a tree inside a transaction"
+        );
+        let mut values = self.to_vec();
+        values.extend(iter);
+        *self = Self::from_vec(&values);
+    }
+}
+
+/// Iterator over the elements of a `RangeSumSegmentTree`,
+/// created by `RangeSumSegmentTree::iter`
+pub struct SegTreeIter<'a, T: Summable> {
+    tree: &'a mut RangeSumSegmentTree<T>,
+    // (cl, cr, p) of the nodes still to visit, leftmost on top
+    stack: Vec<(usize, usize, usize)>,
+}
+
+impl<'a, T: Summable> Iterator for SegTreeIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while let Some((cl, cr, p)) = self.stack.pop() {
+            if cl == cr {
+                return Some(self.tree.arr[p]);
+            }
+            self.tree.push_down(p, cr - cl + 1);
+            let mid = cl + (cr - cl) / 2;
+            self.stack.push((mid + 1, cr, p * 2 + 1));
+            self.stack.push((cl, mid, p * 2));
+        }
+        None
+    }
+}
+
+impl<'a, T: Summable> IntoIterator for &'a mut RangeSumSegmentTree<T> {
+    type Item = T;
+    type IntoIter = SegTreeIter<'a, T>;
+
+    fn into_iter(self) -> SegTreeIter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: Summable> PartialEq for RangeSumSegmentTree<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && (1..=self.len).all(|i| self.get(i) == other.get(i))
+    }
+}
+
+impl<T: Summable + Eq> Eq for RangeSumSegmentTree<T> {}
+
+// levels printed by the Debug impl before truncating,
+// enough to show every node of a tree with 16 elements
+const DEBUG_MAX_LEVELS: usize = 5;
+
+/// Prints the tree level by level, one line per level. Each node is shown
+/// as the range [cl, cr] it covers followed by its stored sum, and its
+/// pending lazy mark in parentheses if there is one. Levels past the
+/// fifth are elided.
+impl<T: Summable + fmt::Debug> fmt::Debug for RangeSumSegmentTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RangeSumSegmentTree {{ len: {} }}", self.len)?;
+        if self.len == 0 {
+            return Ok(());
+        }
+        // (cl, cr, p) of every node on the current level
+        let mut level = vec![(1, self.len, 1)];
+        let mut depth = 0;
+        while !level.is_empty() {
+            if depth == DEBUG_MAX_LEVELS {
+                return write!(f, "\n...");
+            }
+            let mut next = Vec::new();
+            for (k, &(cl, cr, p)) in level.iter().enumerate() {
+                let sep = if k == 0 { "\n" } else { " | " };
+                write!(f, "{}[{}, {}]: {:?}", sep, cl, cr, self.arr[p])?;
+                if self.mark[p] != T::default() {
+                    write!(f, " (+{:?})", self.mark[p])?;
+                }
+                if cl != cr {
+                    let mid = cl + (cr - cl) / 2;
+                    next.push((cl, mid, p * 2));
+                    next.push((mid + 1, cr, p * 2 + 1));
+                }
+            }
+            level = next;
+            depth += 1;
+        }
+        Ok(())
+    }
+}
+
+/// A `RangeSumSegmentTree` without pending lazy marks, made by `freeze`.
+/// With nothing left to push down, queries only read the tree and take
+/// `&self`, so a frozen tree can be shared by several threads at once.
+/// It can't be updated, `thaw` gives back the mutable tree.
+pub struct FrozenSegmentTree<T: Summable> {
+    tree: RangeSumSegmentTree<T>,
+}
+
+impl<T: Summable> FrozenSegmentTree<T> {
+    /// return the number of elements in the tree
+    pub fn len(&self) -> usize {
+        self.tree.len
+    }
+
+    /// return true if the tree holds no elements
+    pub fn is_empty(&self) -> bool {
+        self.tree.len == 0
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> T {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.tree.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.tree.len, 1)
+    }
+
+    fn query_rec(&self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> T {
+        if cl > r || cr < l {
+            return self.tree.identity();
+        }
+        if cl >= l && cr <= r {
+            return self.tree.arr[p];
+        }
+        let mid = cl + (cr - cl) / 2;
+        RangeSumSegmentTree::combine(
+            self.query_rec(l, r, cl, mid, p * 2),
+            self.query_rec(l, r, mid + 1, cr, p * 2 + 1),
+        )
+    }
+
+    /// return the mutable tree, holding the same array
+    pub fn thaw(self) -> RangeSumSegmentTree<T> {
+        self.tree
+    }
+}
+
+/// The values of a `RangeSumSegmentTree` at some point, made by
+/// `checkpoint`. They are kept as prefix sums, so the sum of any range
+/// at the time of the checkpoint takes O(1).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint<T> {
+    // prefix[k] is array[1] + .. + array[k]
+    prefix: Vec<T>,
+}
+
+impl<T> Checkpoint<T> {
+    /// return the number of elements of the tree at the checkpoint
+    pub fn len(&self) -> usize {
+        self.prefix.len() - 1
+    }
+
+    /// return true if the tree was empty at the checkpoint
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// This segment tree supports setting every element in a range
+/// to the same value, alongside range sum queries:
+/// 1. given an index i, j, query the sum of array in range [i, j]
+/// 2. given a range [x, y] and a value, set all values of the array
+///    in range to value
+/// 3. given an index i, j, count the nonzero elements in range [i, j]
+/// 4. given an index i, j, check whether all elements in range [i, j]
+///    are equal, or list the runs of equal elements in the range
+/// 5. given an index i, j, sum and count the positive elements in
+///    range [i, j]
+///
+/// Counting nonzero elements is cheap here because an assignment fixes
+/// the count of a whole segment at once; under range add, knowing the
+/// count of a segment says nothing about its count after the add.
+/// Likewise an assignment makes a whole segment uniform, and a node
+/// stays uniform until a smaller assignment splits it.
+pub struct RangeAssignSumSegmentTree<T: Summable> {
+    // store total range [1, len]
+    len: usize,
+    // same layout as RangeSumSegmentTree
+    arr: Vec<T>,
+    // mark[p] is Some(v) if every element covered by node p
+    // has been set to v but the assignment has not been
+    // propagated to its child nodes
+    mark: Vec<Option<T>>,
+    // nonzero[p] is the number of nonzero elements covered by node p
+    nonzero: Vec<usize>,
+    // uniform[p] is Some(v) if every element covered by node p is v
+    uniform: Vec<Option<T>>,
+    // positive[p] is the sum and number of the positive elements
+    // covered by node p, or None if it changed since last computed
+    positive: Vec<Option<(T, usize)>>,
+}
+
+impl<T: Summable> RangeAssignSumSegmentTree<T> {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[T]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![T::default(); length],
+            mark: vec![None; length],
+            nonzero: vec![0; length],
+            uniform: vec![None; length],
+            positive: vec![None; length],
+        };
+
+        build_rec(&mut tree.arr, values, 1, n, 1);
+        if n > 0 {
+            tree.build_summary_rec(values, 1, n, 1);
+        }
+
+        tree
+    }
+
+    // fill nonzero and uniform, arr is built by build_rec
+    fn build_summary_rec(&mut self, values: &[T], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.nonzero[p] = Self::count_of(values[left - 1], 1);
+            self.uniform[p] = Some(values[left - 1]);
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_summary_rec(values, left, mid, p * 2);
+        self.build_summary_rec(values, mid + 1, right, p * 2 + 1);
+        self.pull_up(p);
+    }
+
+    fn pull_up(&mut self, p: usize) {
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+        self.nonzero[p] = self.nonzero[p * 2] + self.nonzero[p * 2 + 1];
+        self.uniform[p] = match (self.uniform[p * 2], self.uniform[p * 2 + 1]) {
+            (Some(a), Some(b)) if a == b => Some(a),
+            _ => None,
+        };
+        self.positive[p] = None;
+    }
+
+    // number of nonzero elements in a segment of length elements all set to value
+    fn count_of(value: T, length: usize) -> usize {
+        if value == T::default() {
+            0
+        } else {
+            length
+        }
+    }
+
+    /// set all elements in range [i, j] to value
+    pub fn assign(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>, value: T) {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        self.assign_and_return_old_sum(i, j, value);
+    }
+
+    /// set all elements in range [i, j] to value, and return their sum
+    /// from before the assignment. The old sum is collected on the way
+    /// down, so this costs no more than `assign`.
+    pub fn assign_and_return_old_sum(
+        &mut self,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+        value: T,
+    ) -> T {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("assign range out of bounds");
+        self.assign_rec(i, j, 1, self.len, 1, value)
+    }
+
+    // return the sum of the overwritten elements of the current segment
+    fn assign_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, value: T) -> T {
+        // no intersection of current segment and target segment
+        if cl > r || cr < l {
+            return T::default();
+        }
+
+        // current segment is contained in target segment.
+        // A newer assignment simply replaces any that is still pending here
+        if cl >= l && cr <= r {
+            let old = self.arr[p];
+            self.arr[p] = value * T::from_usize(cr - cl + 1);
+            self.nonzero[p] = Self::count_of(value, cr - cl + 1);
+            self.uniform[p] = Some(value);
+            self.positive[p] = None;
+            if cl < cr {
+                self.mark[p] = Some(value);
+            }
+            return old;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        let old = self.assign_rec(l, r, cl, mid, p * 2, value)
+            + self.assign_rec(l, r, mid + 1, cr, p * 2 + 1, value);
+
+        self.pull_up(p);
+        old
+    }
+
+    // overwrite, rather than accumulate into, the children
+    fn push_down(&mut self, p: usize, length: usize) {
+        if let Some(value) = self.mark[p].take() {
+            self.mark[p * 2] = Some(value);
+            self.mark[p * 2 + 1] = Some(value);
+            self.arr[p * 2] = value * T::from_usize(length.div_ceil(2));
+            self.arr[p * 2 + 1] = value * T::from_usize(length / 2);
+            self.nonzero[p * 2] = Self::count_of(value, length.div_ceil(2));
+            self.nonzero[p * 2 + 1] = Self::count_of(value, length / 2);
+            self.uniform[p * 2] = Some(value);
+            self.uniform[p * 2 + 1] = Some(value);
+            self.positive[p * 2] = None;
+            self.positive[p * 2 + 1] = None;
+        }
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> T {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> T {
+        if cl > r || cr < l {
+            return T::default();
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.query_rec(l, r, cl, mid, p * 2) + self.query_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+
+    /// return the number of nonzero elements among array[i]..array[j] inclusive
+    pub fn count_nonzero(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> usize {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.count_nonzero_rec(i, j, 1, self.len, 1)
+    }
+
+    fn count_nonzero_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> usize {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.nonzero[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.count_nonzero_rec(l, r, cl, mid, p * 2)
+            + self.count_nonzero_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+
+    /// return Some(v) if every element among array[i]..array[j]
+    /// inclusive is v, and None otherwise
+    pub fn is_uniform(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> Option<T> {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.uniform_rec(i, j, 1, self.len, 1)
+    }
+
+    // the current segment intersects [l, r]
+    fn uniform_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> Option<T> {
+        // a uniform segment is uniform on any part of it too
+        if (cl >= l && cr <= r) || self.uniform[p].is_some() {
+            return self.uniform[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        if r <= mid {
+            return self.uniform_rec(l, r, cl, mid, p * 2);
+        }
+        if l > mid {
+            return self.uniform_rec(l, r, mid + 1, cr, p * 2 + 1);
+        }
+        let left = self.uniform_rec(l, r, cl, mid, p * 2)?;
+        let right = self.uniform_rec(l, r, mid + 1, cr, p * 2 + 1)?;
+        if left == right {
+            Some(left)
+        } else {
+            None
+        }
+    }
+
+    /// return the maximal runs of equal elements among array[i]..array[j]
+    /// inclusive as (start, end, value), in order. A uniform node is
+    /// emitted whole, so this is O(k log n) for k runs.
+    pub fn runs(
+        &mut self,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+    ) -> Vec<(usize, usize, T)> {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        let mut runs = Vec::new();
+        self.runs_rec(i, j, 1, self.len, 1, &mut runs);
+        runs
+    }
+
+    fn runs_rec(
+        &mut self,
+        l: usize,
+        r: usize,
+        cl: usize,
+        cr: usize,
+        p: usize,
+        runs: &mut Vec<(usize, usize, T)>,
+    ) {
+        if cl > r || cr < l {
+            return;
+        }
+        if let Some(value) = self.uniform[p] {
+            let (start, end) = (cl.max(l), cr.min(r));
+            // neighbouring nodes of the same value make one run
+            match runs.last_mut() {
+                Some(last) if last.2 == value => last.1 = end,
+                _ => runs.push((start, end, value)),
+            }
+            return;
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.runs_rec(l, r, cl, mid, p * 2, runs);
+        self.runs_rec(l, r, mid + 1, cr, p * 2 + 1, runs);
+    }
+}
+
+// positive sums are cached in every node, but only brought up to
+// date on demand, so that the other methods don't need PartialOrd
+impl<T: Summable + PartialOrd> RangeAssignSumSegmentTree<T> {
+    /// return the sum and the number of the positive elements
+    /// among array[i]..array[j] inclusive
+    pub fn query_positive(
+        &mut self,
+        i: impl Into<LeafIndex>,
+        j: impl Into<LeafIndex>,
+    ) -> (T, usize) {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.positive_rec(i, j, 1, self.len, 1)
+    }
+
+    fn positive_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> (T, usize) {
+        if cl > r || cr < l {
+            return (T::default(), 0);
+        }
+        if cl >= l && cr <= r {
+            return self.node_positive(cl, cr, p);
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        let left = self.positive_rec(l, r, cl, mid, p * 2);
+        let right = self.positive_rec(l, r, mid + 1, cr, p * 2 + 1);
+        (left.0 + right.0, left.1 + right.1)
+    }
+
+    // the positive sum and count of node p, recomputed where an
+    // assignment changed them. A node with a pending mark is uniform,
+    // so nodes that are not have up to date children.
+    fn node_positive(&mut self, cl: usize, cr: usize, p: usize) -> (T, usize) {
+        if let Some(positive) = self.positive[p] {
+            return positive;
+        }
+        let positive = match self.uniform[p] {
+            Some(value) if value > T::default() => {
+                (value * T::from_usize(cr - cl + 1), cr - cl + 1)
+            }
+            Some(_) => (T::default(), 0),
+            None => {
+                let mid = cl + (cr - cl) / 2;
+                let left = self.node_positive(cl, mid, p * 2);
+                let right = self.node_positive(mid + 1, cr, p * 2 + 1);
+                (left.0 + right.0, left.1 + right.1)
+            }
+        };
+        self.positive[p] = Some(positive);
+        positive
+    }
+}
+
+/// This segment tree supports both adding a diff to and setting
+/// a value on every element in a range, alongside range sum queries.
+pub struct RangeUpdateSumSegmentTree<T: Summable> {
+    // store total range [1, len]
+    len: usize,
+    // same layout as RangeSumSegmentTree
+    arr: Vec<T>,
+    // pending updates of node p that have not been
+    // propagated to its child nodes
+    mark: Vec<UpdateTag<T>>,
+}
+
+// A pending update: first set every element to `assign` if present,
+// then add `add` to every element.
+#[derive(Clone, Copy, Default)]
+struct UpdateTag<T> {
+    assign: Option<T>,
+    add: T,
+}
+
+impl<T: Summable> RangeUpdateSumSegmentTree<T> {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[T]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![T::default(); length],
+            mark: vec![UpdateTag::default(); length],
+        };
+
+        build_rec(&mut tree.arr, values, 1, n, 1);
+
+        tree
+    }
+
+    /// add diff to all elements in range [i, j]
+    pub fn add(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>, diff: T) {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("add range out of bounds");
+        self.update_rec(
+            i,
+            j,
+            1,
+            self.len,
+            1,
+            UpdateTag {
+                assign: None,
+                add: diff,
+            },
+        )
+    }
+
+    /// set all elements in range [i, j] to value
+    pub fn assign(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>, value: T) {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("assign range out of bounds");
+        self.update_rec(
+            i,
+            j,
+            1,
+            self.len,
+            1,
+            UpdateTag {
+                assign: Some(value),
+                add: T::default(),
+            },
+        )
+    }
+
+    fn update_rec(
+        &mut self,
+        l: usize,
+        r: usize,
+        cl: usize,
+        cr: usize,
+        p: usize,
+        tag: UpdateTag<T>,
+    ) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, cr - cl + 1, tag);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, tag);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, tag);
+
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+    }
+
+    // apply tag to node p covering length elements, composing it after
+    // whatever is already pending: an assignment discards any pending add,
+    // while an add after an assignment accumulates alongside it
+    fn apply(&mut self, p: usize, length: usize, tag: UpdateTag<T>) {
+        if let Some(value) = tag.assign {
+            self.arr[p] = value * T::from_usize(length);
+            self.mark[p] = UpdateTag {
+                assign: Some(value),
+                add: T::default(),
+            };
+        }
+        self.arr[p] += tag.add * T::from_usize(length);
+        self.mark[p].add += tag.add;
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let tag = core::mem::take(&mut self.mark[p]);
+        self.apply(p * 2, length.div_ceil(2), tag);
+        self.apply(p * 2 + 1, length / 2, tag);
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive
+    pub fn query(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> T {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> T {
+        if cl > r || cr < l {
+            return T::default();
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        self.query_rec(l, r, cl, mid, p * 2) + self.query_rec(l, r, mid + 1, cr, p * 2 + 1)
+    }
+}
+
+/// This segment tree pairs the sum of a range with the number of
+/// elements in it that are still present:
+/// 1. given an index i, j, query the sum of the present elements of
+///    array in range [i, j]
+/// 2. given an index i, j, query how many elements in range [i, j]
+///    are present
+/// 3. given an index i, delete array[i], or restore it with a value
+/// 4. given k, find the position of the k-th present element
+///
+/// Every element is present when the tree is built, and a deleted
+/// element counts as zero towards range sums. Deleting and restoring
+/// make the tree a set over the fixed universe [1, len] that can be
+/// indexed by rank, e.g. to seat people in the k-th free chair.
+pub struct CountedSumSegmentTree<T: Summable> {
+    // store total range [1, len]
+    len: usize,
+    // same layout as RangeSumSegmentTree
+    arr: Vec<T>,
+    // count[p] is the number of present elements covered by node p
+    count: Vec<usize>,
+}
+
+impl<T: Summable> CountedSumSegmentTree<T> {
+    /// build tree from an array of values, all of them present
+    pub fn from_vec(values: &[T]) -> Self {
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![T::default(); length],
+            count: vec![0; length],
+        };
+
+        build_rec(&mut tree.arr, values, 1, n, 1);
+        if n > 0 {
+            tree.build_count_rec(1, n, 1);
+        }
+
+        tree
+    }
+
+    fn build_count_rec(&mut self, left: usize, right: usize, p: usize) {
+        self.count[p] = right - left + 1;
+        if left == right {
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_count_rec(left, mid, p * 2);
+        self.build_count_rec(mid + 1, right, p * 2 + 1);
+    }
+
+    /// return the number of elements, present or not
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if the tree was built from no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// delete array[i], returning false if it was already deleted
+    pub fn delete_at(&mut self, i: impl Into<LeafIndex>) -> bool {
+        let i = i.into().one_based();
+        check_range(i, i, self.len).expect("index out of bounds");
+        if self.query_count(i, i) == 0 {
+            return false;
+        }
+        self.set_leaf_rec(i, 1, self.len, 1, T::default(), false);
+        true
+    }
+
+    /// make the deleted array[i] present again with value, returning
+    /// false, and changing nothing, if it was present already
+    pub fn restore_at(&mut self, i: impl Into<LeafIndex>, value: T) -> bool {
+        let i = i.into().one_based();
+        check_range(i, i, self.len).expect("index out of bounds");
+        if self.query_count(i, i) == 1 {
+            return false;
+        }
+        self.set_leaf_rec(i, 1, self.len, 1, value, true);
+        true
+    }
+
+    /// return the position of the k-th present element, counting from 1,
+    /// or None if fewer than k elements are present
+    pub fn kth_present(&self, k: usize) -> Option<usize> {
+        if k == 0 || self.len == 0 || self.count[1] < k {
+            return None;
+        }
+        let (mut cl, mut cr, mut p) = (1, self.len, 1);
+        let mut k = k;
+        while cl != cr {
+            let mid = cl + (cr - cl) / 2;
+            if self.count[p * 2] >= k {
+                cr = mid;
+                p *= 2;
+            } else {
+                k -= self.count[p * 2];
+                cl = mid + 1;
+                p = p * 2 + 1;
+            }
+        }
+        Some(cl)
+    }
+
+    // overwrite leaf i, then recompute sums and counts on the way up
+    fn set_leaf_rec(&mut self, i: usize, cl: usize, cr: usize, p: usize, value: T, present: bool) {
+        if cl == cr {
+            self.arr[p] = value;
+            self.count[p] = present as usize;
+            return;
+        }
+        let mid = cl + (cr - cl) / 2;
+        if i <= mid {
+            self.set_leaf_rec(i, cl, mid, p * 2, value, present);
+        } else {
+            self.set_leaf_rec(i, mid + 1, cr, p * 2 + 1, value, present);
+        }
+        self.arr[p] = self.arr[p * 2] + self.arr[p * 2 + 1];
+        self.count[p] = self.count[p * 2] + self.count[p * 2 + 1];
+    }
+
+    /// return the sum of the present elements among array[i]..array[j] inclusive
+    pub fn query(&self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> T {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1).0
+    }
+
+    /// return the number of present elements among array[i]..array[j] inclusive
+    pub fn query_count(&self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> usize {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1).1
+    }
+
+    // (sum, count) of the present elements in [l, r]
+    fn query_rec(&self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> (T, usize) {
+        if cl > r || cr < l {
+            return (T::default(), 0);
+        }
+        if cl >= l && cr <= r {
+            return (self.arr[p], self.count[p]);
+        }
+        let mid = cl + (cr - cl) / 2;
+        let (left_sum, left_count) = self.query_rec(l, r, cl, mid, p * 2);
+        let (right_sum, right_count) = self.query_rec(l, r, mid + 1, cr, p * 2 + 1);
+        (left_sum + right_sum, left_count + right_count)
+    }
+}
+
+impl<T: Summable + ToF64> CountedSumSegmentTree<T> {
+    /// return the average of the present elements among
+    /// array[i]..array[j] inclusive, or NaN if none is present
+    pub fn range_average(&self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> f64 {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        let (sum, count) = self.query_rec(i, j, 1, self.len, 1);
+        sum.to_f64() / count as f64
+    }
+}
+
+/// This segment tree is the `RangeSumSegmentTree` of u64 values,
+/// with every value, diff and sum kept modulo a modulus given
+/// at construction, as needed when sums are taken modulo
+/// a prime like 1e9 + 7.
+///
+/// Besides adding a diff, every element in a range can be multiplied
+/// by a constant. A node then has a pending multiply and a pending add,
+/// meaning x -> x * scale + diff for every element it covers. Applying
+/// a multiply by k on top turns that into x * scale * k + diff * k, so
+/// the multiply distributes over the pending add, and an add on top
+/// only changes diff. Both tags keep the same form, and `push_down`
+/// always hands the children the multiply first.
+pub struct ModSumSegmentTree {
+    // store total range [1, len]
+    len: usize,
+    // same layout as RangeSumSegmentTree
+    arr: Vec<u64>,
+    // mark[p] stores the diffs that have been applied
+    // to node p but has not been propagated to its child node
+    mark: Vec<u64>,
+    // scale[p] stores the factor the elements of node p have been
+    // multiplied by before mark[p] was added, 1 for none
+    scale: Vec<u64>,
+    modulus: u64,
+}
+
+impl ModSumSegmentTree {
+    /// build tree from an array of values, reducing
+    /// every value modulo modulus
+    pub fn from_vec_mod(values: &[u64], modulus: u64) -> Self {
+        assert!(modulus > 0, "modulus must be positive");
+        let n = values.len();
+        let length = calculate_length(n);
+        let mut tree = Self {
+            len: n,
+            arr: vec![0; length],
+            mark: vec![0; length],
+            scale: vec![1; length],
+            modulus,
+        };
+
+        if n > 0 {
+            tree.build_rec(values, 1, n, 1);
+        }
+
+        tree
+    }
+
+    fn build_rec(&mut self, values: &[u64], left: usize, right: usize, p: usize) {
+        if left == right {
+            self.arr[p] = values[left - 1] % self.modulus;
+            return;
+        }
+        let mid = left + (right - left) / 2;
+        self.build_rec(values, left, mid, p * 2);
+        self.build_rec(values, mid + 1, right, p * 2 + 1);
+        self.arr[p] = self.add_mod(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    /// the modulus all values are reduced by
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+    // a + b mod modulus, computed in u128 so that
+    // moduli close to u64::MAX don't overflow
+    fn add_mod(&self, a: u64, b: u64) -> u64 {
+        ((a as u128 + b as u128) % self.modulus as u128) as u64
+    }
+
+    // a * b mod modulus, computed in u128
+    fn mul_mod(&self, a: u64, b: u64) -> u64 {
+        ((a as u128 * b as u128) % self.modulus as u128) as u64
+    }
+
+    /// add diff to all element in range [i, j]
+    pub fn update(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>, diff: u64) {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("update range out of bounds");
+        let diff = diff % self.modulus;
+        self.update_rec(i, j, 1, self.len, 1, diff)
+    }
+
+    fn update_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, diff: u64) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, cr - cl + 1, 1, diff);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.update_rec(l, r, cl, mid, p * 2, diff);
+        self.update_rec(l, r, mid + 1, cr, p * 2 + 1, diff);
+
+        self.arr[p] = self.add_mod(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    /// multiply all element in range [i, j] by k
+    pub fn mul_range(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>, k: u64) {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("update range out of bounds");
+        let k = k % self.modulus;
+        self.mul_rec(i, j, 1, self.len, 1, k)
+    }
+
+    fn mul_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize, k: u64) {
+        if cl > r || cr < l {
+            return;
+        }
+        if cl >= l && cr <= r {
+            self.apply(p, cr - cl + 1, k, 0);
+            return;
+        }
+
+        self.push_down(p, cr - cl + 1);
+
+        let mid = cl + (cr - cl) / 2;
+        self.mul_rec(l, r, cl, mid, p * 2, k);
+        self.mul_rec(l, r, mid + 1, cr, p * 2 + 1, k);
+
+        self.arr[p] = self.add_mod(self.arr[p * 2], self.arr[p * 2 + 1]);
+    }
+
+    // multiply the length elements covered by node p by k, then add diff
+    fn apply(&mut self, p: usize, length: usize, k: u64, diff: u64) {
+        let scaled = self.mul_mod(diff, length as u64 % self.modulus);
+        self.arr[p] = self.add_mod(self.mul_mod(self.arr[p], k), scaled);
+        self.mark[p] = self.add_mod(self.mul_mod(self.mark[p], k), diff);
+        self.scale[p] = self.mul_mod(self.scale[p], k);
+    }
+
+    fn push_down(&mut self, p: usize, length: usize) {
+        let diff = core::mem::take(&mut self.mark[p]);
+        let k = core::mem::replace(&mut self.scale[p], 1);
+        if diff != 0 || k != 1 {
+            self.apply(p * 2, length.div_ceil(2), k, diff);
+            self.apply(p * 2 + 1, length / 2, k, diff);
+        }
+    }
+
+    /// return the range sum of array[i]..array[j] inclusive,
+    /// modulo the modulus
+    pub fn query(&mut self, i: impl Into<LeafIndex>, j: impl Into<LeafIndex>) -> u64 {
+        let (i, j) = (i.into().one_based(), j.into().one_based());
+        check_range(i, j, self.len).expect("query range out of bounds");
+        self.query_rec(i, j, 1, self.len, 1)
+    }
+
+    fn query_rec(&mut self, l: usize, r: usize, cl: usize, cr: usize, p: usize) -> u64 {
+        if cl > r || cr < l {
+            return 0;
+        }
+        if cl >= l && cr <= r {
+            return self.arr[p];
+        }
+        self.push_down(p, cr - cl + 1);
+        let mid = cl + (cr - cl) / 2;
+        let left = self.query_rec(l, r, cl, mid, p * 2);
+        let right = self.query_rec(l, r, mid + 1, cr, p * 2 + 1);
+        self.add_mod(left, right)
+    }
+}
+
+fn build_rec<T: Summable>(arr: &mut [T], values: &[T], left: usize, right: usize, p: usize) {
+    build_mapped_rec(arr, values, &|value: &T| *value, left, right, p)
+}
+
+// build_rec with leaf k set to map(&values[k - 1])
+fn build_mapped_rec<T: Summable, U>(
+    arr: &mut [T],
+    values: &[U],
+    map: &impl Fn(&U) -> T,
+    left: usize,
+    right: usize,
+    p: usize,
+) {
+    // only reached by an empty tree, which has nothing to build
+    if left > right {
+        return;
+    }
+    if left == right {
+        arr[p] = map(&values[left - 1]);
+        return;
+    }
+    let mid = left + (right - left) / 2;
+    build_mapped_rec(arr, values, map, left, mid, p * 2);
+    build_mapped_rec(arr, values, map, mid + 1, right, p * 2 + 1);
+    arr[p] = sum_of(arr[p * 2], arr[p * 2 + 1]);
+}
+
+// valid ranges satisfy 1 <= i <= j <= len
+pub(crate) fn check_range(i: usize, j: usize, len: usize) -> Result<(), SegTreeError> {
+    if i == 0 || i > j {
+        return Err(SegTreeError::InvalidRange { left: i, right: j });
+    }
+    if j > len {
+        return Err(SegTreeError::IndexOutOfBounds { index: j, len });
+    }
+    Ok(())
+}
+
+// calculate the length needed for
+// a segmentree covering range [1, n]
+//
+// Splitting [cl, cr] at mid = cl + (cr - cl) / 2 gives the left child
+// ceil(len / 2) elements, so a segment of length at most 2^k always has
+// halves of length at most 2^(k - 1). With 2^k = n.next_power_of_two()
+// the leaves are at depth at most k, and as node p at depth d satisfies
+// p < 2^(d + 1), every index is below 2 * n.next_power_of_two()
+pub(crate) fn calculate_length(n: usize) -> usize {
+    n.next_power_of_two() << 1
+}
+
+// calculate_length, or TooLarge if it overflows or exceeds max_nodes
+pub(crate) fn checked_length(n: usize, max_nodes: usize) -> Result<usize, SegTreeError> {
+    n.checked_next_power_of_two()
+        .and_then(|length| length.checked_mul(2))
+        .filter(|&length| length <= max_nodes)
+        .ok_or(SegTreeError::TooLarge { len: n })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_query() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(21, seg_tree.query(1, 6));
+        assert_eq!(5, seg_tree.query(2, 3));
+        assert_eq!(4, seg_tree.query(4, 4));
+        assert_eq!(12, seg_tree.query(3, 5));
+    }
+
+    #[test]
+    fn test_update() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+
+        assert_eq!(8, seg_tree.query(2, 4));
+
+        // update
+        seg_tree.update(2, 4, 1);
+        // new values should be [2, 5, 2, 4, 5, 7]
+        assert_eq!(11, seg_tree.query(2, 4));
+        assert_eq!(5, seg_tree.query(2, 2));
+        assert_eq!(2, seg_tree.query(3, 3));
+        assert_eq!(4, seg_tree.query(4, 4));
+        assert_eq!(9, seg_tree.query(1, 3));
+
+        seg_tree.update(3, 6, -2);
+        // new values should be [2, 5, 0, 2, 3, 5]
+        assert_eq!(8, seg_tree.query(5, 6));
+        assert_eq!(17, seg_tree.query(1, 6));
+    }
+
+    #[test]
+    fn test_build() {
+        for length in 10..10000 {
+            let values = vec![2; length];
+            let _seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values[..]);
+        }
+    }
+
+    #[test]
+    fn test_from_vec_mapped() {
+        struct Item {
+            name: &'static str,
+            weight: i32,
+        }
+        let items = [
+            Item {
+                name: "a",
+                weight: 4,
+            },
+            Item {
+                name: "bb",
+                weight: -1,
+            },
+            Item {
+                name: "ccc",
+                weight: 7,
+            },
+            Item {
+                name: "d",
+                weight: 0,
+            },
+            Item {
+                name: "ee",
+                weight: 3,
+            },
+        ];
+        let mut mapped = RangeSumSegmentTree::from_vec_mapped(&items, |item| item.weight);
+        let weights: Vec<i32> = items.iter().map(|item| item.weight).collect();
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&weights);
+        assert_eq!(seg_tree, mapped);
+        assert_eq!(13, mapped.query(1, 5));
+        assert_eq!(6, mapped.query(2, 3));
+
+        mapped.update(1, 3, 2);
+        seg_tree.update(1, 3, 2);
+        assert_eq!(seg_tree.to_vec(), mapped.to_vec());
+
+        // any summable type works as the projection
+        let mut lengths = RangeSumSegmentTree::from_vec_mapped(&items, |item| item.name.len());
+        assert_eq!(9, lengths.query(1, 5));
+        assert!(RangeSumSegmentTree::from_vec_mapped(&items[..0], |item| item.weight).is_empty());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let values = [2, -5, 0, 300, -70000, 5];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        // leaves pending marks behind
+        seg_tree.update(2, 5, -3);
+        let bytes = seg_tree.to_bytes();
+        assert_eq!(b"SEGT\x01\x06", &bytes[..6]);
+        let mut decoded = RangeSumSegmentTree::<i32>::from_bytes(&bytes).unwrap();
+        assert_eq!(seg_tree, decoded);
+        assert_eq!(seg_tree.query(1, 6), decoded.query(1, 6));
+        assert_eq!(vec![2, -8, -3, 297, -70003, 5], decoded.to_vec());
+        // 6 header bytes and 1 to 3 bytes per element
+        assert_eq!(6 + 1 + 1 + 1 + 2 + 3 + 1, bytes.len());
+
+        let extremes = [i64::MIN, i64::MAX, -1, 1];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&extremes);
+        let decoded = RangeSumSegmentTree::<i64>::from_bytes(&seg_tree.to_bytes()).unwrap();
+        assert_eq!(seg_tree, decoded);
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[u64::MAX - 255, 0, 127, 128]);
+        let decoded = RangeSumSegmentTree::<u64>::from_bytes(&seg_tree.to_bytes()).unwrap();
+        assert_eq!(seg_tree, decoded);
+
+        let mut empty = RangeSumSegmentTree::<u8>::from_vec(&[]);
+        assert_eq!(b"SEGT\x01\x00", &empty.to_bytes()[..]);
+        assert!(RangeSumSegmentTree::<u8>::from_bytes(&empty.to_bytes())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_bytes_invalid() {
+        let mut seg_tree = RangeSumSegmentTree::<i64>::from_vec(&[1, -200, 30000, 4]);
+        let bytes = seg_tree.to_bytes();
+        for end in 0..bytes.len() {
+            assert_eq!(
+                Err(SegTreeError::InvalidEncoding),
+                RangeSumSegmentTree::<i64>::from_bytes(&bytes[..end]).map(|_| ())
+            );
+        }
+        let invalid = |bytes: &[u8]| {
+            RangeSumSegmentTree::<i64>::from_bytes(bytes).map(|_| ())
+                == Err(SegTreeError::InvalidEncoding)
+        };
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(invalid(&trailing));
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(invalid(&magic));
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert!(invalid(&version));
+        // a len far larger than the bytes that follow
+        assert!(invalid(b"SEGT\x01\xff\xff\xff\xff\x0f\x00"));
+        // a LEB128 longer than a u64
+        assert!(invalid(
+            b"SEGT\x01\x01\xff\xff\xff\xff\xff\xff\xff\xff\xff\x7f"
+        ));
+
+        // values that don't fit in the element type
+        assert_eq!(
+            Err(SegTreeError::InvalidEncoding),
+            RangeSumSegmentTree::<i8>::from_bytes(&bytes).map(|_| ())
+        );
+        assert!(RangeSumSegmentTree::<i16>::from_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_try_from_vec() {
+        let values = [2, 5, 0, 2, 3, 5];
+        let mut seg_tree = RangeSumSegmentTree::try_from_vec(&values).unwrap();
+        assert_eq!(17, seg_tree.query(1, 6));
+        // 6 elements need 16 nodes
+        assert!(RangeSumSegmentTree::try_from_vec_with_max_nodes(&values, 16).is_ok());
+        assert_eq!(
+            Err(SegTreeError::TooLarge { len: 6 }),
+            RangeSumSegmentTree::try_from_vec_with_max_nodes(&values, 15).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_checked_length() {
+        for n in 0..100 {
+            assert_eq!(Ok(calculate_length(n)), checked_length(n, usize::MAX));
+        }
+        // the next power of two of these overflows, or doubling it does
+        let huge = usize::MAX / 2 + 2;
+        assert_eq!(
+            Err(SegTreeError::TooLarge { len: huge }),
+            checked_length(huge, usize::MAX)
+        );
+        let huge = usize::MAX / 4 + 2;
+        assert_eq!(
+            Err(SegTreeError::TooLarge { len: huge }),
+            checked_length(huge, usize::MAX)
+        );
+        assert_eq!(
+            Ok(usize::MAX / 2 + 1),
+            checked_length(usize::MAX / 4, usize::MAX)
+        );
+        assert_eq!(
+            Err(SegTreeError::TooLarge { len: 1 << 40 }),
+            checked_length(1 << 40, 1 << 40)
+        );
+    }
+
+    #[test]
+    fn test_try_query() {
+        let values = [2, 5, 0, 2, 3, 5];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(Ok(17), seg_tree.try_query(1, 6));
+        assert_eq!(
+            Err(SegTreeError::InvalidRange { left: 0, right: 6 }),
+            seg_tree.try_query(0, 6)
+        );
+        assert_eq!(
+            Err(SegTreeError::InvalidRange { left: 4, right: 3 }),
+            seg_tree.try_query(4, 3)
+        );
+        assert_eq!(
+            Err(SegTreeError::IndexOutOfBounds { index: 9, len: 6 }),
+            seg_tree.try_query(7, 9)
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[]);
+        assert_eq!(0, seg_tree.len());
+        assert!(seg_tree.is_empty());
+        assert_eq!(
+            Err(SegTreeError::IndexOutOfBounds { index: 1, len: 0 }),
+            seg_tree.try_query(1, 1)
+        );
+        assert_eq!(0, seg_tree.query_range(..));
+        seg_tree.update_range(.., 1);
+        assert_eq!(Vec::<i32>::new(), seg_tree.to_vec());
+        assert_eq!(None, seg_tree.lower_bound_prefix(1));
+
+        // the other sum trees can be built empty too
+        RangeAssignSumSegmentTree::<i32>::from_vec(&[]);
+        RangeUpdateSumSegmentTree::<i32>::from_vec(&[]);
+        ModSumSegmentTree::from_vec_mod(&[], 7);
+    }
+
+    #[test]
+    fn test_single_element() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[5]);
+        assert_eq!(1, seg_tree.len());
+        assert!(!seg_tree.is_empty());
+        assert_eq!(5, seg_tree.query(1, 1));
+        seg_tree.update(1, 1, 2);
+        assert_eq!(7, seg_tree.query(1, 1));
+        assert_eq!(7, seg_tree.get(1));
+        assert_eq!(vec![7], seg_tree.to_vec());
+        assert_eq!(
+            Err(SegTreeError::IndexOutOfBounds { index: 2, len: 1 }),
+            seg_tree.try_query(1, 2)
+        );
+    }
+
+    #[test]
+    fn test_try_update() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(
+            Err(SegTreeError::IndexOutOfBounds { index: 7, len: 6 }),
+            seg_tree.try_update(1, 7, 1)
+        );
+        // a failed update leaves the tree untouched
+        assert_eq!(21, seg_tree.query(1, 6));
+        assert_eq!(Ok(()), seg_tree.try_update(1, 6, 1));
+        assert_eq!(27, seg_tree.query(1, 6));
+    }
+
+    #[test]
+    #[should_panic(expected = "query range out of bounds")]
+    fn test_query_out_of_bounds() {
+        let values = [1, 2, 3];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        seg_tree.query(2, 4);
+    }
+
+    #[test]
+    fn test_query_many() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        seg_tree.update(2, 5, 3);
+        let ranges = [(1, 6), (2, 3), (4, 4), (3, 6), (1, 1), (2, 5)];
+        let answers = seg_tree.query_many(&ranges);
+        let expected: Vec<i32> = ranges.iter().map(|&(i, j)| seg_tree.query(i, j)).collect();
+        assert_eq!(expected, answers);
+        assert_eq!(Vec::<i32>::new(), seg_tree.query_many::<usize>(&[]));
+    }
+
+    #[test]
+    #[should_panic(expected = "query range out of bounds")]
+    fn test_query_many_out_of_bounds() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3]);
+        seg_tree.query_many(&[(1, 2), (2, 4)]);
+    }
+
+    #[test]
+    fn test_query_range() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(seg_tree.query(1, 6), seg_tree.query_range(..));
+        assert_eq!(seg_tree.query(3, 5), seg_tree.query_range(2..5));
+        assert_eq!(seg_tree.query(3, 5), seg_tree.query_range(2..=4));
+        assert_eq!(seg_tree.query(4, 6), seg_tree.query_range(3..));
+        assert_eq!(seg_tree.query(1, 2), seg_tree.query_range(..2));
+        assert_eq!(seg_tree.query(1, 3), seg_tree.query_range(..=2));
+        assert_eq!(seg_tree.query(4, 4), seg_tree.query_range(3..4));
+        assert_eq!(0, seg_tree.query_range(3..3));
+        assert_eq!(0, seg_tree.query_range(6..6));
+    }
+
+    #[test]
+    #[should_panic(expected = "query range out of bounds")]
+    fn test_query_range_reversed() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3, 4, 5, 6]);
+        let (start, end) = (4, 2);
+        seg_tree.query_range(start..end);
+    }
+
+    #[test]
+    #[should_panic(expected = "query range out of bounds")]
+    fn test_query_range_empty_out_of_bounds() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3]);
+        seg_tree.query_range(7..7);
+    }
+
+    #[test]
+    #[should_panic(expected = "update range out of bounds")]
+    fn test_update_range_reversed() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3, 4, 5, 6]);
+        let (start, end) = (5, 1);
+        seg_tree.update_range(start..end, 1);
+    }
+
+    #[test]
+    fn test_update_range() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut legacy = RangeSumSegmentTree::<i32>::from_vec(&values);
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+
+        legacy.update(2, 4, 1);
+        seg_tree.update_range(1..4, 1);
+        legacy.update(3, 6, -2);
+        seg_tree.update_range(2.., -2);
+        legacy.update(1, 6, 3);
+        seg_tree.update_range(.., 3);
+        // empty range is a no-op
+        seg_tree.update_range(4..4, 100);
+
+        for i in 1..=6 {
+            for j in i..=6 {
+                assert_eq!(legacy.query(i, j), seg_tree.query_range(i - 1..j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_point_operations() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+
+        // lazy marks pending above index 3
+        seg_tree.update(1, 6, 2);
+        seg_tree.update(2, 4, -1);
+        // values should be [4, 5, 2, 4, 7, 9]
+        assert_eq!(2, seg_tree.get(3));
+
+        seg_tree.add_at(3, 10);
+        assert_eq!(12, seg_tree.get(3));
+        assert_eq!(12, seg_tree.query(3, 3));
+        assert_eq!(21, seg_tree.query(2, 4));
+
+        seg_tree.update(3, 5, 1);
+        assert_eq!(13, seg_tree.get(3));
+        seg_tree.set_at(3, 0);
+        assert_eq!(0, seg_tree.get(3));
+        // values should be [4, 5, 0, 5, 8, 9]
+        assert_eq!(31, seg_tree.query(1, 6));
+        assert_eq!(5, seg_tree.get(4));
+        assert_eq!(8, seg_tree.get(5));
+
+        seg_tree.add_at(1, -4);
+        assert_eq!(0, seg_tree.get(1));
+        assert_eq!(27, seg_tree.query(1, 6));
+    }
+
+    #[test]
+    fn test_to_vec() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(values.to_vec(), seg_tree.to_vec());
+
+        seg_tree.update(2, 4, 1);
+        seg_tree.update(3, 6, -2);
+        assert_eq!(vec![2, 5, 0, 2, 3, 5], seg_tree.to_vec());
+        // resolving the marks doesn't change the answers
+        assert_eq!(8, seg_tree.query(5, 6));
+        assert_eq!(17, seg_tree.query(1, 6));
+    }
+
+    #[test]
+    fn test_iter() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        seg_tree.update(2, 4, 1);
+        seg_tree.update(3, 6, -2);
+        let mut expected = seg_tree.clone();
+        let collected: Vec<i32> = seg_tree.iter().collect();
+        assert_eq!(expected.to_vec(), collected);
+        assert_eq!(17, seg_tree.iter().sum::<i32>());
+
+        let mut doubled = Vec::new();
+        for v in &mut seg_tree {
+            doubled.push(v * 2);
+        }
+        assert_eq!(vec![4, 10, 0, 4, 6, 10], doubled);
+        assert_eq!(
+            None,
+            RangeSumSegmentTree::<i32>::from_vec(&[]).iter().next()
+        );
+    }
+
+    #[test]
+    fn test_clear_to() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[2, 4, 1, 3, 5, 7]);
+        seg_tree.update(2, 4, 1);
+        seg_tree.clear_to(3);
+        assert_eq!(vec![3; 6], seg_tree.to_vec());
+        assert_eq!(18, seg_tree.query(1, 6));
+        assert_eq!(9, seg_tree.query(2, 4));
+        seg_tree.update(1, 2, 1);
+        assert_eq!(20, seg_tree.query(1, 6));
+        seg_tree.clear_to(0);
+        assert_eq!(0, seg_tree.query(1, 6));
+    }
+
+    #[test]
+    fn test_reset_from() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        seg_tree.update(1, 4, 3);
+        seg_tree.set_at(6, -1);
+
+        let fresh_values = [6, 5, 4, 3, 2, 1];
+        seg_tree.reset_from(&fresh_values);
+        let mut fresh = RangeSumSegmentTree::from_vec(&fresh_values);
+        for i in 1..=6 {
+            for j in i..=6 {
+                assert_eq!(fresh.query(i, j), seg_tree.query(i, j));
+            }
+        }
+        seg_tree.update(2, 5, 2);
+        fresh.update(2, 5, 2);
+        assert_eq!(fresh.to_vec(), seg_tree.to_vec());
+
+        // a different length rebuilds at the new size
+        seg_tree.reset_from(&[1, 2, 3]);
+        assert_eq!(3, seg_tree.len());
+        assert_eq!(6, seg_tree.query(1, 3));
+        seg_tree.reset_from(&[1; 20]);
+        assert_eq!(20, seg_tree.len());
+        assert_eq!(20, seg_tree.query(1, 20));
+        seg_tree.reset_from(&[]);
+        assert!(seg_tree.is_empty());
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::with_capacity(50);
+        assert!(seg_tree.is_empty());
+        let storage = seg_tree.arr.as_ptr();
+        for round in 0..10 {
+            let values: Vec<i32> = (0..50 - round * 5).map(|i| (i * 7 + round) % 11).collect();
+            assert_eq!(Ok(()), seg_tree.build_into(&values));
+            seg_tree.update(1, values.len(), 1);
+            let mut fresh = RangeSumSegmentTree::from_vec(&values);
+            fresh.update(1, values.len(), 1);
+            assert_eq!(fresh.to_vec(), seg_tree.to_vec());
+            assert_eq!(
+                fresh.query(1, values.len()),
+                seg_tree.query(1, values.len())
+            );
+            // the reserved storage is reused every round
+            assert_eq!(storage, seg_tree.arr.as_ptr());
+        }
+        assert_eq!(Ok(()), seg_tree.build_into(&[]));
+        assert!(seg_tree.is_empty());
+    }
+
+    #[test]
+    fn test_build_into_over_capacity() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::with_capacity(8);
+        seg_tree.build_into(&[1, 2, 3]).unwrap();
+        assert_eq!(
+            Err(SegTreeError::TooLarge { len: 100 }),
+            seg_tree.build_into(&[1; 100])
+        );
+        // the tree is left as it was
+        assert_eq!(vec![1, 2, 3], seg_tree.to_vec());
+
+        // reset_from grows the storage instead
+        seg_tree.reset_from(&[1; 100]);
+        assert_eq!(100, seg_tree.query(1, 100));
+        assert_eq!(Ok(()), seg_tree.build_into(&[2; 100]));
+        assert_eq!(200, seg_tree.query(1, 100));
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let mut collected: RangeSumSegmentTree<i32> = (1..=6).collect();
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[1, 2, 3, 4, 5, 6]);
+        for i in 1..=6 {
+            for j in i..=6 {
+                assert_eq!(seg_tree.query(i, j), collected.query(i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3]);
+        seg_tree.update(1, 3, 1);
+        seg_tree.extend(vec![4, 5, 6]);
+        // values should be [2, 3, 4, 4, 5, 6]
+        assert_eq!(vec![2, 3, 4, 4, 5, 6], seg_tree.to_vec());
+        assert_eq!(24, seg_tree.query(1, 6));
+        assert_eq!(13, seg_tree.query(3, 5));
+        seg_tree.extend(None);
+        assert_eq!(24, seg_tree.query(1, 6));
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3, 4, 5, 6]);
+        seg_tree.update(2, 5, 1);
+        let mut cloned = seg_tree.clone();
+        cloned.update(1, 6, 1);
+        assert_eq!(25, seg_tree.query(1, 6));
+        assert_eq!(31, cloned.query(1, 6));
+    }
+
+    #[test]
+    fn test_eq() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut a = RangeSumSegmentTree::<i32>::from_vec(&values);
+        let mut b = RangeSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(a, b);
+
+        // both end up as [1, 4, 5, 4, 5, 6], with different marks pending
+        a.update(2, 4, 1);
+        a.update(2, 3, 1);
+        a.update(4, 5, -1);
+        a.update(5, 5, 1);
+        b.update(1, 6, 1);
+        b.update(1, 1, -1);
+        b.update(2, 3, 1);
+        b.update(4, 6, -1);
+        assert_eq!(a, b);
+        assert_eq!(a.clone(), b);
+
+        b.set_at(6, 7);
+        assert_ne!(a, b);
+        assert_ne!(a, RangeSumSegmentTree::from_vec(&[1, 4, 5, 4, 5]));
+        assert_eq!(a, RangeSumSegmentTree::from_vec(&[1, 4, 5, 4, 5, 6]));
+    }
+
+    #[test]
+    fn test_debug() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(
+            format!("{:?}", seg_tree),
+            "RangeSumSegmentTree { len: 6 }\n\
+             [1, 6]: 21\n\
+             [1, 3]: 6 | [4, 6]: 15\n\
+             [1, 2]: 3 | [3, 3]: 3 | [4, 5]: 9 | [6, 6]: 6\n\
+             [1, 1]: 1 | [2, 2]: 2 | [4, 4]: 4 | [5, 5]: 5"
+        );
+
+        seg_tree.update(1, 3, 1);
+        assert_eq!(
+            format!("{:?}", seg_tree),
+            "RangeSumSegmentTree { len: 6 }\n\
+             [1, 6]: 24\n\
+             [1, 3]: 9 (+1) | [4, 6]: 15\n\
+             [1, 2]: 3 | [3, 3]: 3 | [4, 5]: 9 | [6, 6]: 6\n\
+             [1, 1]: 1 | [2, 2]: 2 | [4, 4]: 4 | [5, 5]: 5"
+        );
+
+        let seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1; 100]);
+        let output = format!("{:?}", seg_tree);
+        assert_eq!(DEBUG_MAX_LEVELS + 2, output.lines().count());
+        assert!(output.ends_with("\n..."));
+    }
+
+    #[test]
+    fn test_assign() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = RangeAssignSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(22, seg_tree.query(1, 6));
+        assert_eq!(8, seg_tree.query(2, 4));
+
+        seg_tree.assign(2, 4, 1);
+        // new values should be [2, 1, 1, 1, 5, 7]
+        assert_eq!(3, seg_tree.query(2, 4));
+        assert_eq!(17, seg_tree.query(1, 6));
+        assert_eq!(6, seg_tree.query(4, 5));
+
+        seg_tree.assign(3, 6, -2);
+        // new values should be [2, 1, -2, -2, -2, -2]
+        assert_eq!(-4, seg_tree.query(5, 6));
+        assert_eq!(-5, seg_tree.query(1, 6));
+        assert_eq!(1, seg_tree.query(2, 2));
+    }
+
+    #[test]
+    fn test_assign_and_return_old_sum() {
+        let values = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut seg_tree = RangeAssignSumSegmentTree::from_vec(&values);
+        let before = seg_tree.query(2, 5);
+        assert_eq!(before, seg_tree.assign_and_return_old_sum(2, 5, 10));
+        // new values should be [1, 10, 10, 10, 10, 6, 7, 8]
+        assert_eq!(40, seg_tree.query(2, 5));
+        assert_eq!(62, seg_tree.query(1, 8));
+
+        // overlaps the pending assignment
+        let before = seg_tree.query(4, 7);
+        assert_eq!(33, before);
+        assert_eq!(before, seg_tree.assign_and_return_old_sum(4, 7, 0));
+        // new values should be [1, 10, 10, 0, 0, 0, 0, 8]
+        assert_eq!(29, seg_tree.query(1, 8));
+        assert_eq!(20, seg_tree.assign_and_return_old_sum(2, 3, -1));
+        assert_eq!(7, seg_tree.query(1, 8));
+    }
+
+    #[test]
+    fn test_count_nonzero() {
+        let values = [0, 4, 1, 0, 5, 7, 0, 2];
+        let mut seg_tree = RangeAssignSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(5, seg_tree.count_nonzero(1, 8));
+        assert_eq!(2, seg_tree.count_nonzero(1, 3));
+        assert_eq!(0, seg_tree.count_nonzero(4, 4));
+
+        seg_tree.assign(2, 6, 0);
+        // new values should be [0, 0, 0, 0, 0, 0, 0, 2]
+        assert_eq!(1, seg_tree.count_nonzero(1, 8));
+        assert_eq!(0, seg_tree.count_nonzero(1, 7));
+
+        seg_tree.assign(3, 7, -3);
+        // new values should be [0, 0, -3, -3, -3, -3, -3, 2]
+        assert_eq!(6, seg_tree.count_nonzero(1, 8));
+        assert_eq!(2, seg_tree.count_nonzero(2, 4));
+
+        seg_tree.assign(4, 5, 0);
+        seg_tree.assign(1, 1, 9);
+        // new values should be [9, 0, -3, 0, 0, -3, -3, 2]
+        assert_eq!(5, seg_tree.count_nonzero(1, 8));
+        assert_eq!(1, seg_tree.count_nonzero(2, 5));
+        assert_eq!(3, seg_tree.count_nonzero(5, 8));
+        assert_eq!(2, seg_tree.query(1, 8));
+    }
+
+    #[test]
+    fn test_is_uniform() {
+        let values = [3, 3, 1, 2, 2, 2, 5];
+        let mut seg_tree = RangeAssignSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(Some(3), seg_tree.is_uniform(1, 2));
+        assert_eq!(Some(2), seg_tree.is_uniform(4, 6));
+        assert_eq!(Some(1), seg_tree.is_uniform(3, 3));
+        assert_eq!(None, seg_tree.is_uniform(2, 3));
+        assert_eq!(None, seg_tree.is_uniform(1, 7));
+
+        seg_tree.assign(2, 6, 4);
+        // new values should be [3, 4, 4, 4, 4, 4, 5]
+        assert_eq!(Some(4), seg_tree.is_uniform(2, 6));
+        assert_eq!(Some(4), seg_tree.is_uniform(3, 5));
+        assert_eq!(None, seg_tree.is_uniform(1, 6));
+        assert_eq!(None, seg_tree.is_uniform(2, 7));
+
+        seg_tree.assign(1, 7, 0);
+        assert_eq!(Some(0), seg_tree.is_uniform(1, 7));
+        // a smaller assignment breaks it up
+        seg_tree.assign(4, 4, 1);
+        // new values should be [0, 0, 0, 1, 0, 0, 0]
+        assert_eq!(None, seg_tree.is_uniform(1, 7));
+        assert_eq!(None, seg_tree.is_uniform(3, 4));
+        assert_eq!(Some(0), seg_tree.is_uniform(1, 3));
+        assert_eq!(Some(0), seg_tree.is_uniform(5, 7));
+        assert_eq!(Some(1), seg_tree.is_uniform(4, 4));
+
+        // and an assignment of the same value restores it
+        seg_tree.assign(4, 4, 0);
+        assert_eq!(Some(0), seg_tree.is_uniform(1, 7));
+    }
+
+    #[test]
+    fn test_runs() {
+        let values = [1, 1, 2, 2, 2, 3, 1, 1];
+        let mut seg_tree = RangeAssignSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(
+            vec![(1, 2, 1), (3, 5, 2), (6, 6, 3), (7, 8, 1)],
+            seg_tree.runs(1, 8)
+        );
+        assert_eq!(vec![(2, 2, 1), (3, 4, 2)], seg_tree.runs(2, 4));
+
+        seg_tree.assign(2, 6, 7);
+        seg_tree.assign(4, 8, 1);
+        seg_tree.assign(5, 5, 7);
+        // new values should be [1, 7, 7, 1, 7, 1, 1, 1]
+        assert_eq!(
+            vec![(1, 1, 1), (2, 3, 7), (4, 4, 1), (5, 5, 7), (6, 8, 1)],
+            seg_tree.runs(1, 8)
+        );
+        seg_tree.assign(2, 5, 1);
+        assert_eq!(vec![(1, 8, 1)], seg_tree.runs(1, 8));
+        assert_eq!(vec![(3, 6, 1)], seg_tree.runs(3, 6));
+    }
+
+    #[test]
+    fn test_runs_against_brute_force() {
+        let mut rng = Lcg(2718);
+        let n = 50;
+        let mut values: Vec<i32> = (0..n).map(|i| i as i32 % 3).collect();
+        let mut seg_tree = RangeAssignSumSegmentTree::from_vec(&values);
+        for _ in 0..300 {
+            let (a, b) = (rng.range(1, n), rng.range(1, n));
+            let (l, r) = (a.min(b), a.max(b));
+            let value = (rng.next() % 3) as i32;
+            seg_tree.assign(l, r, value);
+            for v in values[l - 1..r].iter_mut() {
+                *v = value;
+            }
+
+            let (a, b) = (rng.range(1, n), rng.range(1, n));
+            let (l, r) = (a.min(b), a.max(b));
+            // run length encoding of values[l..=r]
+            let mut expected: Vec<(usize, usize, i32)> = Vec::new();
+            for k in l..=r {
+                match expected.last_mut() {
+                    Some(last) if last.2 == values[k - 1] => last.1 = k,
+                    _ => expected.push((k, k, values[k - 1])),
+                }
+            }
+            assert_eq!(expected, seg_tree.runs(l, r));
+        }
+    }
+
+    #[test]
+    fn test_query_positive() {
+        let values = [3, -1, 0, 5, -2, 4, 1, -6];
+        let mut seg_tree = RangeAssignSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!((13, 4), seg_tree.query_positive(1, 8));
+        assert_eq!((5, 1), seg_tree.query_positive(2, 5));
+        assert_eq!((0, 0), seg_tree.query_positive(2, 3));
+
+        seg_tree.assign(2, 5, 2);
+        // new values should be [3, 2, 2, 2, 2, 4, 1, -6]
+        assert_eq!((16, 7), seg_tree.query_positive(1, 8));
+        seg_tree.assign(4, 7, 0);
+        // new values should be [3, 2, 2, 0, 0, 0, 0, -6]
+        assert_eq!((7, 3), seg_tree.query_positive(1, 8));
+        assert_eq!((2, 1), seg_tree.query_positive(3, 6));
+        seg_tree.assign(1, 3, -4);
+        seg_tree.assign(6, 8, 7);
+        // new values should be [-4, -4, -4, 0, 0, 7, 7, 7]
+        assert_eq!((21, 3), seg_tree.query_positive(1, 8));
+        assert_eq!((0, 0), seg_tree.query_positive(1, 5));
+        assert_eq!((7, 1), seg_tree.query_positive(5, 6));
+        assert_eq!(9, seg_tree.query(1, 8));
+    }
+
+    #[test]
+    fn test_query_positive_against_brute_force() {
+        let mut rng = Lcg(1414);
+        let n = 40;
+        let mut values: Vec<i64> = (0..n).map(|i| (i as i64 * 7) % 11 - 5).collect();
+        let mut seg_tree = RangeAssignSumSegmentTree::from_vec(&values);
+        for _ in 0..300 {
+            let (a, b) = (rng.range(1, n), rng.range(1, n));
+            let (l, r) = (a.min(b), a.max(b));
+            let value = (rng.next() % 7) as i64 - 3;
+            seg_tree.assign(l, r, value);
+            for v in values[l - 1..r].iter_mut() {
+                *v = value;
+            }
+
+            let (a, b) = (rng.range(1, n), rng.range(1, n));
+            let (l, r) = (a.min(b), a.max(b));
+            let positive: Vec<i64> = values[l - 1..r]
+                .iter()
+                .copied()
+                .filter(|&v| v > 0)
+                .collect();
+            assert_eq!(
+                (positive.iter().sum::<i64>(), positive.len()),
+                seg_tree.query_positive(l, r)
+            );
+        }
+    }
+
+    #[test]
+    fn test_assign_over_pending_assign() {
+        let values = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut seg_tree = RangeAssignSumSegmentTree::<i32>::from_vec(&values);
+
+        // leaves a pending mark on the root
+        seg_tree.assign(1, 8, 3);
+        assert_eq!(24, seg_tree.query(1, 8));
+        // replaces the pending marks on the nodes it fully covers
+        seg_tree.assign(1, 8, 5);
+        assert_eq!(40, seg_tree.query(1, 8));
+
+        // must push the whole-range assignment down before splitting
+        seg_tree.assign(3, 6, 0);
+        // new values should be [5, 5, 0, 0, 0, 0, 5, 5]
+        assert_eq!(20, seg_tree.query(1, 8));
+        assert_eq!(5, seg_tree.query(2, 3));
+        assert_eq!(0, seg_tree.query(4, 5));
+        assert_eq!(10, seg_tree.query(6, 8));
+
+        seg_tree.assign(2, 2, 9);
+        assert_eq!(14, seg_tree.query(1, 3));
+    }
+
+    #[test]
+    fn test_add_and_assign() {
+        let values = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut seg_tree = RangeUpdateSumSegmentTree::<i32>::from_vec(&values);
+
+        seg_tree.add(1, 8, 1);
+        // new values should be [2, 3, 4, 5, 6, 7, 8, 9]
+        assert_eq!(44, seg_tree.query(1, 8));
+
+        // discards the pending add on the nodes it covers
+        seg_tree.assign(1, 4, 10);
+        // new values should be [10, 10, 10, 10, 6, 7, 8, 9]
+        assert_eq!(70, seg_tree.query(1, 8));
+
+        // folds into the pending assign of node [1, 4]
+        seg_tree.add(1, 4, -3);
+        // new values should be [7, 7, 7, 7, 6, 7, 8, 9]
+        assert_eq!(58, seg_tree.query(1, 8));
+        assert_eq!(14, seg_tree.query(3, 4));
+        assert_eq!(7, seg_tree.query(2, 2));
+
+        // splits node [1, 4], so its assign must reach the children before its add
+        seg_tree.add(2, 5, 2);
+        // new values should be [7, 9, 9, 9, 8, 7, 8, 9]
+        assert_eq!(66, seg_tree.query(1, 8));
+        assert_eq!(7, seg_tree.query(1, 1));
+        assert_eq!(18, seg_tree.query(3, 4));
+        assert_eq!(15, seg_tree.query(5, 6));
+
+        seg_tree.assign(4, 7, 0);
+        seg_tree.add(1, 8, 1);
+        // new values should be [8, 10, 10, 1, 1, 1, 1, 10]
+        assert_eq!(42, seg_tree.query(1, 8));
+        assert_eq!(24, seg_tree.query(3, 8));
+    }
+
+    #[test]
+    fn test_lower_bound_prefix() {
+        let values = [2, 0, 3, 1, 0, 4];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        // prefix sums are [2, 2, 5, 6, 6, 10]
+        assert_eq!(Some(1), seg_tree.lower_bound_prefix(1));
+        assert_eq!(Some(1), seg_tree.lower_bound_prefix(2));
+        assert_eq!(Some(3), seg_tree.lower_bound_prefix(3));
+        assert_eq!(Some(3), seg_tree.lower_bound_prefix(5));
+        assert_eq!(Some(4), seg_tree.lower_bound_prefix(6));
+        assert_eq!(Some(6), seg_tree.lower_bound_prefix(7));
+        assert_eq!(Some(6), seg_tree.lower_bound_prefix(10));
+        assert_eq!(None, seg_tree.lower_bound_prefix(11));
+
+        seg_tree.update(1, 3, 1);
+        // prefix sums are [3, 4, 8, 9, 9, 13]
+        assert_eq!(Some(2), seg_tree.lower_bound_prefix(4));
+        assert_eq!(Some(3), seg_tree.lower_bound_prefix(5));
+        assert_eq!(Some(4), seg_tree.lower_bound_prefix(9));
+        assert_eq!(Some(6), seg_tree.lower_bound_prefix(13));
+        assert_eq!(None, seg_tree.lower_bound_prefix(14));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let values = [2, 5, 0, 2, 3, 5];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        seg_tree.update(1, 6, 1);
+        let checkpoint = seg_tree.checkpoint();
+        assert_eq!(6, checkpoint.len());
+        assert_eq!(0, seg_tree.diff_since(&checkpoint, 1, 6));
+
+        seg_tree.update(2, 4, 3);
+        seg_tree.add_at(6, -10);
+        assert_eq!(9 - 10, seg_tree.diff_since(&checkpoint, 1, 6));
+        assert_eq!(9, seg_tree.diff_since(&checkpoint, 1, 4));
+        assert_eq!(3, seg_tree.diff_since(&checkpoint, 4, 5));
+        assert_eq!(0, seg_tree.diff_since(&checkpoint, 5, 5));
+        assert_eq!(-10, seg_tree.diff_since(&checkpoint, 6, 6));
+
+        // a checkpoint never changes, a new one starts from scratch
+        let later = seg_tree.checkpoint();
+        seg_tree.set_at(1, 0);
+        assert_eq!(-3, seg_tree.diff_since(&later, 1, 2));
+        assert_eq!(0, seg_tree.diff_since(&checkpoint, 1, 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "checkpoint is of a tree of another length")]
+    fn test_checkpoint_of_other_tree() {
+        let checkpoint = RangeSumSegmentTree::<i32>::from_vec(&[1, 2]).checkpoint();
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3]);
+        seg_tree.diff_since(&checkpoint, 1, 2);
+    }
+
+    #[test]
+    fn test_visit() {
+        let values = [2, 5, 0, 2, 3, 5, 1, 4];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        let mut nodes = Vec::new();
+        seg_tree.visit(2, 7, |l, r, sum| nodes.push((l, r, sum)));
+        assert_eq!(vec![(2, 2, 5), (3, 4, 2), (5, 6, 8), (7, 7, 1)], nodes);
+
+        nodes.clear();
+        seg_tree.visit(1, 8, |l, r, sum| nodes.push((l, r, sum)));
+        assert_eq!(vec![(1, 8, 22)], nodes);
+
+        // the sums of the cover are those of after the update
+        seg_tree.update(1, 8, 1);
+        seg_tree.update(3, 3, 10);
+        for i in 1..=8 {
+            for j in i..=8 {
+                let (mut sum, mut next) = (0, i);
+                seg_tree.visit(i, j, |l, r, node_sum| {
+                    // the nodes tile [i, j] in order
+                    assert_eq!(next, l);
+                    next = r + 1;
+                    sum += node_sum;
+                });
+                assert_eq!(j + 1, next);
+                assert_eq!(seg_tree.query(i, j), sum);
+            }
+        }
+    }
+
+    #[test]
+    fn test_weighted_median() {
+        let weights = [1, 0, 2, 10, 1, 1, 0, 3];
+        let mut seg_tree = RangeSumSegmentTree::<u32>::from_vec(&weights);
+        // the heavy weight at 4 outweighs everything else
+        assert_eq!(Some(4), seg_tree.weighted_median(1, 8));
+        assert_eq!(Some(4), seg_tree.weighted_median(3, 6));
+        // [1, 0, 2] has total 3, and 1 + 0 + 2 is the first to reach 1.5
+        assert_eq!(Some(3), seg_tree.weighted_median(1, 3));
+        // [1, 1, 0, 3] has total 5, reaching 2.5 takes the last weight
+        assert_eq!(Some(8), seg_tree.weighted_median(5, 8));
+        // exactly half counts
+        assert_eq!(Some(5), seg_tree.weighted_median(5, 6));
+        assert_eq!(Some(3), seg_tree.weighted_median(2, 3));
+        // nothing to weigh
+        assert_eq!(None, seg_tree.weighted_median(2, 2));
+        assert_eq!(None, seg_tree.weighted_median(7, 7));
+
+        seg_tree.set_at(4, 0);
+        // weights are [1, 0, 2, 0, 1, 1, 0, 3], total 8
+        assert_eq!(Some(5), seg_tree.weighted_median(1, 8));
+        assert_eq!(None, seg_tree.weighted_median(4, 4));
+    }
+
+    #[test]
+    fn test_weighted_median_large_weights() {
+        // the weights fit in i32, but twice their sums would not
+        let half = i32::MAX / 2;
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[half - 1, 3, half - 10]);
+        assert_eq!(Some(1), seg_tree.weighted_median(1, 3));
+        assert_eq!(Some(1), seg_tree.weighted_median(1, 2));
+        assert_eq!(Some(3), seg_tree.weighted_median(2, 3));
+        assert_eq!(Some(3), seg_tree.weighted_median(3, 3));
+    }
+
+    #[test]
+    fn test_weighted_median_uniform() {
+        let mut seg_tree = RangeSumSegmentTree::<i64>::from_vec(&[3; 11]);
+        for i in 1..=11usize {
+            for j in i..=11 {
+                // half of the elements, rounded up
+                let m = i + (j - i + 1).div_ceil(2) - 1;
+                assert_eq!(Some(m), seg_tree.weighted_median(i, j));
+            }
+        }
+        seg_tree.clear_to(0);
+        assert_eq!(None, seg_tree.weighted_median(1, 11));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_from_reader() {
+        let text = "3 -1  4\n1\t-5 9\n\n2 6 5   3 5\n";
+        let values: Vec<i64> = text
+            .split_whitespace()
+            .map(|token| token.parse().unwrap())
+            .collect();
+        let mut seg_tree =
+            RangeSumSegmentTree::<i64>::from_reader(std::io::Cursor::new(text), values.len())
+                .unwrap();
+        let mut expected = RangeSumSegmentTree::from_vec(&values);
+        assert_eq!(expected.arr, seg_tree.arr);
+        assert_eq!(expected.to_vec(), seg_tree.to_vec());
+        assert_eq!(32, seg_tree.query(1, 11));
+
+        // the numbers after the first len are left in the reader
+        let mut cursor = std::io::Cursor::new(text);
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_reader(&mut cursor, 4).unwrap();
+        assert_eq!(vec![3, -1, 4, 1], seg_tree.to_vec());
+        let mut rest = String::new();
+        std::io::Read::read_to_string(&mut cursor, &mut rest).unwrap();
+        assert_eq!(
+            vec!["-5", "9", "2", "6", "5", "3", "5"],
+            rest.split_whitespace().collect::<Vec<_>>()
+        );
+
+        let empty = RangeSumSegmentTree::<i32>::from_reader(std::io::Cursor::new(""), 0).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_from_reader_errors() {
+        use std::io::{Cursor, ErrorKind};
+        let short = RangeSumSegmentTree::<i32>::from_reader(Cursor::new("1 2 3"), 4);
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            short.map(|_| ()).unwrap_err().kind()
+        );
+        let invalid = RangeSumSegmentTree::<i32>::from_reader(Cursor::new("1 2 x3 4"), 4);
+        assert_eq!(
+            ErrorKind::InvalidData,
+            invalid.map(|_| ()).unwrap_err().kind()
+        );
+        // a value that doesn't fit in T doesn't parse either
+        let overflow = RangeSumSegmentTree::<u8>::from_reader(Cursor::new("1 256"), 2);
+        assert_eq!(
+            ErrorKind::InvalidData,
+            overflow.map(|_| ()).unwrap_err().kind()
+        );
+        // and neither does a sum of them
+        let sum_overflow = RangeSumSegmentTree::<u8>::from_reader(Cursor::new("200 100"), 2);
+        assert_eq!(
+            ErrorKind::InvalidData,
+            sum_overflow.map(|_| ()).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_debug_nodes() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3, 4, 5, 6]);
+        let nodes: Vec<_> = seg_tree.debug_nodes().collect();
+        // a tree of n elements has 2n - 1 nodes
+        assert_eq!(11, nodes.len());
+        assert_eq!((1, 1, 6, 21, 0), nodes[0]);
+        assert_eq!((2, 1, 3, 6, 0), nodes[1]);
+
+        // node p at position of p, to look up children
+        let check = |nodes: &[(usize, usize, usize, i32, i32)]| {
+            let mut by_index = vec![None; 16];
+            for &(p, cl, cr, value, mark) in nodes {
+                by_index[p] = Some((cl, cr, value, mark));
+            }
+            for &(p, cl, cr, value, mark) in nodes {
+                if cl == cr {
+                    assert_eq!(0, mark);
+                    continue;
+                }
+                let (left_cl, _, left, _) = by_index[p * 2].unwrap();
+                let (_, right_cr, right, _) = by_index[p * 2 + 1].unwrap();
+                assert_eq!((cl, cr), (left_cl, right_cr));
+                // the mark has been applied to p but not to its children
+                assert_eq!(value, left + right + mark * (cr - cl + 1) as i32);
+            }
+        };
+        check(&nodes);
+        for (_, _, _, _, mark) in nodes {
+            assert_eq!(0, mark);
+        }
+
+        seg_tree.update(1, 3, 2);
+        seg_tree.update(2, 6, -1);
+        let nodes: Vec<_> = seg_tree.debug_nodes().collect();
+        assert!(nodes.iter().any(|node| node.4 != 0));
+        check(&nodes);
+        assert_eq!(
+            0,
+            RangeSumSegmentTree::<i32>::from_vec(&[])
+                .debug_nodes()
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let values = [5, -3, 2, 8, 0, 7, -1, 4, 6];
+        let mut seg_tree = RangeSumSegmentTree::<i64>::from_vec(&values);
+        // leave some marks pending before the transaction
+        seg_tree.update(2, 7, 3);
+        seg_tree.update(1, 4, -2);
+        let before = seg_tree.clone();
+        assert!(before.mark.iter().any(|&mark| mark != 0));
+        // get doesn't push marks down
+        let array: Vec<i64> = (1..=9).map(|i| seg_tree.get(i)).collect();
+
+        seg_tree.begin_transaction().unwrap();
+        assert!(seg_tree.in_transaction());
+        seg_tree.update(3, 9, 10);
+        seg_tree.add_at(5, -4);
+        seg_tree.set_at(2, 100);
+        assert_eq!(19, seg_tree.query(4, 4));
+        seg_tree.update(1, 9, 1);
+        assert_ne!(array.iter().sum::<i64>(), seg_tree.query(1, 9));
+        assert_eq!(
+            Err(TransactionError::AlreadyActive),
+            seg_tree.begin_transaction()
+        );
+        seg_tree.rollback().unwrap();
+
+        // every node is back, marks included
+        assert!(!seg_tree.in_transaction());
+        assert_eq!(before.arr, seg_tree.arr);
+        assert_eq!(before.mark, seg_tree.mark);
+        for i in 1..=9usize {
+            for j in i..=9 {
+                assert_eq!(array[i - 1..j].iter().sum::<i64>(), seg_tree.query(i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_transaction_commit() {
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&[1, 2, 3, 4]);
+        assert_eq!(Err(TransactionError::NotActive), seg_tree.commit());
+        assert_eq!(Err(TransactionError::NotActive), seg_tree.rollback());
+
+        seg_tree.begin_transaction().unwrap();
+        seg_tree.update(1, 3, 2);
+        seg_tree.commit().unwrap();
+        assert_eq!(16, seg_tree.query(1, 4));
+        // nothing is recorded outside of a transaction
+        assert!(seg_tree.undo.is_none());
+
+        seg_tree.begin_transaction().unwrap();
+        seg_tree.clear_to(5);
+        assert_eq!(20, seg_tree.query(1, 4));
+        seg_tree.rollback().unwrap();
+        assert_eq!(vec![3, 4, 5, 4], seg_tree.to_vec());
+    }
+
+    #[test]
+    fn test_find_subrange_with_sum() {
+        let values = [2, 0, 3, 1, 0, 4];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        // sums from index 1 are [2, 2, 5, 6, 6, 10]
+        assert_eq!(Some((1, 1)), seg_tree.find_subrange_with_sum(1, 2));
+        assert_eq!(Some((1, 3)), seg_tree.find_subrange_with_sum(1, 5));
+        assert_eq!(Some((1, 6)), seg_tree.find_subrange_with_sum(1, 10));
+        // sums from index 3 are [3, 4, 4, 8]
+        assert_eq!(Some((3, 4)), seg_tree.find_subrange_with_sum(3, 4));
+        assert_eq!(Some((6, 6)), seg_tree.find_subrange_with_sum(6, 4));
+
+        // the sums jump over the target
+        assert_eq!(None, seg_tree.find_subrange_with_sum(1, 3));
+        assert_eq!(None, seg_tree.find_subrange_with_sum(3, 5));
+        assert_eq!(None, seg_tree.find_subrange_with_sum(3, 7));
+        // or never reach it
+        assert_eq!(None, seg_tree.find_subrange_with_sum(3, 9));
+        assert_eq!(None, seg_tree.find_subrange_with_sum(1, 11));
+
+        // zero is the empty range
+        assert_eq!(Some((1, 0)), seg_tree.find_subrange_with_sum(1, 0));
+        assert_eq!(Some((4, 3)), seg_tree.find_subrange_with_sum(4, 0));
+
+        // no range of nonnegative elements sums to a negative target
+        assert_eq!(None, seg_tree.find_subrange_with_sum(1, -1));
+        assert_eq!(None, seg_tree.find_subrange_with_sum(4, -1));
+        assert_eq!(None, seg_tree.find_subrange_with_sum(6, -5));
+
+        seg_tree.update(2, 2, 1);
+        // sums from index 1 are [2, 3, 6, 7, 7, 11]
+        assert_eq!(Some((1, 2)), seg_tree.find_subrange_with_sum(1, 3));
+        assert_eq!(None, seg_tree.find_subrange_with_sum(1, 5));
+    }
+
+    #[test]
+    fn test_find_subrange_against_brute_force() {
+        let values: Vec<u32> = (0..29).map(|i| i * i * 5 % 7).collect();
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        for start in 1..=values.len() {
+            for target in 1..40 {
+                let mut sum = 0;
+                let expected = (start..=values.len()).find_map(|end| {
+                    sum += values[end - 1];
+                    if sum == target {
+                        Some((start, end))
+                    } else {
+                        None
+                    }
+                });
+                assert_eq!(expected, seg_tree.find_subrange_with_sum(start, target));
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_reach() {
+        let values = [2, 0, 3, 1, 0, 4, 5];
+        let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+        // sums from index 2 are [0, 3, 4, 4, 8, 13]
+        assert_eq!(2, seg_tree.max_reach(2, 0));
+        assert_eq!(2, seg_tree.max_reach(2, 2));
+        assert_eq!(3, seg_tree.max_reach(2, 3));
+        assert_eq!(5, seg_tree.max_reach(2, 4));
+        assert_eq!(6, seg_tree.max_reach(2, 12));
+        assert_eq!(7, seg_tree.max_reach(2, 13));
+        assert_eq!(7, seg_tree.max_reach(2, 100));
+
+        // array[start] alone exceeds the limit
+        assert_eq!(0, seg_tree.max_reach(1, 1));
+        assert_eq!(5, seg_tree.max_reach(6, 3));
+        assert_eq!(7, seg_tree.max_reach(7, 5));
+        assert_eq!(6, seg_tree.max_reach(7, 4));
+
+        seg_tree.update(3, 5, 1);
+        // values are [2, 0, 4, 2, 1, 4, 5], sums from index 1 are [2, 2, 6, 8, 9, 13, 18]
+        assert_eq!(2, seg_tree.max_reach(1, 5));
+        assert_eq!(5, seg_tree.max_reach(1, 9));
+        assert_eq!(6, seg_tree.max_reach(1, 17));
+        assert_eq!(7, seg_tree.max_reach(1, 18));
+        assert_eq!(7, seg_tree.query(4, 6));
+        assert_eq!(6, seg_tree.max_reach(4, 7));
+    }
+
+    #[test]
+    fn test_max_reach_against_brute_force() {
+        let values: Vec<u32> = (0..37).map(|i| i * i * 7 % 10).collect();
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        for start in 1..=values.len() {
+            for limit in 0..60 {
+                let mut end = start - 1;
+                let mut sum = 0;
+                while end < values.len() && sum + values[end] <= limit {
+                    sum += values[end];
+                    end += 1;
+                }
+                assert_eq!(end, seg_tree.max_reach(start, limit));
+            }
+        }
+    }
+
+    // linear congruential generator for the randomized tests, which
+    // can't use math::PCG32 since the math module needs std
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0 >> 33
+        }
+
+        // uniform in [lo, hi]
+        fn range(&mut self, lo: usize, hi: usize) -> usize {
+            lo + self.next() as usize % (hi - lo + 1)
+        }
+    }
+
+    // Property test against a plain Vec<i64>: every case draws a random
+    // length and a random sequence of updates and queries, with a quarter
+    // of the ranges a single element and diffs of both signs. Failing
+    // cases are shrunk to a minimal sequence of operations.
+    //
+    // Run it alone with
+    //     cargo test segment_tree_sum::tests::prop_matches_naive_array
+    // and set PROPTEST_CASES to run more cases than the default.
+    proptest! {
+        #[test]
+        fn prop_matches_naive_array(
+            (mut naive, ops) in (1usize..70).prop_flat_map(|len| (
+                proptest::collection::vec(-1000i64..1000, len),
+                // (a, b, single element, Some(diff) to update or None to query)
+                proptest::collection::vec(
+                    (
+                        0..len,
+                        0..len,
+                        proptest::bool::weighted(0.25),
+                        proptest::option::of(-100i64..=100),
+                    ),
+                    1..200,
+                ),
+            ))
+        ) {
+            let mut seg_tree = RangeSumSegmentTree::from_vec(&naive);
+            for (a, b, single, diff) in ops {
+                let i = a.min(b) + 1;
+                let j = if single { i } else { a.max(b) + 1 };
+                match diff {
+                    Some(diff) => {
+                        seg_tree.update(i, j, diff);
+                        for value in &mut naive[i - 1..j] {
+                            *value += diff;
+                        }
+                    }
+                    None => {
+                        prop_assert_eq!(naive[i - 1..j].iter().sum::<i64>(), seg_tree.query(i, j));
+                    }
+                }
+            }
+            prop_assert_eq!(naive, seg_tree.to_vec());
+        }
+    }
+
+    // largest node index used by a tree covering [left, right]
+    fn max_index(left: usize, right: usize, p: usize) -> usize {
+        if left == right {
+            return p;
+        }
+        let mid = left + (right - left) / 2;
+        max_index(left, mid, p * 2).max(max_index(mid + 1, right, p * 2 + 1))
+    }
+
+    #[test]
+    fn test_calculate_length() {
+        assert_eq!(2, calculate_length(0));
+        assert_eq!(2, calculate_length(1));
+        assert_eq!(4, calculate_length(2));
+        assert_eq!(8, calculate_length(3));
+        assert_eq!(8, calculate_length(4));
+        assert_eq!(16, calculate_length(5));
+        for length in 1..10000 {
+            let max = max_index(1, length, 1);
+            assert!(max < calculate_length(length));
+            // never more than twice the space actually used
+            assert!(calculate_length(length) <= 2 * (max + 1));
+        }
+    }
+
+    #[test]
+    fn test_i64_no_overflow() {
+        let values = vec![2_000_000_000i64; 1000];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        assert_eq!(2_000_000_000_000, seg_tree.query(1, 1000));
+
+        seg_tree.update(1, 500, 1_000_000_000);
+        assert_eq!(2_500_000_000_000, seg_tree.query(1, 1000));
+        assert_eq!(3_000_000_000, seg_tree.query(250, 250));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "segment tree sum overflow")]
+    fn test_update_overflow() {
+        let values = vec![0i32; 10_000];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        // 1_000_000 * 10_000 doesn't fit in i32
+        seg_tree.update(1, 10_000, 1_000_000);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "segment tree sum overflow")]
+    fn test_push_down_overflow() {
+        let values = [2_000_000_000i32, -2_000_000_000];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        // the root sum stays small, the overflow only
+        // shows up once the mark reaches the left leaf
+        seg_tree.update(1, 2, 200_000_000);
+        assert_eq!(400_000_000, seg_tree.query(1, 2));
+        seg_tree.query(1, 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "segment tree sum overflow")]
+    fn test_add_at_overflow() {
+        let values = [2_000_000_000i32, 0];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        seg_tree.add_at(1, 200_000_000);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "segment tree sum overflow")]
+    fn test_build_overflow() {
+        let words = ["2000000000", "200000000"];
+        RangeSumSegmentTree::from_vec_mapped(&words, |word| word.parse::<i32>().unwrap());
+    }
+
+    #[test]
+    fn test_checked_scale() {
+        assert_eq!(Some(100), 50i32.checked_scale(2));
+        assert_eq!(None, i32::MAX.checked_scale(2));
+        // the length itself doesn't fit in i8
+        assert_eq!(None, 1i8.checked_scale(300));
+        assert_eq!(Some(0), 0i8.checked_scale(300));
+        assert_eq!(Some(3), 1u8.checked_add(2));
+        assert_eq!(None, 255u8.checked_add(1));
+    }
+
+    #[test]
+    fn test_zero_marks_on_long_small_int_tree() {
+        // lengths past i8::MAX and i16::MAX are fine while sums fit
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[0i8; 300]);
+        assert_eq!(0, seg_tree.query(1, 1));
+        seg_tree.update(150, 150, 7);
+        assert_eq!(7, seg_tree.query(1, 300));
+        assert_eq!(7, seg_tree.query(150, 150));
+
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&vec![0i16; 70_000]);
+        assert_eq!(0, seg_tree.query(1, 1));
+        assert_eq!(0, seg_tree.query(35_000, 70_000));
+    }
+
+    #[test]
+    fn test_unsigned() {
+        let values: Vec<u64> = vec![1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        seg_tree.update(1, 6, u32::MAX as u64);
+        assert_eq!(21 + 6 * u32::MAX as u64, seg_tree.query(1, 6));
+        assert_eq!(3 + 2 * u32::MAX as u64, seg_tree.query(1, 2));
+    }
+
+    #[test]
+    fn test_mod_sum() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = ModSumSegmentTree::from_vec_mod(&values, 7);
+        // 21 mod 7
+        assert_eq!(0, seg_tree.query(1, 6));
+        assert_eq!(5, seg_tree.query(2, 3));
+        assert_eq!(5, seg_tree.query(3, 5));
+
+        seg_tree.update(2, 5, 10);
+        // new values should be [1, 12, 13, 14, 15, 6], or [1, 5, 6, 0, 1, 6] mod 7
+        assert_eq!(5, seg_tree.query(1, 6));
+        assert_eq!(4, seg_tree.query(2, 3));
+        assert_eq!(0, seg_tree.query(4, 4));
+        assert_eq!(6, seg_tree.query(3, 6));
+    }
+
+    #[test]
+    fn test_mod_sum_mul_range() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = ModSumSegmentTree::from_vec_mod(&values, 7);
+        seg_tree.mul_range(2, 4, 3);
+        // new values should be [1, 6, 9, 12, 5, 6], or [1, 6, 2, 5, 5, 6] mod 7
+        assert_eq!(4, seg_tree.query(1, 6));
+        assert_eq!(1, seg_tree.query(2, 3));
+        assert_eq!(5, seg_tree.query(4, 4));
+
+        // the multiply distributes over the pending add
+        seg_tree.update(1, 6, 2);
+        seg_tree.mul_range(1, 6, 4);
+        // new values should be [3, 8, 4, 7, 7, 8] * 4, or [5, 4, 2, 0, 0, 4] mod 7
+        assert_eq!(1, seg_tree.query(1, 6));
+        assert_eq!(6, seg_tree.query(2, 3));
+        assert_eq!(0, seg_tree.query(4, 5));
+
+        // multiplying by zero, or by a multiple of the modulus, clears
+        seg_tree.mul_range(2, 5, 14);
+        assert_eq!(2, seg_tree.query(1, 6));
+        assert_eq!(0, seg_tree.query(2, 5));
+    }
+
+    #[test]
+    fn test_mod_sum_mul_against_brute_force() {
+        let mut rng = Lcg(1729);
+        let m = 13;
+        let n = 30;
+        let mut values: Vec<u64> = (0..n as u64).map(|i| i * i % m).collect();
+        let mut seg_tree = ModSumSegmentTree::from_vec_mod(&values, m);
+        for _ in 0..1000 {
+            let (a, b) = (rng.range(1, n), rng.range(1, n));
+            let (l, r) = (a.min(b), a.max(b));
+            match rng.next() % 3 {
+                0 => {
+                    let k = rng.next() % 20;
+                    seg_tree.mul_range(l, r, k);
+                    for v in values[l - 1..r].iter_mut() {
+                        *v = *v * k % m;
+                    }
+                }
+                1 => {
+                    let diff = rng.next() % 20;
+                    seg_tree.update(l, r, diff);
+                    for v in values[l - 1..r].iter_mut() {
+                        *v = (*v + diff) % m;
+                    }
+                }
+                _ => {
+                    let sum = values[l - 1..r].iter().sum::<u64>() % m;
+                    assert_eq!(sum, seg_tree.query(l, r));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mod_sum_large_modulus() {
+        // (m - 1) * 6 doesn't fit in u64, the reduction must happen in u128
+        let m = u64::MAX - 58;
+        let values = [m - 1; 6];
+        let mut seg_tree = ModSumSegmentTree::from_vec_mod(&values, m);
+        assert_eq!(m - 6, seg_tree.query(1, 6));
+        seg_tree.update(1, 6, m - 1);
+        assert_eq!(m - 12, seg_tree.query(1, 6));
+        assert_eq!(m - 4, seg_tree.query(2, 3));
+    }
+
+    #[test]
+    fn test_counted_range_average() {
+        let mut seg_tree = CountedSumSegmentTree::from_vec(&[2, 4, 6, 8, 10, 12]);
+        assert_eq!(7.0, seg_tree.range_average(1, 6));
+        seg_tree.delete_at(6);
+        seg_tree.delete_at(2);
+        // present values are [2, 6, 8, 10], averaged over 4 elements
+        assert_eq!(6.5, seg_tree.range_average(1, 6));
+        assert_eq!(16.0 / 3.0, seg_tree.range_average(1, 4));
+        assert_eq!(10.0, seg_tree.range_average(5, 6));
+        seg_tree.delete_at(5);
+        assert!(seg_tree.range_average(5, 6).is_nan());
+
+        let mut seg_tree = CountedSumSegmentTree::<i64>::from_vec(&[1, 2, 6]);
+        seg_tree.delete_at(2);
+        assert_eq!(3.5, seg_tree.range_average(1, 3));
+    }
+
+    #[test]
+    fn test_delete_at() {
+        let values = [2, 4, 1, 3, 5, 7];
+        let mut seg_tree = CountedSumSegmentTree::<i32>::from_vec(&values);
+        assert_eq!(6, seg_tree.len());
+        assert_eq!(22, seg_tree.query(1, 6));
+        assert_eq!(6, seg_tree.query_count(1, 6));
+        assert_eq!(3, seg_tree.query_count(2, 4));
+
+        assert!(seg_tree.delete_at(3));
+        assert!(seg_tree.delete_at(5));
+        // present values should be [2, 4, _, 3, _, 7]
+        assert_eq!(16, seg_tree.query(1, 6));
+        assert_eq!(4, seg_tree.query_count(1, 6));
+        assert_eq!(7, seg_tree.query(2, 4));
+        assert_eq!(2, seg_tree.query_count(2, 4));
+        assert_eq!(0, seg_tree.query(5, 5));
+        assert_eq!(0, seg_tree.query_count(5, 5));
+
+        // deleting twice changes nothing
+        assert!(!seg_tree.delete_at(3));
+        assert_eq!(4, seg_tree.query_count(1, 6));
+
+        for i in 1..=6 {
+            seg_tree.delete_at(i);
+        }
+        assert_eq!(0, seg_tree.query(1, 6));
+        assert_eq!(0, seg_tree.query_count(1, 6));
+    }
+
+    #[test]
+    fn test_restore_and_kth_present() {
+        let mut seg_tree = CountedSumSegmentTree::<i32>::from_vec(&[1; 10]);
+        assert_eq!(Some(1), seg_tree.kth_present(1));
+        assert_eq!(Some(10), seg_tree.kth_present(10));
+        assert_eq!(None, seg_tree.kth_present(11));
+        assert_eq!(None, seg_tree.kth_present(0));
+
+        for i in 4..=7 {
+            seg_tree.delete_at(i);
+        }
+        // present positions are [1, 2, 3, 8, 9, 10]
+        assert_eq!(Some(3), seg_tree.kth_present(3));
+        assert_eq!(Some(8), seg_tree.kth_present(4));
+        assert_eq!(Some(10), seg_tree.kth_present(6));
+        assert_eq!(None, seg_tree.kth_present(7));
+
+        assert!(seg_tree.restore_at(5, 20));
+        // present positions are [1, 2, 3, 5, 8, 9, 10]
+        assert_eq!(Some(5), seg_tree.kth_present(4));
+        assert_eq!(Some(8), seg_tree.kth_present(5));
+        assert_eq!(26, seg_tree.query(1, 10));
+        assert_eq!(20, seg_tree.query(4, 7));
+        assert_eq!(1, seg_tree.query_count(4, 7));
+
+        // restoring a present element changes nothing
+        assert!(!seg_tree.restore_at(5, 30));
+        assert!(!seg_tree.restore_at(1, 30));
+        assert_eq!(26, seg_tree.query(1, 10));
+
+        // a restored element can be deleted again
+        assert!(seg_tree.delete_at(5));
+        assert_eq!(Some(8), seg_tree.kth_present(4));
+        assert!(seg_tree.restore_at(7, 0));
+        assert_eq!(Some(7), seg_tree.kth_present(4));
+        assert_eq!(7, seg_tree.query_count(1, 10));
+    }
+
+    #[test]
+    fn test_no_mark_on_leaves() {
+        // leaves have no children, so a mark left on them would never
+        // be read, only accumulate until it overflows on its own
+        let mut seg_tree = RangeSumSegmentTree::<i8>::from_vec(&[0, 0, 0]);
+        for _ in 0..3 {
+            // [2, 3] covers leaf 2 and leaf 3 directly
+            seg_tree.update(2, 3, 60);
+            seg_tree.update(2, 2, -60);
+            seg_tree.update(3, 3, -60);
+            // [1, 2] is marked, then pushed down to its leaves
+            seg_tree.update(1, 2, 60);
+            assert_eq!(60, seg_tree.query(1, 1));
+            seg_tree.update(1, 1, -60);
+            seg_tree.update(2, 2, -60);
+        }
+        assert_eq!(vec![0, 0, 0], seg_tree.to_vec());
+        for (_, cl, cr, _, mark) in seg_tree.debug_nodes() {
+            if cl == cr {
+                assert_eq!(0, mark);
+            }
+        }
+    }
+
+    #[test]
+    fn test_leaves() {
+        let values = [1, 2, 3, 4, 5, 6, 7];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        let leaves: Vec<i32> = seg_tree.leaves().unwrap().collect();
+        assert_eq!(values.to_vec(), leaves);
+
+        seg_tree.update(2, 6, 10);
+        assert_eq!(Some(LazyPendingError), seg_tree.leaves().err());
+        // a query only pushes down the marks on its own path
+        assert_eq!(12, seg_tree.query(2, 2));
+        assert!(seg_tree.leaves().is_err());
+
+        let expected = seg_tree.to_vec();
+        let leaves: Vec<i32> = seg_tree.leaves().unwrap().collect();
+        assert_eq!(expected, leaves);
+        assert_eq!(vec![1, 12, 13, 14, 15, 16, 7], leaves);
+        // single element updates never leave a mark
+        seg_tree.update(7, 7, 1);
+        assert_eq!(8, seg_tree.leaves().unwrap().last().unwrap());
+
+        let empty = RangeSumSegmentTree::<i32>::from_vec(&[]);
+        assert_eq!(0, empty.leaves().unwrap().count());
+    }
+
+    #[test]
+    fn test_flush() {
+        let values: Vec<i64> = (1..=13).collect();
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        let mut expected = values.clone();
+        for &(i, j, diff) in &[(1, 13, 5), (3, 9, -2), (5, 12, 7), (2, 3, 1), (8, 8, 4)] {
+            seg_tree.update(i, j, diff);
+            for value in &mut expected[i - 1..j] {
+                *value += diff;
+            }
+        }
+        assert!(seg_tree.mark.iter().any(|&mark| mark != 0));
+        seg_tree.flush();
+        assert!(seg_tree.mark.iter().all(|&mark| mark == 0));
+        assert_eq!(expected, seg_tree.leaves().unwrap().collect::<Vec<_>>());
+        for i in 1..=13 {
+            for j in i..=13 {
+                let sum: i64 = expected[i - 1..j].iter().sum();
+                assert_eq!(sum, seg_tree.query(i, j));
+            }
+        }
+        // flushing again changes nothing
+        seg_tree.flush();
+        assert_eq!(expected, seg_tree.to_vec());
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[1i64, 2, 3, 4, 5, 6, 7, 8]);
+        seg_tree.update(2, 7, 10);
+        seg_tree.update(1, 4, -1);
+        // new values should be [0, 11, 12, 13, 15, 16, 17, 8]
+        let expected = [0i64, 11, 12, 13, 15, 16, 17, 8];
+        let frozen = seg_tree.freeze();
+        assert!(frozen.tree.mark.iter().all(|&mark| mark == 0));
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let frozen = &frozen;
+                scope.spawn(move || {
+                    for i in 1..=8 {
+                        for j in i..=8 {
+                            let sum: i64 = expected[i - 1..j].iter().sum();
+                            assert_eq!(sum, frozen.query(i, j));
+                        }
+                    }
+                });
+            }
+        });
+        let mut seg_tree = frozen.thaw();
+        seg_tree.update(8, 8, 2);
+        assert_eq!(94, seg_tree.query(1, 8));
+    }
+
+    #[test]
+    fn test_from_sparse() {
+        let entries = vec![(2, 5), (7, -3), (2, 4), (10, 1)];
+        let mut seg_tree = RangeSumSegmentTree::from_sparse(10, entries);
+        // duplicate index 2 sums to 9
+        let dense = RangeSumSegmentTree::from_vec(&[0, 9, 0, 0, 0, 0, -3, 0, 0, 1]);
+        assert!(seg_tree == dense);
+        assert_eq!(7, seg_tree.query(1, 10));
+        assert_eq!(9, seg_tree.query(1, 6));
+        assert!(RangeSumSegmentTree::<i32>::from_sparse(0, None).is_empty());
+    }
+
+    #[test]
+    fn test_identity_and_combine() {
+        let values = [1, 2, 3, 4, 5, 6];
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&values);
+        assert_eq!(0, seg_tree.identity());
+        assert_eq!(7, RangeSumSegmentTree::combine(3, 4));
+        // every range sum is the combine of its two halves
+        for i in 1..=6 {
+            for j in i..=6 {
+                let expected: i32 = values[i - 1..j].iter().sum();
+                assert_eq!(expected, seg_tree.query(i, j));
+                for k in i..j {
+                    let halves = RangeSumSegmentTree::combine(
+                        seg_tree.query(i, k),
+                        seg_tree.query(k + 1, j),
+                    );
+                    assert_eq!(expected, halves);
+                }
+            }
+        }
+        assert_eq!(seg_tree.identity(), seg_tree.query_range(3..3));
+    }
+
+    #[test]
+    fn test_leaf_index() {
+        assert_eq!(LeafIndex::from_one_based(3), LeafIndex::from_zero_based(2));
+        assert_eq!(LeafIndex::from_one_based(3), LeafIndex::from(3));
+        assert_eq!(1, LeafIndex::from_zero_based(0).one_based());
+
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[1, 2, 3, 4, 5, 6]);
+        let first = LeafIndex::from_zero_based(0);
+        let last = LeafIndex::from_zero_based(5);
+        assert_eq!(seg_tree.query(1, 6), seg_tree.query(first, last));
+        assert_eq!(21, seg_tree.query(first, 6));
+        seg_tree.update(
+            LeafIndex::from_zero_based(2),
+            LeafIndex::from_one_based(4),
+            10,
+        );
+        // new values should be [1, 2, 13, 14, 5, 6]
+        assert_eq!(13, seg_tree.get(LeafIndex::from_zero_based(2)));
+        assert_eq!(13, seg_tree.get(3));
+        seg_tree.set_at(LeafIndex::from_zero_based(5), 0);
+        seg_tree.add_at(LeafIndex::from_one_based(1), 1);
+        assert_eq!(vec![2, 2, 13, 14, 5, 0], seg_tree.to_vec());
+        assert_eq!(
+            Err(SegTreeError::IndexOutOfBounds { index: 7, len: 6 }),
+            seg_tree.try_query(first, LeafIndex::from_zero_based(6))
+        );
+
+        // the rest of the positional api takes them too
+        let pairs = [(first, last), (LeafIndex::from_zero_based(2), last)];
+        assert_eq!(vec![36, 32], seg_tree.query_many(&pairs));
+        seg_tree.apply_batch(&[(first, LeafIndex::from_zero_based(1), 1)]);
+        // new values should be [3, 3, 13, 14, 5, 0]
+        assert_eq!(
+            3.0,
+            seg_tree.range_average(first, LeafIndex::from_zero_based(1))
+        );
+        assert_eq!(Some(3), seg_tree.weighted_median(first, last));
+        assert_eq!(
+            Some((2, 3)),
+            seg_tree.find_subrange_with_sum(LeafIndex::from_zero_based(1), 16)
+        );
+        assert_eq!(4, seg_tree.max_reach(LeafIndex::from_zero_based(2), 30));
+        assert_eq!(38, seg_tree.clone().freeze().query(first, last));
+
+        let mut assign_tree = RangeAssignSumSegmentTree::from_vec(&[1, 2, 3, 4]);
+        assign_tree.assign(LeafIndex::from_zero_based(1), 3, 0);
+        assert_eq!(5, assign_tree.query(first, LeafIndex::from_zero_based(3)));
+        let mut mod_tree = ModSumSegmentTree::from_vec_mod(&[1, 2, 3, 4], 5);
+        mod_tree.update(LeafIndex::from_zero_based(0), 4, 1);
+        assert_eq!(4, mod_tree.query(first, LeafIndex::from_zero_based(3)));
+    }
+
+    #[test]
+    fn test_range_average() {
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[1, 2, 3, 4, 5, 6, 7]);
+        // odd and even spans
+        assert_eq!(4.0, seg_tree.range_average(1, 7));
+        assert_eq!(2.5, seg_tree.range_average(1, 4));
+        assert_eq!(5.0, seg_tree.range_average(5, 5));
+        seg_tree.update(2, 3, 1);
+        // new values should be [1, 3, 4, 4, 5, 6, 7]
+        assert_eq!(8.0 / 3.0, seg_tree.range_average(1, 3));
+        assert_eq!(4.0, seg_tree.range_average(2, 5));
+
+        // 64 bit elements have averages too
+        let mut seg_tree = RangeSumSegmentTree::<i64>::from_vec(&[-3, 8, 1, 10]);
+        assert_eq!(4.0, seg_tree.range_average(1, 4));
+        let mut seg_tree = RangeSumSegmentTree::<u64>::from_vec(&[3, 8]);
+        assert_eq!(5.5, seg_tree.range_average(1, 2));
+        let mut seg_tree = RangeSumSegmentTree::<usize>::from_vec(&[3, 8, 1]);
+        assert_eq!(4.0, seg_tree.range_average(1, 3));
+    }
+
+    #[test]
+    fn test_apply_batch() {
+        let values: Vec<i32> = (0..20).map(|i| i * 3 % 7).collect();
+        let ops: Vec<(usize, usize, i32)> = (0..30)
+            .map(|k| {
+                let i = k * 7 % 20 + 1;
+                let j = i + (k * 11 % (21 - i));
+                (i, j, k as i32 % 9 - 4)
+            })
+            .collect();
+        let mut batched = RangeSumSegmentTree::from_vec(&values);
+        let mut one_by_one = RangeSumSegmentTree::from_vec(&values);
+        batched.apply_batch(&ops);
+        for &(i, j, diff) in &ops {
+            one_by_one.update(i, j, diff);
+        }
+        for i in 1..=20 {
+            for j in i..=20 {
+                assert_eq!(one_by_one.query(i, j), batched.query(i, j));
+            }
+        }
+        assert_eq!(one_by_one.to_vec(), batched.to_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "update range out of bounds")]
+    fn test_apply_batch_out_of_bounds() {
+        let mut seg_tree = RangeSumSegmentTree::from_vec(&[1, 2, 3]);
+        seg_tree.apply_batch(&[(1, 2, 1), (2, 4, 1)]);
     }
 }
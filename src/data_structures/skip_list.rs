@@ -0,0 +1,236 @@
+use math::PCG32;
+
+// towers never grow past this many levels, enough
+// for far more than 2^32 elements at p = 1/2
+const MAX_LEVEL: usize = 32;
+const DEFAULT_SEED: u64 = 0x5eed;
+
+/// Skip list: a sorted linked list with express lanes, storing an
+/// ordered set of distinct values.
+///
+/// Every node is in the bottom list, and each node in a list is also in
+/// the list above it with probability 1/2, so searching from the top
+/// lane down skips about half of the remaining nodes per level. Insert,
+/// remove and contains take expected O(log n). Node heights come from a
+/// seeded random number generator, so the shape is reproducible.
+pub struct SkipList<T: Ord> {
+    // nodes[0] is the head, which holds no value and is in every lane.
+    // Removed nodes are left in place and recycled through free.
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    len: usize,
+    rng: PCG32,
+}
+
+struct Node<T> {
+    value: Option<T>,
+    // next[level] is the following node in that lane
+    next: Vec<Option<usize>>,
+}
+
+impl<T: Ord> Default for SkipList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> SkipList<T> {
+    /// create an empty skip list with a fixed default seed
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    /// create an empty skip list, drawing node heights from seed
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            nodes: vec![Node {
+                value: None,
+                next: vec![None; MAX_LEVEL],
+            }],
+            free: Vec::new(),
+            len: 0,
+            rng: PCG32::new_default(seed),
+        }
+    }
+
+    /// return the number of values
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// return true if there are no values
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // for every lane, the last node whose value is less than value
+    fn predecessors(&self, value: &T) -> [usize; MAX_LEVEL] {
+        let mut preds = [0; MAX_LEVEL];
+        let mut node = 0;
+        for level in (0..MAX_LEVEL).rev() {
+            while let Some(next) = self.nodes[node].next[level] {
+                if self.nodes[next].value.as_ref().unwrap() < value {
+                    node = next;
+                } else {
+                    break;
+                }
+            }
+            preds[level] = node;
+        }
+        preds
+    }
+
+    // the node right after preds[0] holding value, if there is one
+    fn find(&self, value: &T, preds: &[usize; MAX_LEVEL]) -> Option<usize> {
+        self.nodes[preds[0]].next[0].filter(|&next| self.nodes[next].value.as_ref() == Some(value))
+    }
+
+    fn random_height(&mut self) -> usize {
+        // each trailing one bit is a coin flip won
+        (self.rng.get_u32().trailing_ones() as usize + 1).min(MAX_LEVEL)
+    }
+
+    /// insert value, returning false if it was already present
+    pub fn insert(&mut self, value: T) -> bool {
+        let preds = self.predecessors(&value);
+        if self.find(&value, &preds).is_some() {
+            return false;
+        }
+        let height = self.random_height();
+        let next = (0..height)
+            .map(|level| self.nodes[preds[level]].next[level])
+            .collect();
+        let node = Node {
+            value: Some(value),
+            next,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        for (level, &pred) in preds.iter().enumerate().take(height) {
+            self.nodes[pred].next[level] = Some(index);
+        }
+        self.len += 1;
+        true
+    }
+
+    /// remove value, returning false if it wasn't present
+    pub fn remove(&mut self, value: &T) -> bool {
+        let preds = self.predecessors(value);
+        let index = match self.find(value, &preds) {
+            Some(index) => index,
+            None => return false,
+        };
+        let next = std::mem::take(&mut self.nodes[index].next);
+        for (level, &after) in next.iter().enumerate() {
+            self.nodes[preds[level]].next[level] = after;
+        }
+        self.nodes[index].value = None;
+        self.free.push(index);
+        self.len -= 1;
+        true
+    }
+
+    /// return true if value is present
+    pub fn contains(&self, value: &T) -> bool {
+        self.find(value, &self.predecessors(value)).is_some()
+    }
+
+    /// iterate over the values in increasing order
+    pub fn iter(&self) -> SkipListIter<'_, T> {
+        SkipListIter {
+            list: self,
+            node: self.nodes[0].next[0],
+        }
+    }
+}
+
+/// Iterator over the values of a `SkipList` in increasing order
+pub struct SkipListIter<'a, T: Ord> {
+    list: &'a SkipList<T>,
+    node: Option<usize>,
+}
+
+impl<'a, T: Ord> Iterator for SkipListIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = &self.list.nodes[self.node?];
+        self.node = node.next[0];
+        node.value.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sorted_iteration() {
+        let mut rng = PCG32::new_default(7);
+        let mut values: Vec<u32> = (0..500).collect();
+        // shuffle
+        for i in (1..values.len()).rev() {
+            let j = rng.get_u32() as usize % (i + 1);
+            values.swap(i, j);
+        }
+        let mut list = SkipList::with_seed(42);
+        for &v in &values {
+            assert!(list.insert(v));
+        }
+        assert!(!list.insert(250));
+        assert_eq!(500, list.len());
+        let sorted: Vec<u32> = list.iter().cloned().collect();
+        assert_eq!((0..500).collect::<Vec<_>>(), sorted);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = SkipList::new();
+        for v in 0..20 {
+            list.insert(v);
+        }
+        assert!(list.remove(&5));
+        assert!(list.remove(&12));
+        assert!(list.remove(&0));
+        assert!(list.remove(&19));
+        assert!(!list.remove(&12));
+        assert!(!list.remove(&100));
+        assert_eq!(16, list.len());
+        assert!(!list.contains(&5));
+        assert!(list.contains(&6));
+        let expected: Vec<i32> = (1..19).filter(|&v| v != 5 && v != 12).collect();
+        assert_eq!(expected, list.iter().cloned().collect::<Vec<_>>());
+
+        // removed slots are reused
+        assert!(list.insert(12));
+        assert!(list.contains(&12));
+        // 20 nodes plus the head
+        assert_eq!(21, list.nodes.len());
+        assert_eq!(17, list.iter().count());
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let build = || {
+            let mut list = SkipList::with_seed(99);
+            for v in 0..100 {
+                list.insert(v);
+            }
+            list.nodes
+                .iter()
+                .map(|node| node.next.len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(build(), build());
+        assert!(SkipList::<i32>::new().is_empty());
+    }
+}
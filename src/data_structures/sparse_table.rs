@@ -0,0 +1,275 @@
+/// A sparse table answers range queries over an immutable array in O(1),
+/// after O(n log n) preprocessing, for any idempotent operation such as
+/// min, max or gcd, where combining a value with itself changes nothing.
+///
+/// table[k][i] holds the fold of arr[i . . . i + 2^k - 1], and any range
+/// [l, r] is covered by two, possibly overlapping, ranges of length 2^k.
+pub struct SparseTable<T: Clone> {
+    table: Vec<Vec<T>>,
+    combine: fn(&T, &T) -> T,
+}
+
+impl<T: Clone> SparseTable<T> {
+    /// build the table from an array of values and an idempotent combine
+    pub fn from_vec(values: &[T], combine: fn(&T, &T) -> T) -> Self {
+        let mut table = vec![values.to_vec()];
+        let mut k = 1;
+        while (1 << k) <= values.len() {
+            let prev = &table[k - 1];
+            let half = 1 << (k - 1);
+            let level = (0..=values.len() - (1 << k))
+                .map(|i| combine(&prev[i], &prev[i + half]))
+                .collect();
+            table.push(level);
+            k += 1;
+        }
+        SparseTable { table, combine }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.table[0].len()
+    }
+
+    /// return true if the table has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// return the fold of arr[l . . . r] inclusive
+    pub fn query(&self, l: usize, r: usize) -> T {
+        assert!(l <= r && r < self.len(), "invalid range");
+        // the largest k with 2^k <= r - l + 1
+        let k = (usize::BITS - 1 - (r - l + 1).leading_zeros()) as usize;
+        (self.combine)(&self.table[k][l], &self.table[k][r + 1 - (1 << k)])
+    }
+}
+
+impl<T: Ord + Clone> SparseTable<T> {
+    /// build a table answering range minimum queries
+    pub fn min_table(values: &[T]) -> Self {
+        SparseTable::from_vec(values, |a, b| a.min(b).clone())
+    }
+
+    /// build a table answering range maximum queries
+    pub fn max_table(values: &[T]) -> Self {
+        SparseTable::from_vec(values, |a, b| a.max(b).clone())
+    }
+}
+
+/// A range minimum structure for arrays where adjacent elements differ by
+/// exactly 1, such as the depths of an Euler tour of a tree, answering:
+/// 1. given l, r, the minimum of arr[l . . . r]
+/// 2. given l, r, the leftmost index of that minimum
+///
+/// in O(1) after O(n) preprocessing, which is what makes the lowest
+/// common ancestor of two tree nodes an O(1) query. The array is cut into
+/// blocks of b = log(n) / 2 elements. A sparse table over the n / b block
+/// minima answers the whole blocks of a query in O(n) space. Within a
+/// block only the b - 1 up or down steps matter, so there are at most
+/// 2^(b - 1) = O(sqrt(n)) kinds of blocks, and the answer of every
+/// in-block range is precomputed for each kind.
+pub struct PlusMinusOneRmq {
+    values: Vec<i64>,
+    block: usize,
+    // (min, index) of every block
+    block_min: SparseTable<(i64, usize)>,
+    // kind of every block, bit k set if the block steps up after offset k
+    kinds: Vec<usize>,
+    // in_block[(kind * block + l) * block + r] is the offset of the
+    // leftmost minimum of offsets [l, r] in a block of that kind
+    in_block: Vec<u8>,
+}
+
+impl PlusMinusOneRmq {
+    /// build the structure from an array whose adjacent elements
+    /// differ by exactly 1
+    pub fn from_vec(values: &[i64]) -> Self {
+        assert!(
+            values.windows(2).all(|w| (w[1] - w[0]).abs() == 1),
+            "adjacent elements must differ by exactly 1"
+        );
+        let n = values.len();
+        let log = (usize::BITS - n.max(1).leading_zeros()) as usize;
+        let block = (log / 2).max(1);
+
+        let kinds: Vec<usize> = (0..n)
+            .step_by(block)
+            .map(|start| {
+                // steps past the end of the array count as up
+                (0..block - 1).fold(0, |kind, k| {
+                    let i = start + k;
+                    if i + 1 >= n || values[i + 1] > values[i] {
+                        kind | 1 << k
+                    } else {
+                        kind
+                    }
+                })
+            })
+            .collect();
+        let minima: Vec<(i64, usize)> = (0..n)
+            .step_by(block)
+            .map(|start| {
+                (start..n.min(start + block))
+                    .map(|i| (values[i], i))
+                    .min()
+                    .unwrap()
+            })
+            .collect();
+
+        let kind_count = 1 << (block - 1);
+        let mut in_block = vec![0; kind_count * block * block];
+        for kind in 0..kind_count {
+            // heights of the block relative to its first element
+            let mut height = vec![0i64; block];
+            for k in 1..block {
+                let step = if kind >> (k - 1) & 1 == 1 { 1 } else { -1 };
+                height[k] = height[k - 1] + step;
+            }
+            for l in 0..block {
+                let mut best = l;
+                for r in l..block {
+                    if height[r] < height[best] {
+                        best = r;
+                    }
+                    in_block[(kind * block + l) * block + r] = best as u8;
+                }
+            }
+        }
+
+        PlusMinusOneRmq {
+            values: values.to_vec(),
+            block,
+            block_min: SparseTable::min_table(&minima),
+            kinds,
+            in_block,
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// return true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // leftmost index of the minimum of offsets [l, r] of block b
+    fn in_block_argmin(&self, b: usize, l: usize, r: usize) -> usize {
+        let kind = self.kinds[b];
+        let offset = self.in_block[(kind * self.block + l) * self.block + r];
+        b * self.block + offset as usize
+    }
+
+    /// return the leftmost index of the minimum of arr[l . . . r] inclusive
+    pub fn argmin(&self, l: usize, r: usize) -> usize {
+        assert!(l <= r && r < self.len(), "invalid range");
+        let (bl, br) = (l / self.block, r / self.block);
+        if bl == br {
+            return self.in_block_argmin(bl, l % self.block, r % self.block);
+        }
+        let left = self.in_block_argmin(bl, l % self.block, self.block - 1);
+        let right = self.in_block_argmin(br, 0, r % self.block);
+        let mut best = (self.values[left], left);
+        if bl + 1 < br {
+            best = best.min(self.block_min.query(bl + 1, br - 1));
+        }
+        best.min((self.values[right], right)).1
+    }
+
+    /// return the minimum of arr[l . . . r] inclusive
+    pub fn query(&self, l: usize, r: usize) -> i64 {
+        self.values[self.argmin(l, r)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_min_max() {
+        let mut rng = PCG32::new_default(577215);
+        for &n in &[1usize, 2, 3, 8, 37] {
+            let values: Vec<i32> = (0..n).map(|_| rng.get_u32() as i32 % 1000).collect();
+            let min_table = SparseTable::min_table(&values);
+            let max_table = SparseTable::max_table(&values);
+            for l in 0..n {
+                for r in l..n {
+                    assert_eq!(*values[l..=r].iter().min().unwrap(), min_table.query(l, r));
+                    assert_eq!(*values[l..=r].iter().max().unwrap(), max_table.query(l, r));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_combine() {
+        fn gcd(a: &u64, b: &u64) -> u64 {
+            if *b == 0 {
+                *a
+            } else {
+                gcd(b, &(a % b))
+            }
+        }
+        let values = [12, 18, 24, 30, 7, 14];
+        let table = SparseTable::from_vec(&values, gcd);
+        assert_eq!(6, table.query(0, 3));
+        assert_eq!(1, table.query(3, 4));
+        assert_eq!(7, table.query(4, 5));
+        assert_eq!(24, table.query(2, 2));
+        assert_eq!(6, table.len());
+    }
+
+    #[test]
+    fn test_plus_minus_one_rmq() {
+        let mut rng = PCG32::new_default(141421);
+        for &n in &[1usize, 2, 3, 4, 15, 16, 17, 100, 257] {
+            // a random walk starting at 0
+            let mut values = vec![0i64];
+            while values.len() < n {
+                let step = if rng.get_u32().is_multiple_of(2) {
+                    1
+                } else {
+                    -1
+                };
+                values.push(values[values.len() - 1] + step);
+            }
+            let rmq = PlusMinusOneRmq::from_vec(&values);
+            assert_eq!(n, rmq.len());
+            for l in 0..n {
+                let mut best = l;
+                for r in l..n {
+                    if values[r] < values[best] {
+                        best = r;
+                    }
+                    assert_eq!(best, rmq.argmin(l, r));
+                    assert_eq!(values[best], rmq.query(l, r));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_euler_tour_depths() {
+        // depths of the Euler tour 0 1 3 1 4 1 0 2 0 of the tree with
+        // edges 0-1, 0-2, 1-3, 1-4
+        let tour = [0, 1, 3, 1, 4, 1, 0, 2, 0];
+        let depths = [0, 1, 2, 1, 2, 1, 0, 1, 0];
+        let rmq = PlusMinusOneRmq::from_vec(&depths);
+        // the lowest common ancestor of 3 and 4 is 1, of 4 and 2 is 0
+        assert_eq!(1, tour[rmq.argmin(2, 4)]);
+        assert_eq!(0, tour[rmq.argmin(4, 7)]);
+        assert_eq!(1, tour[rmq.argmin(1, 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "adjacent elements must differ by exactly 1")]
+    fn test_plus_minus_one_rmq_invalid() {
+        PlusMinusOneRmq::from_vec(&[0, 1, 3]);
+    }
+}
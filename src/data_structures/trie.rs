@@ -5,6 +5,8 @@ use std::hash::Hash;
 struct Node<Key: Default, Type: Default> {
     children: HashMap<Key, Node<Key, Type>>,
     value: Option<Type>,
+    // number of keys stored at or below this node
+    count: usize,
 }
 
 #[derive(Debug, Default)]
@@ -31,13 +33,41 @@ where
     where
         Key: Eq + Hash,
     {
+        let key: Vec<Key> = key.into_iter().collect();
+        if let Some(node) = Self::find_mut(&mut self.root, &key) {
+            if node.value.is_some() {
+                // overwriting a value doesn't change any count
+                node.value = Some(value);
+                return;
+            }
+        }
         let mut node = &mut self.root;
-        for c in key.into_iter() {
-            node = node.children.entry(c).or_insert_with(Node::default);
+        node.count += 1;
+        for c in key {
+            node = node.children.entry(c).or_default();
+            node.count += 1;
         }
         node.value = Some(value);
     }
 
+    fn find_mut<'a>(
+        mut node: &'a mut Node<Key, Type>,
+        key: &[Key],
+    ) -> Option<&'a mut Node<Key, Type>> {
+        for c in key {
+            node = node.children.get_mut(c)?;
+        }
+        Some(node)
+    }
+
+    fn find(&self, key: impl IntoIterator<Item = Key>) -> Option<&Node<Key, Type>> {
+        let mut node = &self.root;
+        for c in key.into_iter() {
+            node = node.children.get(&c)?;
+        }
+        Some(node)
+    }
+
     pub fn get(&self, key: impl IntoIterator<Item = Key>) -> Option<&Type>
     where
         Key: Eq + Hash,
@@ -52,6 +82,21 @@ where
         }
         node.value.as_ref()
     }
+
+    /// return true if key has been inserted
+    pub fn contains(&self, key: impl IntoIterator<Item = Key>) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// return true if some inserted key starts with prefix
+    pub fn starts_with(&self, prefix: impl IntoIterator<Item = Key>) -> bool {
+        self.count_prefix(prefix) > 0
+    }
+
+    /// return the number of inserted keys that start with prefix
+    pub fn count_prefix(&self, prefix: impl IntoIterator<Item = Key>) -> usize {
+        self.find(prefix).map_or(0, |node| node.count)
+    }
 }
 
 #[cfg(test)]
@@ -94,4 +139,37 @@ mod tests {
         assert_eq!(trie.get(vec![42, 6, 1000]), Some(&3));
         assert_eq!(trie.get(vec![43, 44, 45]), None);
     }
+
+    #[test]
+    fn test_prefixes() {
+        let mut trie = Trie::new();
+        trie.insert("app".chars(), 1);
+        trie.insert("apple".chars(), 2);
+        trie.insert("apply".chars(), 3);
+        trie.insert("bat".chars(), 4);
+
+        assert!(trie.contains("app".chars()));
+        assert!(trie.contains("apple".chars()));
+        assert!(!trie.contains("ap".chars()));
+        assert!(!trie.contains("applesauce".chars()));
+
+        assert!(trie.starts_with("ap".chars()));
+        assert!(trie.starts_with("apple".chars()));
+        assert!(!trie.starts_with("bx".chars()));
+        assert!(!trie.starts_with("c".chars()));
+
+        assert_eq!(4, trie.count_prefix("".chars()));
+        assert_eq!(3, trie.count_prefix("a".chars()));
+        assert_eq!(3, trie.count_prefix("app".chars()));
+        assert_eq!(2, trie.count_prefix("appl".chars()));
+        assert_eq!(1, trie.count_prefix("apply".chars()));
+        assert_eq!(1, trie.count_prefix("b".chars()));
+        assert_eq!(0, trie.count_prefix("apples".chars()));
+
+        // inserting an existing key again replaces its value only
+        trie.insert("apple".chars(), 5);
+        assert_eq!(Some(&5), trie.get("apple".chars()));
+        assert_eq!(3, trie.count_prefix("app".chars()));
+        assert_eq!(4, trie.count_prefix("".chars()));
+    }
 }
@@ -0,0 +1,329 @@
+/// Union-Find (disjoint set union) over the elements 0..n.
+///
+/// `find` compresses the path it walks, and `union` attaches the root
+/// of the smaller component below the root of the larger one, so any
+/// sequence of operations takes amortized near-constant time per operation.
+pub struct UnionFind {
+    // parent[x] == x for the root of each component
+    parent: Vec<usize>,
+    // size[r] is the number of elements in the component rooted at r,
+    // only meaningful for roots
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    /// create n singleton components 0..n
+    pub fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// return true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// return the root of the component containing x
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // point every node on the path directly at the root
+        let mut node = x;
+        while node != root {
+            let next = self.parent[node];
+            self.parent[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    /// merge the components containing x and y, returning
+    /// false if they were already the same component
+    pub fn union(&mut self, x: usize, y: usize) -> bool {
+        let mut a = self.find(x);
+        let mut b = self.find(y);
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+
+    /// return true if x and y are in the same component
+    pub fn connected(&mut self, x: usize, y: usize) -> bool {
+        self.find(x) == self.find(y)
+    }
+
+    /// return the number of elements in the component containing x
+    pub fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+}
+
+/// Union-Find over the elements 0..n whose unions can be undone.
+///
+/// Unions are by rank and `find` never compresses paths, so every union
+/// changes at most two entries and the depth of any tree stays below
+/// log2(n). Each change is recorded on a stack, and `rollback` pops the
+/// stack to revert every union made after a `snapshot`, as needed by
+/// offline dynamic connectivity.
+pub struct RollbackUnionFind {
+    // parent[x] == x for the root of each component
+    parent: Vec<usize>,
+    // upper bound on the height of the tree rooted at r, only
+    // meaningful for roots
+    rank: Vec<usize>,
+    // every modification made by union, most recent last
+    history: Vec<Change>,
+}
+
+// an entry overwritten by union, along with its previous value
+enum Change {
+    Parent { node: usize, old: usize },
+    Rank { node: usize, old: usize },
+}
+
+impl RollbackUnionFind {
+    /// create n singleton components 0..n
+    pub fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            rank: vec![0; n],
+            history: Vec::new(),
+        }
+    }
+
+    /// return the number of elements
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// return true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// return the root of the component containing x
+    pub fn find(&self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        root
+    }
+
+    /// merge the components containing x and y, returning
+    /// false if they were already the same component
+    pub fn union(&mut self, x: usize, y: usize) -> bool {
+        let mut a = self.find(x);
+        let mut b = self.find(y);
+        if a == b {
+            return false;
+        }
+        if self.rank[a] < self.rank[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.history.push(Change::Parent {
+            node: b,
+            old: self.parent[b],
+        });
+        self.parent[b] = a;
+        if self.rank[a] == self.rank[b] {
+            self.history.push(Change::Rank {
+                node: a,
+                old: self.rank[a],
+            });
+            self.rank[a] += 1;
+        }
+        true
+    }
+
+    /// return true if x and y are in the same component
+    pub fn connected(&self, x: usize, y: usize) -> bool {
+        self.find(x) == self.find(y)
+    }
+
+    /// return a handle to the current state, to be passed to `rollback`
+    pub fn snapshot(&self) -> usize {
+        self.history.len()
+    }
+
+    /// undo every union performed since the snapshot to was taken.
+    /// Panics if to is newer than the current state, which happens
+    /// when rolling back to a snapshot that was already rolled past.
+    pub fn rollback(&mut self, to: usize) {
+        assert!(
+            to <= self.history.len(),
+            "snapshot is newer than current state"
+        );
+        while self.history.len() > to {
+            match self.history.pop().unwrap() {
+                Change::Parent { node, old } => self.parent[node] = old,
+                Change::Rank { node, old } => self.rank[node] = old,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_union_and_connected() {
+        let mut uf = UnionFind::new(8);
+        assert_eq!(8, uf.len());
+        for i in 0..8 {
+            assert_eq!(1, uf.size(i));
+            assert!(uf.connected(i, i));
+        }
+
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert!(uf.connected(1, 0));
+        assert!(!uf.connected(1, 2));
+        assert_eq!(2, uf.size(0));
+        assert_eq!(2, uf.size(3));
+
+        assert!(uf.union(1, 3));
+        // already in the same component
+        assert!(!uf.union(0, 2));
+        assert!(uf.connected(0, 3));
+        assert_eq!(4, uf.size(2));
+
+        assert!(uf.union(5, 6));
+        assert!(uf.union(7, 6));
+        assert_eq!(3, uf.size(5));
+        assert_eq!(1, uf.size(4));
+        assert!(!uf.connected(4, 5));
+        assert!(!uf.connected(0, 7));
+
+        assert!(uf.union(4, 0));
+        assert!(uf.union(6, 4));
+        for i in 0..8 {
+            assert_eq!(8, uf.size(i));
+            assert!(uf.connected(0, i));
+        }
+    }
+
+    fn depth(uf: &UnionFind, mut x: usize) -> usize {
+        let mut depth = 0;
+        while uf.parent[x] != x {
+            x = uf.parent[x];
+            depth += 1;
+        }
+        depth
+    }
+
+    #[test]
+    fn test_find_compresses_paths() {
+        let n = 1 << 12;
+        let mut uf = UnionFind::new(n);
+        // merge equal sized components pairwise, the worst case
+        // for union by size without path compression
+        let mut step = 1;
+        while step < n {
+            for i in (0..n).step_by(2 * step) {
+                // link the roots directly so no path is compressed yet
+                let (a, b) = (uf.parent[i], uf.parent[i + step]);
+                assert!(uf.union(a, b));
+            }
+            step *= 2;
+        }
+        let deepest = (0..n).max_by_key(|&x| depth(&uf, x)).unwrap();
+        // union by size bounds the depth by log2(n)
+        assert!(depth(&uf, deepest) <= 12);
+
+        let root = uf.find(deepest);
+        assert_eq!(1, depth(&uf, deepest));
+        for x in 0..n {
+            assert_eq!(root, uf.find(x));
+        }
+        for x in 0..n {
+            assert!(depth(&uf, x) <= 1);
+        }
+        assert_eq!(n, uf.size(0));
+    }
+
+    fn components(uf: &RollbackUnionFind) -> Vec<Vec<bool>> {
+        (0..uf.len())
+            .map(|x| (0..uf.len()).map(|y| uf.connected(x, y)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut uf = RollbackUnionFind::new(8);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert!(uf.union(1, 3));
+        let snapshot = uf.snapshot();
+        let expected = components(&uf);
+
+        assert!(uf.union(4, 5));
+        assert!(uf.union(5, 0));
+        assert!(!uf.union(4, 2));
+        assert!(uf.union(6, 7));
+        assert!(uf.connected(4, 3));
+        assert!(uf.connected(6, 7));
+
+        uf.rollback(snapshot);
+        assert_eq!(expected, components(&uf));
+        assert!(uf.connected(0, 3));
+        assert!(!uf.connected(4, 5));
+        assert!(!uf.connected(6, 7));
+
+        // rolling back to an older snapshot after newer unions
+        let inner = uf.snapshot();
+        assert!(uf.union(3, 7));
+        uf.rollback(inner);
+        uf.rollback(0);
+        for x in 0..8 {
+            assert_eq!(x, uf.find(x));
+        }
+    }
+
+    #[test]
+    fn test_rollback_depth() {
+        let n = 1 << 10;
+        let mut uf = RollbackUnionFind::new(n);
+        for x in 1..n {
+            uf.union(x - 1, x);
+        }
+        // union by rank keeps trees shallow without path compression
+        let depth = |uf: &RollbackUnionFind, mut x: usize| {
+            let mut depth = 0;
+            while uf.parent[x] != x {
+                x = uf.parent[x];
+                depth += 1;
+            }
+            depth
+        };
+        assert!((0..n).all(|x| depth(&uf, x) <= 10));
+    }
+
+    #[test]
+    #[should_panic(expected = "snapshot is newer than current state")]
+    fn test_rollback_to_stale_snapshot() {
+        let mut uf = RollbackUnionFind::new(4);
+        uf.union(0, 1);
+        let snapshot = uf.snapshot();
+        uf.rollback(0);
+        uf.rollback(snapshot);
+    }
+}
@@ -0,0 +1,188 @@
+use super::segment_tree_sum::check_range;
+
+/// A wavelet tree over an immutable array of i32. It supports:
+/// 1. given an index l, r and k, find the k-th smallest element
+///    in range [l, r]
+/// 2. given an index l, r and a value x, count the elements in
+///    range [l, r] that are <= x
+///
+/// both in O(log(max - min)). Each node splits its value range in half
+/// and records, for every prefix of its elements, how many go to the
+/// lower half. That turns a position range in a node into the matching
+/// position ranges in its children with two lookups, so queries walk a
+/// single root-to-leaf path. The tree takes O(n log(max - min)) memory,
+/// one count per element per level.
+pub struct WaveletTree {
+    len: usize,
+    root: Option<Box<Node>>,
+}
+
+struct Node {
+    // the node covers elements with values in [lo, hi]
+    lo: i64,
+    hi: i64,
+    // to_left[k] is the number of the first k elements of this
+    // node with a value in the lower half [lo, mid]
+    to_left: Vec<usize>,
+    left: Option<Box<Node>>,
+    right: Option<Box<Node>>,
+}
+
+impl Node {
+    fn build(values: Vec<i64>, lo: i64, hi: i64) -> Option<Box<Node>> {
+        if values.is_empty() {
+            return None;
+        }
+        if lo == hi {
+            return Some(Box::new(Node {
+                lo,
+                hi,
+                to_left: Vec::new(),
+                left: None,
+                right: None,
+            }));
+        }
+        let mid = lo + (hi - lo) / 2;
+        let mut to_left = Vec::with_capacity(values.len() + 1);
+        to_left.push(0);
+        for &v in &values {
+            to_left.push(to_left.last().unwrap() + (v <= mid) as usize);
+        }
+        // stable partition keeps the original order within each half
+        let (lower, upper): (Vec<i64>, Vec<i64>) = values.into_iter().partition(|&v| v <= mid);
+        Some(Box::new(Node {
+            lo,
+            hi,
+            to_left,
+            left: Node::build(lower, lo, mid),
+            right: Node::build(upper, mid + 1, hi),
+        }))
+    }
+}
+
+impl WaveletTree {
+    /// build tree from an array of values
+    pub fn from_vec(values: &[i32]) -> Self {
+        let values: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+        let lo = values.iter().cloned().min().unwrap_or(0);
+        let hi = values.iter().cloned().max().unwrap_or(0);
+        Self {
+            len: values.len(),
+            root: Node::build(values, lo, hi),
+        }
+    }
+
+    /// return the k-th smallest element of array[l]..array[r] inclusive,
+    /// where k = 1 is the smallest
+    pub fn kth_smallest(&self, l: usize, r: usize, k: usize) -> i32 {
+        check_range(l, r, self.len).expect("query range out of bounds");
+        assert!(1 <= k && k <= r - l + 1, "k out of range");
+        let mut node = self.root.as_ref().unwrap();
+        // the node's elements at positions [a, b) are the ones in range
+        let (mut a, mut b, mut k) = (l - 1, r, k);
+        while node.lo != node.hi {
+            let (left_a, left_b) = (node.to_left[a], node.to_left[b]);
+            if k <= left_b - left_a {
+                a = left_a;
+                b = left_b;
+                node = node.left.as_ref().unwrap();
+            } else {
+                k -= left_b - left_a;
+                a -= left_a;
+                b -= left_b;
+                node = node.right.as_ref().unwrap();
+            }
+        }
+        node.lo as i32
+    }
+
+    /// return the number of elements of array[l]..array[r] inclusive
+    /// that are less than or equal to x
+    pub fn rank(&self, l: usize, r: usize, x: i32) -> usize {
+        check_range(l, r, self.len).expect("query range out of bounds");
+        let x = x as i64;
+        let (mut a, mut b) = (l - 1, r);
+        let mut count = 0;
+        let mut node = self.root.as_ref();
+        while let Some(current) = node {
+            if a == b || x < current.lo {
+                break;
+            }
+            if x >= current.hi {
+                count += b - a;
+                break;
+            }
+            let mid = current.lo + (current.hi - current.lo) / 2;
+            let (left_a, left_b) = (current.to_left[a], current.to_left[b]);
+            if x <= mid {
+                a = left_a;
+                b = left_b;
+                node = current.left.as_ref();
+            } else {
+                // everything in the lower half is <= x
+                count += left_b - left_a;
+                a -= left_a;
+                b -= left_b;
+                node = current.right.as_ref();
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use math::PCG32;
+
+    #[test]
+    fn test_query() {
+        let values = [5, 1, 4, 1, 5, 9, 2, 6];
+        let tree = WaveletTree::from_vec(&values);
+        assert_eq!(2, tree.rank(1, 8, 1));
+        assert_eq!(6, tree.rank(1, 8, 5));
+        assert_eq!(0, tree.rank(6, 6, 8));
+        assert_eq!(1, tree.rank(6, 6, 9));
+        assert_eq!(0, tree.rank(1, 8, 0));
+        assert_eq!(8, tree.rank(1, 8, 100));
+        assert_eq!(1, tree.kth_smallest(1, 8, 1));
+        assert_eq!(1, tree.kth_smallest(1, 8, 2));
+        assert_eq!(2, tree.kth_smallest(1, 8, 3));
+        assert_eq!(9, tree.kth_smallest(1, 8, 8));
+        assert_eq!(5, tree.kth_smallest(3, 6, 3));
+    }
+
+    #[test]
+    fn test_extreme_values() {
+        let values = [i32::MAX, i32::MIN, 0, i32::MIN, -1];
+        let tree = WaveletTree::from_vec(&values);
+        assert_eq!(i32::MIN, tree.kth_smallest(1, 5, 2));
+        assert_eq!(-1, tree.kth_smallest(1, 5, 3));
+        assert_eq!(i32::MAX, tree.kth_smallest(1, 5, 5));
+        assert_eq!(4, tree.rank(1, 5, 0));
+        assert_eq!(5, tree.rank(1, 5, i32::MAX));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = PCG32::new_default(577215);
+        for &n in &[1usize, 2, 9, 50] {
+            let values: Vec<i32> = (0..n).map(|_| (rng.get_u32() % 20) as i32 - 10).collect();
+            let tree = WaveletTree::from_vec(&values);
+            for _ in 0..200 {
+                let a = rng.get_u32() as usize % n + 1;
+                let b = rng.get_u32() as usize % n + 1;
+                let (l, r) = (a.min(b), a.max(b));
+                let x = (rng.get_u32() % 24) as i32 - 12;
+                let expected = values[l - 1..r].iter().filter(|&&v| v <= x).count();
+                assert_eq!(expected, tree.rank(l, r, x));
+
+                let mut sorted = values[l - 1..r].to_vec();
+                sorted.sort_unstable();
+                let k = rng.get_u32() as usize % (r - l + 1) + 1;
+                assert_eq!(sorted[k - 1], tree.kth_smallest(l, r, k));
+            }
+        }
+    }
+}
@@ -1,14 +1,34 @@
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
+// no_std crates get core in scope automatically
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(test)]
+extern crate proptest;
+#[cfg(feature = "serde")]
+extern crate serde;
+
+#[cfg(feature = "std")]
 pub mod ciphers;
 pub mod data_structures;
+#[cfg(feature = "std")]
 pub mod dynamic_programming;
+#[cfg(feature = "std")]
 pub mod general;
+#[cfg(feature = "std")]
 pub mod graph;
+#[cfg(feature = "std")]
 pub mod math;
+#[cfg(feature = "std")]
 pub mod searching;
+#[cfg(feature = "std")]
 pub mod sorting;
+#[cfg(feature = "std")]
 pub mod string;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use sorting;
     #[test]
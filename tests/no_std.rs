@@ -0,0 +1,31 @@
+// Smoke test for the library built without the standard library:
+//
+//     cargo test --no-default-features --test no_std
+//
+// The test crate itself is no_std too, so the tree has to be usable with
+// nothing but core and alloc in scope.
+#![no_std]
+
+extern crate alloc;
+extern crate the_algorithms_rust;
+
+use alloc::vec;
+use the_algorithms_rust::data_structures::{RangeSumSegmentTree, StaticSegmentTree};
+
+#[test]
+fn test_query_without_std() {
+    let values = vec![1, 2, 3, 4, 5, 6];
+    let mut seg_tree = RangeSumSegmentTree::<i32>::from_vec(&values);
+    assert_eq!(21, seg_tree.query(1, 6));
+    seg_tree.update(2, 4, 1);
+    assert_eq!(vec![1, 3, 4, 5, 5, 6], seg_tree.to_vec());
+    assert_eq!(12, seg_tree.query(2, 4));
+}
+
+#[test]
+fn test_static_tree_without_std() {
+    let mut seg_tree = StaticSegmentTree::<16>::from_slice(&[1, 2, 3, 4, 5, 6]).unwrap();
+    assert_eq!(21, seg_tree.query(1, 6));
+    seg_tree.update(2, 4, 1);
+    assert_eq!(12, seg_tree.query(2, 4));
+}
@@ -0,0 +1,40 @@
+// Round trip of the serde support through JSON, run with
+//
+//     cargo test --features serde --test serde
+#![cfg(feature = "serde")]
+
+extern crate serde_json;
+extern crate the_algorithms_rust;
+
+use serde_json::Value;
+use the_algorithms_rust::data_structures::RangeSumSegmentTree;
+
+#[test]
+fn test_serde_round_trip() {
+    let values = [1, 2, 3, 4, 5, 6];
+    let mut seg_tree = RangeSumSegmentTree::<i64>::from_vec(&values);
+    // leave a pending mark in the serialized tree
+    seg_tree.update(1, 6, 1);
+
+    let json = serde_json::to_string(&seg_tree).unwrap();
+    let mut restored: RangeSumSegmentTree<i64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(seg_tree.to_vec(), restored.to_vec());
+    assert_eq!(27, restored.query(1, 6));
+    assert_eq!(15, restored.query(3, 5));
+}
+
+#[test]
+fn test_serde_rejects_malformed_tree() {
+    let seg_tree = RangeSumSegmentTree::<i64>::from_vec(&[1, 2, 3, 4, 5, 6]);
+    let value = serde_json::to_value(&seg_tree).unwrap();
+
+    // drop the last element of mark
+    let mut truncated = value.clone();
+    truncated["mark"].as_array_mut().unwrap().pop();
+    assert!(serde_json::from_value::<RangeSumSegmentTree<i64>>(truncated).is_err());
+
+    // claim more elements than arr has room for
+    let mut too_long = value;
+    too_long["len"] = Value::from(100);
+    assert!(serde_json::from_value::<RangeSumSegmentTree<i64>>(too_long).is_err());
+}